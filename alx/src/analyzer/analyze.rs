@@ -1,22 +1,31 @@
-use super::scanners::scan_setup;
+use super::{
+    diagnostic::{report, Diagnostic, Diagnostics, Severity},
+    scanners::scan_setup,
+    util::join_path,
+};
 use crate::{
     analyzer::scanners::{scan_data, scan_handlers},
     config::{ConfigFormat, Data, Endpoint, Handler, ProjectConfig, Route, RouteHandler},
     error::AlxError,
     print,
+    settings::settings,
 };
 use clap::Args;
+use proc_macro2::Span;
 use std::{
     collections::HashMap,
     fs::{self, DirEntry},
     path::Path,
 };
+use syn::{spanned::Spanned, visit::Visit};
 
 #[derive(Debug)]
 pub struct ScanResult {
     pub handlers: HashMap<String, Vec<Handler>>,
     pub routes: HashMap<String, Vec<Route>>,
     pub data: HashMap<String, Vec<Data>>,
+    /// What the scanners skipped and why
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 #[derive(Debug)]
@@ -26,6 +35,10 @@ pub enum FileScanResult {
     Data(Vec<Data>),
 }
 
+/// Scans a file of the type, collecting what it had to skip
+pub type ScanFile =
+    dyn Fn(&DirEntry, AlxFileType, &mut Vec<Diagnostic>) -> Result<FileScanResult, AlxError>;
+
 #[derive(Debug, Clone)]
 pub enum AlxFileType {
     Setup,
@@ -37,7 +50,7 @@ pub enum AlxFileType {
 #[derive(Debug, Args)]
 pub struct AnalyzeOptions {
     /// Accepted values are "json" | "j" for JSON, "yaml" | "y" for Yaml.
-    /// Creates both by default.
+    /// Creates both by default, or the format in alx.toml.
     #[arg(short, long)]
     pub format: Option<String>,
     /// Print what's going on to std out
@@ -46,29 +59,65 @@ pub struct AnalyzeOptions {
     /// Specify the path to read from.
     #[arg(short, long)]
     pub path: Option<String>,
+    /// Exit with a non-zero code on warnings as well as errors
+    #[arg(long, action)]
+    pub strict: bool,
 }
 
-/// Analyzes the router directory recursively and extracts routing info. Assembles the ProjectConfig struct
-/// after it calling the scanners to do their thing.
-pub fn handle(opts: AnalyzeOptions, api_path: &str) {
+/// Analyzes the router directory recursively and extracts routing info. Writes the assembled
+/// ProjectConfig to the lock file(s) even if some files couldn't be analyzed. Returns true if
+/// there are errors, or warnings with `--strict`.
+pub fn handle(opts: AnalyzeOptions, api_path: &str) -> Result<bool, AlxError> {
     let format = match opts.format {
         Some(f) => match f.as_str() {
             "json" | "j" => ConfigFormat::Json,
             "yaml" | "y" => ConfigFormat::Yaml,
             _ => ConfigFormat::Both,
         },
-        None => ConfigFormat::Both,
+        None => settings().lock.format,
     };
+    let (pc, diagnostics) = analyze_api(api_path)?;
+    report(&diagnostics);
+    let dir = Path::new(&settings().lock.dir);
+    println!(
+        "Writing {}",
+        dir.join(format!("alx_lock{format}")).display()
+    );
+    pc.write_config_lock(format, dir)?;
+    Ok(diagnostics
+        .iter()
+        .any(|d| opts.strict || d.severity == Severity::Error))
+}
+
+/// Runs the scanners on the router directory of the API
+pub fn scan(api_path: &str) -> Result<ScanResult, AlxError> {
     let mut scan = ScanResult {
         handlers: HashMap::new(),
         routes: HashMap::new(),
         data: HashMap::new(),
+        diagnostics: vec![],
     };
-    let path = format!("{}/router", api_path);
-    router_read_recursive(Path::new(&path), &mut scan, &analyze, None).unwrap();
+    let path = settings().paths.router_of(api_path);
+    router_read_recursive(Path::new(&path), &mut scan, &analyze, None)?;
+    Ok(scan)
+}
+
+/// Assembles the ProjectConfig struct from the API directory after calling the scanners to do their
+/// thing. What they had to skip is reported, see [analyze_api] to handle it instead.
+pub fn project_config(api_path: &str) -> Result<ProjectConfig, AlxError> {
+    let (pc, diagnostics) = analyze_api(api_path)?;
+    report(&diagnostics);
+    Ok(pc)
+}
+
+/// The ProjectConfig of the API directory along with what the scanners had to skip
+pub fn analyze_api(api_path: &str) -> Result<(ProjectConfig, Vec<Diagnostic>), AlxError> {
+    let scan = scan(api_path)?;
+    let path = settings().paths.router_of(api_path);
 
     let mut pc = ProjectConfig::default();
-    for ep_name in scan.routes.keys() {
+    let mut diagnostics = scan.diagnostics;
+    for (ep_name, routes) in scan.routes.iter() {
         // Grab the endpoint name
         let file_path = format!("{}/{}", path, ep_name);
         // Get the handlers under the current path
@@ -86,14 +135,16 @@ pub fn handle(opts: AnalyzeOptions, api_path: &str) {
         };
 
         // Get the routes
-        let routes = scan.routes.get(ep_name).expect("Impossible!");
+        let routes = resolve_macro_routes(routes, handlers);
+        let mut missing = vec![];
         let mut ep = Endpoint {
             name: ep_name.to_string(),
             full_path: file_path.to_string(),
             routes: vec![],
+            contracts: None,
         };
 
-        for route in routes {
+        for route in routes.iter() {
             // Get the handler associated with the route name
             let mut handler = handlers
                 .iter()
@@ -117,21 +168,111 @@ pub fn handle(opts: AnalyzeOptions, api_path: &str) {
 
             let handler = handler.pop();
             let data = data.pop();
+            if handler.is_none() && !route.handler_name.is_empty() {
+                missing.push(route.handler_name.as_str());
+            }
 
             let rh = RouteHandler {
                 method: route.method.clone(),
                 path: route.path.clone(),
                 handler: handler.cloned(),
                 middleware: route.middleware.clone(),
+                guards: route.guards.clone(),
                 service: route.service.clone(),
                 input: data.cloned(),
             };
             ep.routes.push(rh);
         }
+        if !missing.is_empty() {
+            let setup = Path::new(&file_path).join("setup.rs");
+            diagnostics.extend(unresolved_handlers(&setup, &missing)?);
+        }
         pc.endpoints.push(ep);
     }
-    println!("Writing alx_lock{format}");
-    pc.write_config_lock(format).unwrap();
+    // Keep the output stable between runs
+    pc.endpoints.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((pc, diagnostics))
+}
+
+/// Warnings for the handlers the setup routes to that aren't in handler.rs, pointing at where the
+/// setup names them
+fn unresolved_handlers(setup: &Path, names: &[&str]) -> Result<Vec<Diagnostic>, AlxError> {
+    struct Refs<'a> {
+        names: &'a [&'a str],
+        found: Vec<(String, Span)>,
+    }
+
+    impl<'ast> Visit<'ast> for Refs<'_> {
+        fn visit_expr_path(&mut self, p: &'ast syn::ExprPath) {
+            if let Some(last) = p.path.segments.last() {
+                let name = last.ident.to_string();
+                if self.names.contains(&name.as_str())
+                    && !self.found.iter().any(|(n, _)| *n == name)
+                {
+                    self.found.push((name, p.span()));
+                }
+            }
+        }
+    }
+
+    let src = fs::read_to_string(setup)?;
+    let mut refs = Refs {
+        names,
+        found: vec![],
+    };
+    // The setup parsed when it was scanned
+    if let Ok(file) = syn::parse_file(&src) {
+        refs.visit_file(&file);
+    }
+    let mut diagnostics = Diagnostics::new(setup, &src);
+    for (name, span) in refs.found {
+        diagnostics.warn(
+            span,
+            format!("the handler {name} isn't in handler.rs"),
+            "move the handler to the handler.rs of the route to have it documented",
+        );
+    }
+    Ok(diagnostics.into_vec())
+}
+
+/// Services registered by handler name, i.e. `cfg.service(handler::get_thing)`, take their method and path
+/// from the actix route macro on the handler. The path of the route from the setup is the prefix of the
+/// enclosing scopes.
+fn resolve_macro_routes(routes: &[Route], handlers: &[Handler]) -> Vec<Route> {
+    let mut resolved = vec![];
+    for route in routes.iter() {
+        if !route.method.is_empty() {
+            resolved.push(route.clone());
+            continue;
+        }
+        let macro_routes = handlers
+            .iter()
+            .find(|h| h.name == route.handler_name)
+            .map(|h| h.macro_routes.as_slice())
+            .unwrap_or_default();
+        if macro_routes.is_empty() {
+            print(&format!(
+                "Service {} has no route macro, skipping",
+                route.handler_name
+            ));
+        }
+        for mr in macro_routes.iter() {
+            let mut r = mr.clone();
+            r.path = join_path(&route.path, &mr.path);
+            if let Some(ref mw) = route.middleware {
+                r.middleware
+                    .get_or_insert_with(Vec::new)
+                    .extend_from_slice(mw);
+            }
+            if let Some(ref guards) = route.guards {
+                r.guards
+                    .get_or_insert_with(Vec::new)
+                    .extend_from_slice(guards);
+            }
+            resolved.push(r);
+        }
+    }
+    resolved
 }
 
 /// Recursively read the file system at the server router
@@ -140,13 +281,10 @@ pub fn handle(opts: AnalyzeOptions, api_path: &str) {
 pub fn router_read_recursive(
     dir: &Path,
     scan: &mut ScanResult,
-    callback: &dyn Fn(&DirEntry, AlxFileType) -> Result<FileScanResult, AlxError>,
+    callback: &ScanFile,
     dir_type: Option<AlxFileType>,
 ) -> Result<(), AlxError> {
-    print(&format!(
-        "\u{1F4D6} Reading {} \u{1F4D6}",
-        dir.to_str().expect("Couldn't read directory name")
-    ));
+    print(&format!("\u{1F4D6} Reading {} \u{1F4D6}", dir.display()));
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
 
@@ -175,12 +313,12 @@ pub fn router_read_recursive(
                 router_read_recursive(&path, scan, callback, None)?;
             }
         } else {
-            let file_name = entry.file_name().into_string().unwrap();
+            let file_name = entry.file_name().to_string_lossy().to_string();
 
             print(&format!("\u{1F440} Analyzing {}", entry.path().display()));
 
             if let Some(dir_type) = dir_type.clone() {
-                match callback(&entry, dir_type)? {
+                match callback(&entry, dir_type, &mut scan.diagnostics)? {
                     FileScanResult::Handlers(ref mut handlers) => {
                         scan.handlers
                             .entry(ep_name)
@@ -201,19 +339,19 @@ pub fn router_read_recursive(
             }
 
             if file_name.contains("setup") {
-                let routes = callback(&entry, AlxFileType::Setup)?;
+                let routes = callback(&entry, AlxFileType::Setup, &mut scan.diagnostics)?;
                 if let FileScanResult::Routes(routes) = routes {
                     scan.routes.insert(ep_name.to_string(), routes);
                 }
             }
             if file_name.contains("handler") {
-                let handlers = callback(&entry, AlxFileType::Handler)?;
+                let handlers = callback(&entry, AlxFileType::Handler, &mut scan.diagnostics)?;
                 if let FileScanResult::Handlers(handlers) = handlers {
                     scan.handlers.insert(ep_name.to_string(), handlers);
                 }
             }
             if file_name.contains("data") {
-                let data = callback(&entry, AlxFileType::Data)?;
+                let data = callback(&entry, AlxFileType::Data, &mut scan.diagnostics)?;
                 if let FileScanResult::Data(data) = data {
                     scan.data.insert(ep_name.to_string(), data);
                 }
@@ -223,12 +361,34 @@ pub fn router_read_recursive(
     Ok(())
 }
 
-/// Parse the given file according to the file type and extract routing info from it
-pub fn analyze(entry: &DirEntry, file_type: AlxFileType) -> Result<FileScanResult, AlxError> {
+/// Parse the given file according to the file type and extract routing info from it. Files that don't
+/// parse are skipped with an error.
+pub fn analyze(
+    entry: &DirEntry,
+    file_type: AlxFileType,
+    found: &mut Vec<Diagnostic>,
+) -> Result<FileScanResult, AlxError> {
     // Get the file syntax struct
-    let src = fs::read_to_string(entry.path())?;
-    let syntax = syn::parse_file(&src).expect("Unable to parse file");
-    match file_type {
+    let path = entry.path();
+    let src = fs::read_to_string(&path)?;
+    let mut diagnostics = Diagnostics::new(&path, &src);
+    let syntax = match syn::parse_file(&src) {
+        Ok(syntax) => syntax,
+        Err(e) => {
+            diagnostics.error(
+                e.span(),
+                format!("unable to parse file: {e}"),
+                "fix the syntax error, nothing in the file was analyzed",
+            );
+            found.extend(diagnostics.into_vec());
+            return Ok(match file_type {
+                AlxFileType::Setup => FileScanResult::Routes(vec![]),
+                AlxFileType::Handler => FileScanResult::Handlers(vec![]),
+                AlxFileType::Data => FileScanResult::Data(vec![]),
+            });
+        }
+    };
+    let result = match file_type {
         AlxFileType::Setup => {
             // Extract the functions. Only the `routes()` function
             // should be top level in this file and this vec should
@@ -242,8 +402,8 @@ pub fn analyze(entry: &DirEntry, file_type: AlxFileType) -> Result<FileScanResul
                 })
                 .collect::<Vec<syn::ItemFn>>();
 
-            let routes = scan_setup(functions);
-            Ok(FileScanResult::Routes(routes))
+            let routes = scan_setup(functions, &mut diagnostics);
+            FileScanResult::Routes(routes)
         }
         AlxFileType::Handler => {
             // Grab all the functions from the file
@@ -255,8 +415,8 @@ pub fn analyze(entry: &DirEntry, file_type: AlxFileType) -> Result<FileScanResul
                     _ => None,
                 })
                 .collect::<Vec<syn::ItemFn>>();
-            let handlers = scan_handlers(functions);
-            Ok(FileScanResult::Handlers(handlers))
+            let handlers = scan_handlers(functions, &mut diagnostics);
+            FileScanResult::Handlers(handlers)
         }
         AlxFileType::Data => {
             // Filter out only the structs
@@ -271,7 +431,33 @@ pub fn analyze(entry: &DirEntry, file_type: AlxFileType) -> Result<FileScanResul
                 })
                 .collect::<Vec<syn::Item>>();
             let data = scan_data(data);
-            Ok(FileScanResult::Data(data))
+            FileScanResult::Data(data)
         }
+    };
+    found.extend(diagnostics.into_vec());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_at_handlers_missing_from_handler_rs() {
+        let dir = std::env::temp_dir().join("alx_unresolved_handlers_test");
+        fs::create_dir_all(&dir).unwrap();
+        let setup = dir.join("setup.rs");
+        fs::write(
+            &setup,
+            "fn routes(cfg: &mut web::ServiceConfig) {\n    \
+             cfg.route(\"/\", web::get().to(handler::list));\n    \
+             cfg.route(\"/old\", web::get().to(legacy::list));\n}\n",
+        )
+        .unwrap();
+        let found = unresolved_handlers(&setup, &["list"]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].line, found[0].column), (2, 34));
+        assert_eq!(found[0].message, "the handler list isn't in handler.rs");
+        assert_eq!(found[0].severity, Severity::Warning);
     }
 }
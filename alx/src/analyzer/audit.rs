@@ -0,0 +1,250 @@
+//! Works out which guard protects each route. Middleware is registered by binding in setup.rs, i.e.
+//! `.wrap(auth_guard.clone())`, so the bindings are resolved to the constructor call they're bound
+//! to, i.e. `let auth_guard = interceptor::AuthGuard::new(pg, rd, Role::User)`. Everything that
+//! needs to know whether a route requires a session goes through [Guards].
+use super::analyze::project_config;
+use crate::{
+    config::{ProjectConfig, RouteHandler},
+    error::AlxError,
+};
+use std::{collections::HashMap, fmt::Display, fs};
+use syn::visit::Visit;
+
+/// Methods that change state and should not be public without a reason
+const STATE_CHANGING: [&str; 4] = ["POST", "PUT", "PATCH", "DELETE"];
+
+/// A middleware that authenticates the request and the role it requires, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guard {
+    pub ty: String,
+    pub role: Option<String>,
+}
+
+impl Display for Guard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.role {
+            Some(ref role) => write!(f, "{}({})", self.ty, role),
+            None => write!(f, "{}", self.ty),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditedRoute {
+    pub endpoint: String,
+    pub method: String,
+    pub path: String,
+    /// The outermost guard wrapping the route
+    pub guard: Option<Guard>,
+}
+
+impl AuditedRoute {
+    /// State changing routes without a guard
+    pub fn exposed(&self) -> bool {
+        self.guard.is_none() && STATE_CHANGING.contains(&self.method.as_str())
+    }
+}
+
+/// Routes that are public on purpose, one per line as `METHOD /path` or `/path` for any method.
+/// Lines starting with `#` are comments.
+#[derive(Debug, Default)]
+pub struct Allowlist(Vec<(Option<String>, String)>);
+
+impl Allowlist {
+    pub fn parse(src: &str) -> Self {
+        let entries = src
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| match l.split_once(char::is_whitespace) {
+                Some((method, path)) => (Some(method.to_uppercase()), path.trim().to_string()),
+                None => (None, l.to_string()),
+            })
+            .collect();
+        Self(entries)
+    }
+
+    pub fn allows(&self, route: &AuditedRoute) -> bool {
+        self.0.iter().any(|e| matches(e, route))
+    }
+
+    /// Entries matching no public route, either because the route is gone or because it got a guard
+    pub fn stale(&self, routes: &[AuditedRoute]) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|e| !routes.iter().any(|r| r.guard.is_none() && matches(e, r)))
+            .map(|(method, path)| match method {
+                Some(m) => format!("{m} {path}"),
+                None => path.clone(),
+            })
+            .collect()
+    }
+}
+
+fn matches((method, path): &(Option<String>, String), route: &AuditedRoute) -> bool {
+    *path == route.path && method.as_ref().is_none_or(|m| *m == route.method)
+}
+
+/// The guards bound in the setup.rs of every endpoint, keyed by endpoint name and binding
+#[derive(Debug, Default)]
+pub struct Guards(HashMap<String, HashMap<String, Guard>>);
+
+impl Guards {
+    /// Resolves the bindings of every endpoint. Endpoints without a setup.rs, i.e. when the project
+    /// config was read from a lock file elsewhere, are left to the middleware names.
+    pub fn of(pc: &ProjectConfig) -> Result<Self, AlxError> {
+        let mut guards = Self::default();
+        for ep in pc.endpoints.iter() {
+            if let Ok(src) = fs::read_to_string(format!("{}/setup.rs", ep.full_path)) {
+                guards.insert(&ep.name, &syn::parse_file(&src)?);
+            }
+        }
+        Ok(guards)
+    }
+
+    /// Adds the bindings of the endpoint's setup.rs
+    pub fn insert(&mut self, endpoint: &str, setup: &syn::File) {
+        self.0.insert(endpoint.to_string(), guard_bindings(setup));
+    }
+
+    /// The guard wrapping the route of the endpoint. The last `.wrap()` is the outermost, but any
+    /// guard rejects the request.
+    pub fn of_route(&self, endpoint: &str, route: &RouteHandler) -> Option<Guard> {
+        route
+            .middleware
+            .iter()
+            .flatten()
+            .rev()
+            .find_map(|mw| self.guard(endpoint, mw))
+    }
+
+    /// Whether the route of the endpoint requires a session
+    pub fn is_guarded(&self, endpoint: &str, route: &RouteHandler) -> bool {
+        self.of_route(endpoint, route).is_some()
+    }
+
+    /// The guard the middleware binding or type stands for, if it is one
+    pub fn guard(&self, endpoint: &str, mw: &str) -> Option<Guard> {
+        match self.0.get(endpoint).and_then(|b| b.get(mw)) {
+            Some(guard) => Some(guard.clone()),
+            None => is_guard(mw).then(|| Guard {
+                ty: mw.to_string(),
+                role: None,
+            }),
+        }
+    }
+}
+
+/// Audits every route of the API, sorted by endpoint, path and method
+pub fn audit(api_path: &str) -> Result<Vec<AuditedRoute>, AlxError> {
+    let pc = project_config(api_path)?;
+    let guards = Guards::of(&pc)?;
+    let mut routes = vec![];
+    for ep in pc.endpoints.iter() {
+        for route in ep.routes.iter() {
+            routes.push(AuditedRoute {
+                endpoint: ep.name.clone(),
+                method: route.method.clone(),
+                path: route.path.clone(),
+                guard: guards.of_route(&ep.name, route),
+            });
+        }
+    }
+    routes
+        .sort_by(|a, b| (&a.endpoint, &a.path, &a.method).cmp(&(&b.endpoint, &b.path, &b.method)));
+    Ok(routes)
+}
+
+/// Whether the middleware type authenticates requests
+fn is_guard(ty: &str) -> bool {
+    let ty = ty.to_lowercase();
+    ty.contains("auth") || ty.contains("guard")
+}
+
+/// The guards bound to locals in the `routes` function of setup.rs
+fn guard_bindings(setup: &syn::File) -> HashMap<String, Guard> {
+    let mut bindings = GuardBindings::default();
+    bindings.visit_file(setup);
+    bindings.0
+}
+
+#[derive(Default)]
+struct GuardBindings(HashMap<String, Guard>);
+
+impl<'ast> Visit<'ast> for GuardBindings {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let (syn::Pat::Ident(ref name), Some((_, ref init))) = (&local.pat, &local.init) {
+            if let syn::Expr::Call(ref call) = **init {
+                if let syn::Expr::Path(ref func) = *call.func {
+                    // `interceptor::AuthGuard::new(..)` is an `AuthGuard`, `AuthGuard(..)` too
+                    let segments = func.path.segments.iter().collect::<Vec<_>>();
+                    let ty = match segments.as_slice() {
+                        [.., ty, _] => ty.ident.to_string(),
+                        [ty] => ty.ident.to_string(),
+                        [] => String::new(),
+                    };
+                    if is_guard(&ty) {
+                        let role = call.args.iter().find_map(role);
+                        self.0.insert(name.ident.to_string(), Guard { ty, role });
+                    }
+                }
+            }
+        }
+        syn::visit::visit_local(self, local);
+    }
+}
+
+/// The variant of a `Role::X` argument
+fn role(arg: &syn::Expr) -> Option<String> {
+    let syn::Expr::Path(p) = arg else {
+        return None;
+    };
+    match p.path.segments.iter().collect::<Vec<_>>().as_slice() {
+        [.., ty, variant] if ty.ident == "Role" => Some(variant.ident.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_guards_and_allows_routes() {
+        let setup = syn::parse_file(
+            r#"
+            pub(crate) fn routes(pg: Arc<Postgres>, rd: Arc<Redis>, cfg: &mut web::ServiceConfig) {
+                let service = UserService { repository: Repository { client: pg.clone() } };
+                let auth_guard = interceptor::AuthGuard::new(pg.clone(), rd.clone(), Role::User);
+                let admin_guard = AuthGuard::new(pg, rd, store::Role::Admin);
+                let logger = Logger::default();
+            }
+            "#,
+        )
+        .unwrap();
+        let guards = guard_bindings(&setup);
+        assert_eq!(guards.len(), 2);
+        assert_eq!(guards["auth_guard"].to_string(), "AuthGuard(User)");
+        assert_eq!(guards["admin_guard"].to_string(), "AuthGuard(Admin)");
+
+        let route = |method: &str, path: &str, guard: Option<&Guard>| AuditedRoute {
+            endpoint: "auth".to_string(),
+            method: method.to_string(),
+            path: path.to_string(),
+            guard: guard.cloned(),
+        };
+        let routes = [
+            route("POST", "/auth/login", None),
+            route("GET", "/auth/reset-password", None),
+            route("POST", "/auth/logout", Some(&guards["auth_guard"])),
+        ];
+        assert_eq!(
+            routes.iter().map(|r| r.exposed()).collect::<Vec<_>>(),
+            [true, false, false]
+        );
+        let allow = Allowlist::parse("# Public\nPOST /auth/login\n/auth/logout\n");
+        assert!(allow.allows(&routes[0]));
+        assert!(!allow.allows(&routes[1]));
+        assert_eq!(allow.stale(&routes), ["/auth/logout"]);
+    }
+}
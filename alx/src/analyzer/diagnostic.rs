@@ -0,0 +1,212 @@
+//! Problems the analyzer runs into. Instead of aborting, they are collected with the file and span
+//! they were found at and what to do about them, and the analysis carries on with whatever it could
+//! make sense of, so one odd handler doesn't take down the whole run.
+use colored::Colorize;
+use proc_macro2::Span;
+use std::{fmt::Display, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Something was skipped, the rest of the file was analyzed
+    Warning,
+    /// The file couldn't be analyzed at all
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    /// Starting from 1
+    pub line: usize,
+    /// Starting from 1
+    pub column: usize,
+    pub message: String,
+    /// The source line the span starts on
+    pub source: String,
+    /// How many characters of the source line the span covers
+    pub width: usize,
+    /// The suggested fix
+    pub help: Option<String>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning".yellow().bold(),
+            Severity::Error => "error".red().bold(),
+        };
+        let gutter = " ".repeat(self.line.to_string().len());
+        let bar = "|".blue().bold();
+        writeln!(f, "{severity}: {}", self.message.bold())?;
+        writeln!(
+            f,
+            "{gutter}{} {}:{}:{}",
+            "-->".blue().bold(),
+            self.file,
+            self.line,
+            self.column
+        )?;
+        if !self.source.is_empty() {
+            let marker = match self.severity {
+                Severity::Warning => "^".repeat(self.width).yellow().bold(),
+                Severity::Error => "^".repeat(self.width).red().bold(),
+            };
+            writeln!(f, "{gutter} {bar}")?;
+            writeln!(
+                f,
+                "{} {bar} {}",
+                self.line.to_string().blue().bold(),
+                self.source
+            )?;
+            writeln!(f, "{gutter} {bar} {}{marker}", " ".repeat(self.column - 1))?;
+        }
+        if let Some(ref help) = self.help {
+            writeln!(
+                f,
+                "{gutter} {} {}: {help}",
+                "=".blue().bold(),
+                "help".bold()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the diagnostics of a source file
+pub struct Diagnostics<'a> {
+    file: String,
+    src: &'a str,
+    found: Vec<Diagnostic>,
+}
+
+impl<'a> Diagnostics<'a> {
+    pub fn new(file: &Path, src: &'a str) -> Self {
+        Self {
+            file: file.display().to_string(),
+            src,
+            found: vec![],
+        }
+    }
+
+    pub fn warn(&mut self, span: Span, message: impl Into<String>, help: impl Into<String>) {
+        self.push(Severity::Warning, span, message.into(), Some(help.into()));
+    }
+
+    pub fn error(&mut self, span: Span, message: impl Into<String>, help: impl Into<String>) {
+        self.push(Severity::Error, span, message.into(), Some(help.into()));
+    }
+
+    /// The source the span covers, as written
+    pub fn snippet(&self, span: Span) -> String {
+        let (start, end) = (span.start(), span.end());
+        self.src
+            .lines()
+            .enumerate()
+            .skip(start.line.saturating_sub(1))
+            .take(end.line + 1 - start.line.max(1))
+            .map(|(i, line)| {
+                let chars = line.chars();
+                match (i + 1 == start.line, i + 1 == end.line) {
+                    (true, true) => chars
+                        .skip(start.column)
+                        .take(end.column - start.column)
+                        .collect(),
+                    (true, false) => chars.skip(start.column).collect(),
+                    (false, true) => chars.take(end.column).collect(),
+                    (false, false) => line.to_string(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn push(&mut self, severity: Severity, span: Span, message: String, help: Option<String>) {
+        let (start, end) = (span.start(), span.end());
+        let source = self
+            .src
+            .lines()
+            .nth(start.line.saturating_sub(1))
+            .unwrap_or_default()
+            .trim_end();
+        // Columns count characters, the underline stops at the end of the first line
+        let line_len = source.chars().count();
+        let column = start.column.min(line_len);
+        let width = match end.line == start.line {
+            true => end.column.min(line_len).saturating_sub(column),
+            false => line_len - column,
+        };
+        self.found.push(Diagnostic {
+            severity,
+            file: self.file.clone(),
+            line: start.line.max(1),
+            column: column + 1,
+            message,
+            source: source.to_string(),
+            width: width.max(1),
+            help,
+        });
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.found
+    }
+}
+
+/// Prints the diagnostics to stderr, so they don't end up in output meant to be piped
+pub fn report(diagnostics: &[Diagnostic]) {
+    for d in diagnostics.iter() {
+        eprintln!("{d}");
+    }
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let (warnings, errors) = (count(Severity::Warning), count(Severity::Error));
+    if errors > 0 {
+        eprintln!(
+            "{}: analysis skipped {errors} file(s) and {warnings} item(s)",
+            "error".red().bold()
+        );
+    } else if warnings > 0 {
+        eprintln!(
+            "{}: analysis skipped {warnings} item(s)",
+            "warning".yellow().bold()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::spanned::Spanned;
+
+    #[test]
+    fn points_at_the_span() {
+        let src = "fn handler(data: web::Json<(String, u16)>) {}\n";
+        let file = syn::parse_file(src).unwrap();
+        let syn::Item::Fn(ref f) = file.items[0] else {
+            unreachable!()
+        };
+        let mut diagnostics = Diagnostics::new(Path::new("handler.rs"), src);
+        diagnostics.warn(f.sig.inputs.span(), "unsupported input", "use a struct");
+        let found = diagnostics.into_vec();
+        assert_eq!(
+            (found[0].line, found[0].column, found[0].width),
+            (1, 12, 30)
+        );
+
+        colored::control::set_override(false);
+        assert_eq!(
+            found[0].to_string(),
+            "warning: unsupported input\n\
+             \x20--> handler.rs:1:12\n\
+             \x20 |\n\
+             1 | fn handler(data: web::Json<(String, u16)>) {}\n\
+             \x20 |            ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^\n\
+             \x20 = help: use a struct\n"
+        );
+    }
+}
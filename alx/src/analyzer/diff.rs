@@ -0,0 +1,550 @@
+//! Semantic comparison of two project configurations used to detect breaking API changes
+use crate::{
+    config::{Data, Extractor, Field, ProjectConfig, RouteHandler},
+    validation::Validation,
+};
+use std::fmt::Display;
+
+/// A single difference between two versions of the API
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// `METHOD /path` of the affected route
+    pub route: String,
+    pub kind: ChangeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    RouteAdded,
+    RouteRemoved,
+    MethodChanged {
+        from: String,
+        to: String,
+    },
+    InputChanged {
+        from: String,
+        to: String,
+    },
+    FieldAdded {
+        name: String,
+        required: bool,
+    },
+    FieldRemoved(String),
+    FieldRequired(String),
+    FieldOptional(String),
+    FieldTypeChanged {
+        name: String,
+        from: String,
+        to: String,
+    },
+    ValidationTightened {
+        field: String,
+        rule: String,
+    },
+    ValidationLoosened {
+        field: String,
+        rule: String,
+    },
+    MiddlewareAdded(String),
+    MiddlewareRemoved(String),
+    GuardAdded(String),
+    GuardRemoved(String),
+}
+
+impl Change {
+    /// Breaking changes are the ones that can make requests valid against the old API fail on the new one
+    pub fn is_breaking(&self) -> bool {
+        match self.kind {
+            ChangeKind::RouteRemoved
+            | ChangeKind::MethodChanged { .. }
+            | ChangeKind::InputChanged { .. }
+            | ChangeKind::FieldRequired(_)
+            | ChangeKind::FieldTypeChanged { .. }
+            | ChangeKind::ValidationTightened { .. }
+            | ChangeKind::MiddlewareAdded(_)
+            | ChangeKind::GuardAdded(_) => true,
+            ChangeKind::FieldAdded { required, .. } => required,
+            ChangeKind::RouteAdded
+            | ChangeKind::FieldRemoved(_)
+            | ChangeKind::FieldOptional(_)
+            | ChangeKind::ValidationLoosened { .. }
+            | ChangeKind::MiddlewareRemoved(_)
+            | ChangeKind::GuardRemoved(_) => false,
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.route)?;
+        match &self.kind {
+            ChangeKind::RouteAdded => write!(f, "route added"),
+            ChangeKind::RouteRemoved => write!(f, "route removed"),
+            ChangeKind::MethodChanged { from, to } => write!(f, "method changed {from} -> {to}"),
+            ChangeKind::InputChanged { from, to } => write!(f, "input changed {from} -> {to}"),
+            ChangeKind::FieldAdded { name, required } => {
+                let req = if *required { "required" } else { "optional" };
+                write!(f, "{req} field '{name}' added")
+            }
+            ChangeKind::FieldRemoved(name) => write!(f, "field '{name}' removed"),
+            ChangeKind::FieldRequired(name) => write!(f, "field '{name}' is now required"),
+            ChangeKind::FieldOptional(name) => write!(f, "field '{name}' is now optional"),
+            ChangeKind::FieldTypeChanged { name, from, to } => {
+                write!(f, "field '{name}' type changed {from} -> {to}")
+            }
+            ChangeKind::ValidationTightened { field, rule } => {
+                write!(f, "validation on '{field}' tightened: {rule}")
+            }
+            ChangeKind::ValidationLoosened { field, rule } => {
+                write!(f, "validation on '{field}' loosened: {rule}")
+            }
+            ChangeKind::MiddlewareAdded(mw) => write!(f, "middleware '{mw}' added"),
+            ChangeKind::MiddlewareRemoved(mw) => write!(f, "middleware '{mw}' removed"),
+            ChangeKind::GuardAdded(guard) => write!(f, "guard '{guard}' added"),
+            ChangeKind::GuardRemoved(guard) => write!(f, "guard '{guard}' removed"),
+        }
+    }
+}
+
+/// Compares the old project config to the new one and returns all the found changes
+pub fn diff(old: &ProjectConfig, new: &ProjectConfig) -> Vec<Change> {
+    let old_routes = routes(old);
+    let new_routes = routes(new);
+    let mut changes = vec![];
+
+    for o in old_routes.iter() {
+        let id = route_id(o);
+        match new_routes
+            .iter()
+            .find(|n| n.path == o.path && n.method == o.method)
+        {
+            Some(n) => diff_route(&id, o, n, &mut changes),
+            None => {
+                // If the method changed on a path with a single route, report it as such
+                let old_on_path = old_routes.iter().filter(|r| r.path == o.path).count();
+                let new_on_path = new_routes
+                    .iter()
+                    .filter(|n| n.path == o.path)
+                    .collect::<Vec<_>>();
+                match new_on_path.as_slice() {
+                    [n] if old_on_path == 1 => {
+                        changes.push(Change {
+                            route: id.clone(),
+                            kind: ChangeKind::MethodChanged {
+                                from: o.method.clone(),
+                                to: n.method.clone(),
+                            },
+                        });
+                        diff_route(&id, o, n, &mut changes);
+                    }
+                    _ => changes.push(Change {
+                        route: id,
+                        kind: ChangeKind::RouteRemoved,
+                    }),
+                }
+            }
+        }
+    }
+
+    for n in new_routes.iter() {
+        let existed = old_routes
+            .iter()
+            .any(|o| o.path == n.path && o.method == n.method);
+        let method_changed = changes.iter().any(|c| {
+            matches!(&c.kind, ChangeKind::MethodChanged { to, .. } if to == &n.method)
+                && c.route.ends_with(&format!(" {}", n.path))
+        });
+        if !existed && !method_changed {
+            changes.push(Change {
+                route: route_id(n),
+                kind: ChangeKind::RouteAdded,
+            });
+        }
+    }
+    changes
+}
+
+fn routes(pc: &ProjectConfig) -> Vec<&RouteHandler> {
+    pc.endpoints
+        .iter()
+        .flat_map(|ep| ep.routes.iter())
+        .collect()
+}
+
+fn route_id(r: &RouteHandler) -> String {
+    format!("{} {}", r.method, r.path)
+}
+
+fn diff_route(id: &str, old: &RouteHandler, new: &RouteHandler, changes: &mut Vec<Change>) {
+    let mut push = |kind| {
+        changes.push(Change {
+            route: id.to_string(),
+            kind,
+        })
+    };
+
+    // Moving the input between the path, query and body breaks every client
+    let location = |r: &RouteHandler| {
+        [
+            Extractor::Path,
+            Extractor::Query,
+            Extractor::Json,
+            Extractor::Form,
+        ]
+        .into_iter()
+        .filter(|e| r.extractor_data(*e).is_some())
+        .map(|e| format!("{e:?}"))
+        .collect::<Vec<_>>()
+        .join("+")
+    };
+    let (old_loc, new_loc) = (location(old), location(new));
+    if old_loc != new_loc {
+        push(ChangeKind::InputChanged {
+            from: if old_loc.is_empty() {
+                "none".into()
+            } else {
+                old_loc
+            },
+            to: if new_loc.is_empty() {
+                "none".into()
+            } else {
+                new_loc
+            },
+        });
+    }
+
+    let empty = Data::default();
+    diff_data(
+        old.input.as_ref().unwrap_or(&empty),
+        new.input.as_ref().unwrap_or(&empty),
+        &mut push,
+    );
+
+    let old_mw = old.middleware.clone().unwrap_or_default();
+    let new_mw = new.middleware.clone().unwrap_or_default();
+    for mw in new_mw.iter().filter(|mw| !old_mw.contains(mw)) {
+        push(ChangeKind::MiddlewareAdded(mw.clone()));
+    }
+    for mw in old_mw.iter().filter(|mw| !new_mw.contains(mw)) {
+        push(ChangeKind::MiddlewareRemoved(mw.clone()));
+    }
+
+    // Requests the old guards let through may not match the new ones
+    let old_guards = old.guards.clone().unwrap_or_default();
+    let new_guards = new.guards.clone().unwrap_or_default();
+    for guard in new_guards.iter().filter(|g| !old_guards.contains(g)) {
+        push(ChangeKind::GuardAdded(guard.clone()));
+    }
+    for guard in old_guards.iter().filter(|g| !new_guards.contains(g)) {
+        push(ChangeKind::GuardRemoved(guard.clone()));
+    }
+}
+
+fn diff_data(old: &Data, new: &Data, push: &mut impl FnMut(ChangeKind)) {
+    for nf in new.fields.iter() {
+        match old
+            .fields
+            .iter()
+            .find(|of| of.wire_name() == nf.wire_name())
+        {
+            Some(of) => diff_field(of, nf, push),
+            None => push(ChangeKind::FieldAdded {
+                name: nf.wire_name().to_string(),
+                required: nf.required,
+            }),
+        }
+    }
+    for of in old.fields.iter() {
+        if !new.fields.iter().any(|nf| nf.wire_name() == of.wire_name()) {
+            push(ChangeKind::FieldRemoved(of.wire_name().to_string()));
+        }
+    }
+}
+
+fn diff_field(old: &Field, new: &Field, push: &mut impl FnMut(ChangeKind)) {
+    // Fields are reported by the name clients see
+    let name = new.wire_name();
+    if !old.required && new.required {
+        push(ChangeKind::FieldRequired(name.to_string()));
+    }
+    if old.required && !new.required {
+        push(ChangeKind::FieldOptional(name.to_string()));
+    }
+    // Wrapping or unwrapping an option is covered above
+    let strip = |ty: &str| {
+        ty.strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+            .unwrap_or(ty)
+            .to_string()
+    };
+    if strip(&old.ty) != strip(&new.ty) {
+        push(ChangeKind::FieldTypeChanged {
+            name: name.to_string(),
+            from: old.ty.clone(),
+            to: new.ty.clone(),
+        });
+    }
+
+    let old_v = Validation::parse_all(&old.validation);
+    let new_v = Validation::parse_all(&new.validation);
+    for nv in new_v.iter() {
+        match old_v.iter().find(|ov| same_rule(ov, nv)) {
+            Some(ov) => compare_rule(name, ov, nv, push),
+            None => push(ChangeKind::ValidationTightened {
+                field: name.to_string(),
+                rule: format!("added {}", rule_name(nv)),
+            }),
+        }
+    }
+    for ov in old_v.iter() {
+        if !new_v.iter().any(|nv| same_rule(ov, nv)) {
+            push(ChangeKind::ValidationLoosened {
+                field: name.to_string(),
+                rule: format!("removed {}", rule_name(ov)),
+            });
+        }
+    }
+}
+
+fn same_rule(a: &Validation, b: &Validation) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn rule_name(v: &Validation) -> String {
+    match v {
+        Validation::Length { .. } => "length".to_string(),
+        Validation::Range { .. } => "range".to_string(),
+        Validation::Regex(r) => format!("regex {r}"),
+        Validation::Email => "email".to_string(),
+        Validation::Url => "url".to_string(),
+        Validation::Custom(c) => format!("custom {c}"),
+        Validation::Other(o) => o.clone(),
+    }
+}
+
+/// Compares two rules of the same kind
+fn compare_rule(
+    field: &str,
+    old: &Validation,
+    new: &Validation,
+    push: &mut impl FnMut(ChangeKind),
+) {
+    let mut tighter = vec![];
+    let mut looser = vec![];
+    let mut bound = |name: &str, o: Option<f64>, n: Option<f64>, is_min: bool| match (o, n) {
+        (None, Some(n)) => tighter.push(format!("{name} = {n}")),
+        (Some(o), None) => looser.push(format!("{name} {o} removed")),
+        (Some(o), Some(n)) if o != n => {
+            if (n > o) == is_min {
+                tighter.push(format!("{name} {o} -> {n}"))
+            } else {
+                looser.push(format!("{name} {o} -> {n}"))
+            }
+        }
+        _ => {}
+    };
+    match (old, new) {
+        (
+            Validation::Length {
+                min: omin,
+                max: omax,
+                equal: oeq,
+            },
+            Validation::Length {
+                min: nmin,
+                max: nmax,
+                equal: neq,
+            },
+        ) => {
+            let f = |v: &Option<u64>| v.map(|v| v as f64);
+            bound("min length", f(omin), f(nmin), true);
+            bound("max length", f(omax), f(nmax), false);
+            if oeq != neq {
+                match neq {
+                    Some(_) => tighter.push(format!("equal length {oeq:?} -> {neq:?}")),
+                    None => looser.push(format!("equal length {oeq:?} removed")),
+                }
+            }
+        }
+        (
+            Validation::Range {
+                min: omin,
+                max: omax,
+            },
+            Validation::Range {
+                min: nmin,
+                max: nmax,
+            },
+        ) => {
+            bound("min", *omin, *nmin, true);
+            bound("max", *omax, *nmax, false);
+        }
+        // For the rest we can only tell whether they are the same or not
+        (o, n) if o != n => tighter.push(format!("{} -> {}", rule_name(o), rule_name(n))),
+        _ => {}
+    }
+    for rule in tighter {
+        push(ChangeKind::ValidationTightened {
+            field: field.to_string(),
+            rule,
+        });
+    }
+    for rule in looser {
+        push(ChangeKind::ValidationLoosened {
+            field: field.to_string(),
+            rule,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Endpoint, Handler, HandlerInput};
+
+    fn route(method: &str, path: &str, fields: Vec<Field>, mw: Option<Vec<&str>>) -> RouteHandler {
+        RouteHandler {
+            method: method.to_string(),
+            path: path.to_string(),
+            handler: Some(Handler {
+                name: "handler".to_string(),
+                inputs: vec![HandlerInput {
+                    ext_type: "Json".to_string(),
+                    data_type: "Input".to_string(),
+                }],
+                bound: None,
+                ..Default::default()
+            }),
+            middleware: mw.map(|mw| mw.into_iter().map(String::from).collect()),
+            guards: None,
+            service: None,
+            input: Some(Data {
+                id: "Input".to_string(),
+                fields,
+            }),
+        }
+    }
+
+    fn field(name: &str, ty: &str, required: bool, validation: &[&str]) -> Field {
+        Field {
+            name: name.to_string(),
+            ty: ty.to_string(),
+            required,
+            validation: validation.iter().map(|v| v.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn config(routes: Vec<RouteHandler>) -> ProjectConfig {
+        ProjectConfig {
+            endpoints: vec![Endpoint {
+                name: "test".to_string(),
+                full_path: String::new(),
+                routes,
+                contracts: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn classifies_route_changes() {
+        let old = config(vec![
+            route("POST", "/a", vec![], None),
+            route("GET", "/b", vec![], None),
+        ]);
+        let new = config(vec![
+            route("PUT", "/a", vec![], None),
+            route("GET", "/c", vec![], None),
+        ]);
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert!(changes
+            .iter()
+            .any(|c| matches!(c.kind, ChangeKind::MethodChanged { .. }) && c.is_breaking()));
+        assert!(changes
+            .iter()
+            .any(|c| c.kind == ChangeKind::RouteRemoved && c.route == "GET /b"));
+        assert!(changes
+            .iter()
+            .any(|c| c.kind == ChangeKind::RouteAdded && !c.is_breaking()));
+    }
+
+    #[test]
+    fn classifies_field_changes() {
+        let old = config(vec![route(
+            "POST",
+            "/a",
+            vec![
+                field("name", "String", true, &["length (min = 2, max = 10)"]),
+                field("age", "Option<u8>", false, &[]),
+            ],
+            None,
+        )]);
+        let new = config(vec![route(
+            "POST",
+            "/a",
+            vec![
+                field("name", "String", true, &["length (min = 3, max = 20)"]),
+                field("age", "u8", true, &[]),
+                field("nick", "Option<String>", false, &[]),
+            ],
+            Some(vec!["auth_guard"]),
+        )]);
+        let changes = diff(&old, &new);
+        let breaking = changes.iter().filter(|c| c.is_breaking()).count();
+        // min length, required age, auth guard
+        assert_eq!(breaking, 3, "{changes:?}");
+        // max length, optional nick
+        assert_eq!(changes.len() - breaking, 2, "{changes:?}");
+    }
+
+    #[test]
+    fn reports_fields_by_their_serde_name() {
+        let renamed = |ty: &str, required: bool| Field {
+            rename: Some("userName".to_string()),
+            ..field("user_name", ty, required, &["length (min = 2)"])
+        };
+        let old = config(vec![route(
+            "POST",
+            "/a",
+            vec![renamed("String", false)],
+            None,
+        )]);
+        let mut new_field = renamed("u32", true);
+        new_field.validation.clear();
+        let new = config(vec![route("POST", "/a", vec![new_field], None)]);
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes.iter().map(|c| &c.kind).collect::<Vec<_>>(),
+            [
+                &ChangeKind::FieldRequired("userName".to_string()),
+                &ChangeKind::FieldTypeChanged {
+                    name: "userName".to_string(),
+                    from: "String".to_string(),
+                    to: "u32".to_string(),
+                },
+                &ChangeKind::ValidationLoosened {
+                    field: "userName".to_string(),
+                    rule: "removed length".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_guard_changes() {
+        let mut old = route("GET", "/a", vec![], None);
+        old.guards = Some(vec!["guard::Header(\"x-version\", \"1\")".to_string()]);
+        let mut new = route("GET", "/a", vec![], None);
+        new.guards = Some(vec!["guard::Host(\"api.example.com\")".to_string()]);
+        let changes = diff(&config(vec![old]), &config(vec![new]));
+        assert_eq!(
+            changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            [
+                "GET /a: guard 'guard::Host(\"api.example.com\")' added",
+                "GET /a: guard 'guard::Header(\"x-version\", \"1\")' removed",
+            ]
+        );
+        assert!(changes[0].is_breaking() && !changes[1].is_breaking());
+    }
+}
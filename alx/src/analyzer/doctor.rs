@@ -0,0 +1,458 @@
+//! Checks the invariants the generators rely on. Routes and middleware have to be declared in
+//! their parent module and contain the files alx generates for them, routes have to be called
+//! from `configure.rs` and the handlers they register have to exist.
+use crate::{
+    boiler::{configure, plate::BoilerType},
+    error::AlxError,
+    settings::settings,
+};
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+use syn::visit::Visit;
+
+/// A broken invariant, optionally with the change that fixes it
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub file: String,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+/// Mechanical changes doctor knows how to make
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// Declare the module in its parent's mod file
+    Declare {
+        mod_file: String,
+        name: String,
+        vis: &'static str,
+    },
+    /// Create an empty module file and declare it
+    Create {
+        path: String,
+        mod_file: String,
+        name: String,
+        vis: &'static str,
+    },
+    /// Call the route's setup from configure
+    Wire {
+        configure: String,
+        route: String,
+        setup: String,
+    },
+}
+
+impl Display for Fix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fix::Declare {
+                mod_file,
+                name,
+                vis,
+            } => write!(f, "declare `{vis} mod {name};` in {mod_file}"),
+            Fix::Create { path, .. } => write!(f, "create {path}"),
+            Fix::Wire {
+                configure, route, ..
+            } => write!(f, "call {route}::setup::routes in {configure}"),
+        }
+    }
+}
+
+impl Diagnostic {
+    fn new(file: impl AsRef<Path>, message: impl Into<String>, fix: Option<Fix>) -> Self {
+        Self {
+            file: file.as_ref().display().to_string(),
+            message: message.into(),
+            fix,
+        }
+    }
+}
+
+/// Runs every check on the API directory. `configure` is the path to `configure.rs`.
+pub fn check(api_path: &str, configure: &str) -> Result<Vec<Diagnostic>, AlxError> {
+    let mut diagnostics = vec![];
+    let router = PathBuf::from(settings().paths.router_of(api_path));
+    let middleware = PathBuf::from(settings().paths.middleware_of(api_path));
+    let configure_src = fs::read_to_string(configure).ok();
+    if configure_src.is_none() {
+        diagnostics.push(Diagnostic::new(configure, "file not found", None));
+    }
+    // Whether routes are wired can't be told from a file that doesn't parse
+    let configure_src =
+        configure_src.filter(|src| parse(Path::new(configure), src, &mut diagnostics).is_some());
+
+    for dir in module_dirs(&router)? {
+        let name = file_name(&dir);
+        check_module(&dir, &router, BoilerType::Route, &mut diagnostics)?;
+
+        let setup = dir.join("setup.rs");
+        let Ok(setup_src) = fs::read_to_string(&setup) else {
+            continue;
+        };
+        if let Some(ref src) = configure_src {
+            if !configure::is_wired(src, &name)? {
+                diagnostics.push(Diagnostic::new(
+                    configure,
+                    format!("{name}::setup::routes is never called, the routes of {name} are not registered"),
+                    Some(Fix::Wire {
+                        configure: configure.to_string(),
+                        route: name.clone(),
+                        setup: setup.display().to_string(),
+                    }),
+                ));
+            }
+        }
+        check_handlers(&dir, &setup, &setup_src, &mut diagnostics)?;
+    }
+    check_declared(&router, &mut diagnostics)?;
+
+    for dir in module_dirs(&middleware)? {
+        check_module(&dir, &middleware, BoilerType::MW, &mut diagnostics)?;
+    }
+    check_declared(&middleware, &mut diagnostics)?;
+
+    Ok(diagnostics)
+}
+
+/// Checks the module directory is declared in its parent and contains all the files of the type
+fn check_module(
+    dir: &Path,
+    parent: &Path,
+    ty: BoilerType,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), AlxError> {
+    let generator = settings().generator(ty);
+    let vis = generator.module_visibility.as_str();
+    let files = &generator.files;
+    let name = file_name(dir);
+    let parent_mod = parent.join("mod.rs");
+    if declared_mods(&parent_mod, diagnostics).is_some_and(|d| !d.contains(&name)) {
+        diagnostics.push(Diagnostic::new(
+            &parent_mod,
+            format!("{name} is not declared"),
+            Some(Fix::Declare {
+                mod_file: parent_mod.display().to_string(),
+                name: name.clone(),
+                vis,
+            }),
+        ));
+    }
+
+    let mod_file = dir.join("mod.rs");
+    let declared = declared_mods(&mod_file, diagnostics);
+    let existing = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| file_name(&e.path()).trim_end_matches(".rs").to_string())
+        .collect::<Vec<_>>();
+    for file in files {
+        // As in the generated mod files, see `plate::mod`
+        let vis = generator.file_visibility(file, ty);
+        let path = dir.join(format!("{file}.rs"));
+        if path.exists() || dir.join(file).join("mod.rs").exists() {
+            if *file != "mod" && declared.as_ref().is_some_and(|d| !d.contains(file)) {
+                diagnostics.push(Diagnostic::new(
+                    &mod_file,
+                    format!("{file} is not declared"),
+                    Some(Fix::Declare {
+                        mod_file: mod_file.display().to_string(),
+                        name: file.to_string(),
+                        vis,
+                    }),
+                ));
+            }
+            continue;
+        }
+        // Don't create a file next to one that is most likely a typo of it
+        match existing
+            .iter()
+            .find(|e| !files.contains(e) && similar(e, file))
+        {
+            Some(typo) => diagnostics.push(Diagnostic::new(
+                &path,
+                format!("file not found, did you mean to name {typo}.rs {file}.rs?"),
+                None,
+            )),
+            None => diagnostics.push(Diagnostic::new(
+                &path,
+                "file not found",
+                Some(Fix::Create {
+                    path: path.display().to_string(),
+                    mod_file: mod_file.display().to_string(),
+                    name: file.to_string(),
+                    vis,
+                }),
+            )),
+        }
+    }
+    Ok(())
+}
+
+/// Checks every module declared in the directory's mod file exists
+fn check_declared(dir: &Path, diagnostics: &mut Vec<Diagnostic>) -> Result<(), AlxError> {
+    let mod_file = dir.join("mod.rs");
+    for name in declared_mods(&mod_file, diagnostics).into_iter().flatten() {
+        if !dir.join(format!("{name}.rs")).exists() && !dir.join(&name).join("mod.rs").exists() {
+            diagnostics.push(Diagnostic::new(
+                &mod_file,
+                format!("{name} is declared but neither {name}.rs nor {name}/mod.rs exist"),
+                None,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the handlers the setup file refers to exist
+fn check_handlers(
+    dir: &Path,
+    setup: &Path,
+    src: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), AlxError> {
+    let Some(file) = parse(setup, src, diagnostics) else {
+        return Ok(());
+    };
+    let mut visitor = HandlerPaths::default();
+    visitor.visit_file(&file);
+
+    let mut refs = visitor.paths;
+    refs.sort();
+    refs.dedup();
+    for path in refs {
+        let Some((name, modules)) = path.split_last() else {
+            continue;
+        };
+        let mut module = dir.join("handler");
+        for m in modules {
+            module.push(m);
+        }
+        let candidates = [module.with_extension("rs"), module.join("mod.rs")];
+        let Some(handler_file) = candidates.iter().find(|c| c.exists()) else {
+            diagnostics.push(Diagnostic::new(
+                setup,
+                format!("handler::{} refers to a missing module", path.join("::")),
+                None,
+            ));
+            continue;
+        };
+        let Some(handler) = parse(
+            handler_file,
+            &fs::read_to_string(handler_file)?,
+            diagnostics,
+        ) else {
+            continue;
+        };
+        let exists = handler.items.iter().any(|item| match item {
+            syn::Item::Fn(f) => f.sig.ident == name,
+            _ => false,
+        });
+        if !exists {
+            diagnostics.push(Diagnostic::new(
+                setup,
+                format!(
+                    "handler::{} is registered but {} has no fn {name}",
+                    path.join("::"),
+                    handler_file.display()
+                ),
+                None,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Collects the segments following `handler` of the paths in expressions and imports, i.e.
+/// `["get_user"]` for `handler::get_user::<S>` and `["favicon", "favicon"]` for
+/// `use super::handler::favicon::favicon`
+#[derive(Debug, Default)]
+struct HandlerPaths {
+    paths: Vec<Vec<String>>,
+}
+
+impl HandlerPaths {
+    fn push(&mut self, segments: Vec<String>) {
+        if let Some(i) = segments.iter().position(|s| s == "handler") {
+            if i + 1 < segments.len() {
+                self.paths.push(segments[i + 1..].to_vec());
+            }
+        }
+    }
+
+    fn use_tree(&mut self, tree: &syn::UseTree, prefix: &mut Vec<String>) {
+        match tree {
+            syn::UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                self.use_tree(&p.tree, prefix);
+                prefix.pop();
+            }
+            syn::UseTree::Name(n) => {
+                let mut path = prefix.clone();
+                path.push(n.ident.to_string());
+                self.push(path);
+            }
+            syn::UseTree::Rename(r) => {
+                let mut path = prefix.clone();
+                path.push(r.ident.to_string());
+                self.push(path);
+            }
+            syn::UseTree::Group(g) => g.items.iter().for_each(|t| self.use_tree(t, prefix)),
+            syn::UseTree::Glob(_) => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for HandlerPaths {
+    fn visit_expr_path(&mut self, p: &'ast syn::ExprPath) {
+        self.push(
+            p.path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect(),
+        );
+        syn::visit::visit_expr_path(self, p);
+    }
+
+    fn visit_item_use(&mut self, u: &'ast syn::ItemUse) {
+        self.use_tree(&u.tree, &mut vec![]);
+    }
+}
+
+/// Subdirectories of the directory, sorted
+fn module_dirs(dir: &Path) -> Result<Vec<PathBuf>, AlxError> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut dirs = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Names of the modules declared with `mod name;` in the file, `None` if it doesn't parse
+fn declared_mods(mod_file: &Path, diagnostics: &mut Vec<Diagnostic>) -> Option<Vec<String>> {
+    let Ok(src) = fs::read_to_string(mod_file) else {
+        return Some(vec![]);
+    };
+    let file = parse(mod_file, &src, diagnostics)?;
+    let mods = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Mod(m) if m.content.is_none() => Some(m.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    Some(mods)
+}
+
+/// Parses the file. One that doesn't parse is reported, once, and the checks needing it are skipped
+/// so the rest of the project is still checked.
+fn parse(path: &Path, src: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<syn::File> {
+    let e = match syn::parse_file(src) {
+        Ok(file) => return Some(file),
+        Err(e) => e,
+    };
+    let start = e.span().start();
+    let diagnostic = Diagnostic::new(
+        path,
+        format!(
+            "can't be parsed, {e} at {}:{}",
+            start.line,
+            start.column + 1
+        ),
+        None,
+    );
+    if !diagnostics
+        .iter()
+        .any(|d| d.file == diagnostic.file && d.message == diagnostic.message)
+    {
+        diagnostics.push(diagnostic);
+    }
+    None
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether the names are at most 2 edits apart, used to spot typos in file names
+fn similar(a: &str, b: &str) -> bool {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()] <= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_handler_paths() {
+        let src = r#"
+            use super::{domain::UserService, handler};
+            use super::handler::favicon::favicon;
+            pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
+                cfg.service(web::resource("/users").route(web::get().to(handler::get_paginated::<S>)));
+            }
+        "#;
+        let mut visitor = HandlerPaths::default();
+        visitor.visit_file(&syn::parse_file(src).unwrap());
+        assert_eq!(
+            visitor.paths,
+            [vec!["favicon", "favicon"], vec!["get_paginated"]]
+        );
+        assert!(similar("infratructure", "infrastructure"));
+        assert!(!similar("domain", "handler"));
+    }
+
+    #[test]
+    fn reports_unparsable_files_and_carries_on() {
+        let api = std::env::temp_dir().join("alx_doctor_parse_test/api");
+        let users = api.join("router/users");
+        fs::create_dir_all(&users).unwrap();
+        fs::write(
+            api.join("router/mod.rs"),
+            "pub(crate) mod users;\npub(crate) mod {\n",
+        )
+        .unwrap();
+        fs::write(users.join("mod.rs"), "mod setup;\n").unwrap();
+        fs::write(
+            users.join("setup.rs"),
+            "fn routes(cfg: &mut Config) { cfg.route(handler::get); }\n",
+        )
+        .unwrap();
+        fs::write(users.join("handler.rs"), "fn get(\n").unwrap();
+
+        let configure = api.join("configure.rs");
+        let found = check(api.to_str().unwrap(), configure.to_str().unwrap()).unwrap();
+        let unparsable = found
+            .iter()
+            .filter(|d| d.message.starts_with("can't be parsed"))
+            .map(|d| Path::new(&d.file).file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        // The router's mod.rs is read for every route but reported once
+        assert_eq!(unparsable, ["mod.rs", "handler.rs"]);
+        // The files that did parse are still checked, the modules of those that didn't aren't
+        assert!(!found.iter().any(|d| d.message == "users is not declared"));
+        assert!(found
+            .iter()
+            .any(|d| d.file.ends_with("domain.rs") && d.message == "file not found"));
+    }
+}
@@ -0,0 +1,623 @@
+//! Dependency graph of the API. Routes lead to their handlers and the middleware wrapping them,
+//! handlers to the service they're registered with and the service through its contracts to the
+//! infrastructure implementing them, the adapters those use and finally the clients. The concrete
+//! types come from the turbofish of the handlers in `setup.rs`, i.e.
+//! `handler::login::<Authentication<Repository<PgUserAdapter>, Cache>>`, the contracts and
+//! repositories from the bounds of the generics in `domain.rs` and `infrastructure.rs`, and the
+//! clients from the `routes` parameters the types are constructed with.
+use super::analyze::project_config;
+use crate::{config::Endpoint, error::AlxError, settings::settings};
+use quote::ToTokens;
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+use syn::{punctuated::Punctuated, visit::Visit};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Route,
+    Handler,
+    Middleware,
+    Service,
+    Contract,
+    Infrastructure,
+    Adapter,
+    Client,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+    pub kind: NodeKind,
+    /// The endpoint the node belongs to, `None` for the ones shared between endpoints
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    /// From, to and label
+    pub edges: Vec<(String, String, Option<String>)>,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz
+    Dot,
+    Mermaid,
+}
+
+/// Builds the graph of the API directory
+pub fn graph(api_path: &str) -> Result<Graph, AlxError> {
+    let pc = project_config(api_path)?;
+    let mut graph = Graph::default();
+    for ep in pc.endpoints.iter() {
+        let dir = Path::new(&settings().paths.router_of(api_path)).join(&ep.name);
+        let parse = |file: &str| -> Result<Option<syn::File>, AlxError> {
+            match fs::read_to_string(dir.join(file)) {
+                Ok(src) => Ok(Some(syn::parse_file(&src)?)),
+                Err(_) => Ok(None),
+            }
+        };
+        graph.endpoint(
+            ep,
+            parse("setup.rs")?.as_ref(),
+            parse("domain.rs")?.as_ref(),
+            parse("infrastructure.rs")?.as_ref(),
+        );
+    }
+    Ok(graph)
+}
+
+impl Graph {
+    fn node(&mut self, id: String, label: &str, kind: NodeKind, endpoint: Option<&str>) -> String {
+        if !self.nodes.iter().any(|n| n.id == id) {
+            self.nodes.push(Node {
+                id: id.clone(),
+                label: label.to_string(),
+                kind,
+                endpoint: endpoint.map(str::to_string),
+            });
+        }
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>) {
+        if !self.edges.iter().any(|(f, t, _)| f == from && t == to) {
+            self.edges
+                .push((from.to_string(), to.to_string(), label.map(str::to_string)));
+        }
+    }
+
+    /// Adds the endpoint's routes and everything they lead to
+    fn endpoint(
+        &mut self,
+        ep: &Endpoint,
+        setup: Option<&syn::File>,
+        domain: Option<&syn::File>,
+        infrastructure: Option<&syn::File>,
+    ) {
+        let setup = setup.map(SetupInfo::new).unwrap_or_default();
+        let name = ep.name.as_str();
+
+        for (i, route) in ep.routes.iter().enumerate() {
+            let label = format!("{} {}", route.method, route.path);
+            let route_id = self.node(
+                format!("{name}_route_{i}"),
+                &label,
+                NodeKind::Route,
+                Some(name),
+            );
+
+            for mw in route.middleware.iter().flatten() {
+                let (ty, clients) = match setup.locals.get(mw) {
+                    Some((ty, clients)) => (ty.as_str(), clients.clone()),
+                    None => (mw.as_str(), vec![]),
+                };
+                let mw_id = self.node(format!("mw_{}", id(ty)), ty, NodeKind::Middleware, None);
+                self.edge(&route_id, &mw_id, Some("wrap"));
+                for client in clients {
+                    self.client(&mw_id, &setup, &client);
+                }
+            }
+
+            let Some(ref handler) = route.handler else {
+                continue;
+            };
+            let handler_id = self.node(
+                format!("{name}_handler_{}", handler.name),
+                &handler.name,
+                NodeKind::Handler,
+                Some(name),
+            );
+            self.edge(&route_id, &handler_id, None);
+
+            let Some(service) = setup.services.get(&handler.name) else {
+                continue;
+            };
+            let (service_name, args) = type_args(service);
+            let service_id = self.node(
+                format!("{name}_service_{}", id(&service_name)),
+                &service_name,
+                NodeKind::Service,
+                Some(name),
+            );
+            self.edge(&handler_id, &service_id, None);
+
+            let contracts = domain
+                .map(|d| generic_bounds(d, &service_name))
+                .unwrap_or_default();
+            for (j, arg) in args.iter().enumerate() {
+                let (infra, adapters) = type_args(arg);
+                let infra_id = self.node(
+                    format!("{name}_infra_{}", id(&infra)),
+                    &infra,
+                    NodeKind::Infrastructure,
+                    Some(name),
+                );
+                match contracts.get(j).and_then(|(_, c)| c.as_ref()) {
+                    Some(contract) => {
+                        let contract_id = self.node(
+                            format!("{name}_contract_{}", id(contract)),
+                            contract,
+                            NodeKind::Contract,
+                            Some(name),
+                        );
+                        self.edge(&service_id, &contract_id, None);
+                        self.edge(&contract_id, &infra_id, Some("impl"));
+                    }
+                    None => self.edge(&service_id, &infra_id, None),
+                }
+                self.constructed_with(&infra_id, &setup, &infra);
+
+                let repositories = infrastructure
+                    .map(|i| generic_bounds(i, &infra))
+                    .unwrap_or_default();
+                for (k, adapter) in adapters.iter().enumerate() {
+                    let bound = repositories.get(k).and_then(|(_, b)| b.as_deref());
+                    self.adapter(&infra_id, &setup, adapter, bound);
+                }
+            }
+        }
+    }
+
+    /// Adds the adapter, its clients and the adapters it's generic over
+    fn adapter(&mut self, from: &str, setup: &SetupInfo, ty: &syn::Type, bound: Option<&str>) {
+        let (name, args) = type_args(ty);
+        let adapter_id = self.node(
+            format!("adapter_{}", id(&name)),
+            &name,
+            NodeKind::Adapter,
+            None,
+        );
+        self.edge(from, &adapter_id, bound);
+        self.constructed_with(&adapter_id, setup, &name);
+        for arg in args.iter() {
+            self.adapter(&adapter_id, setup, arg, None);
+        }
+    }
+
+    /// Adds edges to the clients the struct is constructed with in `routes`
+    fn constructed_with(&mut self, from: &str, setup: &SetupInfo, ty: &str) {
+        for client in setup.structs.get(ty).cloned().unwrap_or_default() {
+            self.client(from, setup, &client);
+        }
+    }
+
+    fn client(&mut self, from: &str, setup: &SetupInfo, param: &str) {
+        let Some(ty) = setup.clients.get(param) else {
+            return;
+        };
+        let client_id = self.node(format!("client_{}", id(ty)), ty, NodeKind::Client, None);
+        self.edge(from, &client_id, None);
+    }
+
+    fn endpoints(&self) -> Vec<&str> {
+        let mut endpoints = vec![];
+        for n in self.nodes.iter() {
+            if let Some(ref ep) = n.endpoint {
+                if !endpoints.contains(&ep.as_str()) {
+                    endpoints.push(ep.as_str());
+                }
+            }
+        }
+        endpoints
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.dot(),
+            GraphFormat::Mermaid => self.mermaid(),
+        }
+    }
+
+    fn dot(&self) -> String {
+        let mut buf =
+            String::from("digraph alx {\n    rankdir=LR;\n    node [fontname=\"Helvetica\"];\n");
+        let node = |buf: &mut String, n: &Node, indent: &str| {
+            let shape = match n.kind {
+                NodeKind::Route => "box",
+                NodeKind::Handler => "ellipse",
+                NodeKind::Middleware => "hexagon",
+                NodeKind::Service => "component",
+                NodeKind::Contract => "note",
+                NodeKind::Infrastructure => "box3d",
+                NodeKind::Adapter => "parallelogram",
+                NodeKind::Client => "cylinder",
+            };
+            writeln!(
+                buf,
+                "{indent}{} [label={:?}, shape={shape}];",
+                n.id, n.label
+            )
+            .unwrap();
+        };
+        for ep in self.endpoints() {
+            writeln!(
+                buf,
+                "    subgraph cluster_{} {{\n        label={ep:?};",
+                id(ep)
+            )
+            .unwrap();
+            for n in self
+                .nodes
+                .iter()
+                .filter(|n| n.endpoint.as_deref() == Some(ep))
+            {
+                node(&mut buf, n, "        ");
+            }
+            writeln!(buf, "    }}").unwrap();
+        }
+        for n in self.nodes.iter().filter(|n| n.endpoint.is_none()) {
+            node(&mut buf, n, "    ");
+        }
+        for (from, to, label) in self.edges.iter() {
+            match label {
+                Some(l) => writeln!(buf, "    {from} -> {to} [label={l:?}];").unwrap(),
+                None => writeln!(buf, "    {from} -> {to};").unwrap(),
+            }
+        }
+        buf.push_str("}\n");
+        buf
+    }
+
+    fn mermaid(&self) -> String {
+        let mut buf = String::from("flowchart LR\n");
+        let node = |buf: &mut String, n: &Node, indent: &str| {
+            let label = n.label.replace('"', "#quot;");
+            let (open, close) = match n.kind {
+                NodeKind::Route => ("[", "]"),
+                NodeKind::Handler => ("(", ")"),
+                NodeKind::Middleware => ("{{", "}}"),
+                NodeKind::Service => ("[[", "]]"),
+                NodeKind::Contract => (">", "]"),
+                NodeKind::Infrastructure => ("[/", "/]"),
+                NodeKind::Adapter => ("[\\", "\\]"),
+                NodeKind::Client => ("[(", ")]"),
+            };
+            writeln!(buf, "{indent}{}{open}\"{label}\"{close}", n.id).unwrap();
+        };
+        for ep in self.endpoints() {
+            writeln!(buf, "    subgraph {}[\"{ep}\"]", id(ep)).unwrap();
+            for n in self
+                .nodes
+                .iter()
+                .filter(|n| n.endpoint.as_deref() == Some(ep))
+            {
+                node(&mut buf, n, "        ");
+            }
+            writeln!(buf, "    end").unwrap();
+        }
+        for n in self.nodes.iter().filter(|n| n.endpoint.is_none()) {
+            node(&mut buf, n, "    ");
+        }
+        for (from, to, label) in self.edges.iter() {
+            match label {
+                Some(l) => writeln!(buf, "    {from} -->|{l}| {to}").unwrap(),
+                None => writeln!(buf, "    {from} --> {to}").unwrap(),
+            }
+        }
+        buf
+    }
+}
+
+/// What `routes` in setup.rs tells about the types of the endpoint
+#[derive(Debug, Default)]
+struct SetupInfo {
+    /// Client parameters and their types, i.e. `pg` and `Postgres` for `pg: Arc<Postgres>`
+    clients: HashMap<String, String>,
+    /// Handlers and the service type they're registered with
+    services: HashMap<String, syn::Type>,
+    /// Struct names and the client parameters they're constructed with
+    structs: HashMap<String, Vec<String>>,
+    /// Locals constructed with a call, i.e. middleware, with their type and the clients passed in
+    locals: HashMap<String, (String, Vec<String>)>,
+}
+
+impl SetupInfo {
+    fn new(file: &syn::File) -> Self {
+        let mut info = Self::default();
+        let Some(routes) = file.items.iter().find_map(|item| match item {
+            syn::Item::Fn(f) if f.sig.ident == "routes" => Some(f),
+            _ => None,
+        }) else {
+            return info;
+        };
+        for arg in routes.sig.inputs.iter() {
+            let syn::FnArg::Typed(t) = arg else {
+                continue;
+            };
+            if let (syn::Pat::Ident(ref param), Some(ty)) = (&*t.pat, innermost(&t.ty)) {
+                if ty != "ServiceConfig" {
+                    info.clients.insert(param.ident.to_string(), ty);
+                }
+            }
+        }
+        info.visit_block(&routes.block);
+        info
+    }
+
+    /// The client parameters mentioned in the expression, not counting nested struct literals
+    fn clients_in(&self, expr: &syn::Expr) -> Vec<String> {
+        let mut idents = Idents::default();
+        idents.visit_expr(expr);
+        let mut clients = vec![];
+        for ident in idents.0 {
+            if self.clients.contains_key(&ident) && !clients.contains(&ident) {
+                clients.push(ident);
+            }
+        }
+        clients
+    }
+}
+
+impl<'ast> Visit<'ast> for SetupInfo {
+    fn visit_expr_path(&mut self, p: &'ast syn::ExprPath) {
+        let segments = p.path.segments.iter().collect::<Vec<_>>();
+        if segments.iter().any(|s| s.ident == "handler") {
+            if let Some(last) = segments.last() {
+                if let syn::PathArguments::AngleBracketed(ref args) = last.arguments {
+                    if let Some(syn::GenericArgument::Type(ty)) = args.args.first() {
+                        self.services.insert(last.ident.to_string(), ty.clone());
+                    }
+                }
+            }
+        }
+        syn::visit::visit_expr_path(self, p);
+    }
+
+    fn visit_expr_struct(&mut self, s: &'ast syn::ExprStruct) {
+        if let Some(last) = s.path.segments.last() {
+            let clients = s
+                .fields
+                .iter()
+                .flat_map(|f| self.clients_in(&f.expr))
+                .collect::<Vec<_>>();
+            self.structs
+                .entry(last.ident.to_string())
+                .or_default()
+                .extend(clients);
+        }
+        syn::visit::visit_expr_struct(self, s);
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let (syn::Pat::Ident(ref name), Some((_, ref init))) = (&local.pat, &local.init) {
+            if let syn::Expr::Call(ref call) = **init {
+                if let syn::Expr::Path(ref func) = *call.func {
+                    // `interceptor::AuthGuard::new(..)` is an `AuthGuard`
+                    let segments = func.path.segments.iter().collect::<Vec<_>>();
+                    let ty = match segments.as_slice() {
+                        [.., ty, _] => ty.ident.to_string(),
+                        [ty] => ty.ident.to_string(),
+                        [] => String::new(),
+                    };
+                    let clients = call.args.iter().flat_map(|a| self.clients_in(a)).collect();
+                    self.locals.insert(name.ident.to_string(), (ty, clients));
+                }
+            }
+        }
+        syn::visit::visit_local(self, local);
+    }
+}
+
+/// Single segment paths in an expression, skipping nested struct literals
+#[derive(Default)]
+struct Idents(Vec<String>);
+
+impl<'ast> Visit<'ast> for Idents {
+    fn visit_expr_path(&mut self, p: &'ast syn::ExprPath) {
+        if let Some(ident) = p.path.get_ident() {
+            self.0.push(ident.to_string());
+        }
+    }
+
+    fn visit_expr_struct(&mut self, _: &'ast syn::ExprStruct) {}
+}
+
+/// The name of the type and its type arguments, i.e. `Repository` and `[PgUserAdapter]` for
+/// `Repository<PgUserAdapter>`
+fn type_args(ty: &syn::Type) -> (String, Vec<syn::Type>) {
+    let syn::Type::Path(p) = ty else {
+        return (ty.to_token_stream().to_string(), vec![]);
+    };
+    let Some(last) = p.path.segments.last() else {
+        return (String::new(), vec![]);
+    };
+    let args = match last.arguments {
+        syn::PathArguments::AngleBracketed(ref args) => args
+            .args
+            .iter()
+            .filter_map(|a| match a {
+                syn::GenericArgument::Type(t) => Some(t.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    (last.ident.to_string(), args)
+}
+
+/// The innermost type of wrappers like `Arc<..>` or `&mut ..`, i.e. `Postgres` for `Arc<Postgres>`
+fn innermost(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Reference(r) => innermost(&r.elem),
+        syn::Type::Path(_) => {
+            let (name, args) = type_args(ty);
+            match (name.as_str(), args.as_slice()) {
+                ("Arc" | "Rc" | "Box", [inner]) => innermost(inner),
+                _ => Some(name),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The type parameters of the struct and the trait each is bound by, inline or in the where clause.
+/// Auto traits and the like aren't counted.
+fn generic_bounds(file: &syn::File, name: &str) -> Vec<(String, Option<String>)> {
+    let Some(s) = file.items.iter().find_map(|item| match item {
+        syn::Item::Struct(s) if s.ident == name => Some(s),
+        _ => None,
+    }) else {
+        return vec![];
+    };
+    let bound = |bounds: &Punctuated<syn::TypeParamBound, syn::Token![+]>| {
+        bounds.iter().find_map(|b| match b {
+            syn::TypeParamBound::Trait(t) => t
+                .path
+                .segments
+                .last()
+                .map(|s| s.ident.to_string())
+                .filter(|s| !matches!(s.as_str(), "Send" | "Sync" | "Clone" | "Debug" | "Sized")),
+            _ => None,
+        })
+    };
+    s.generics
+        .type_params()
+        .map(|param| {
+            let inline = bound(&param.bounds);
+            let in_where = s
+                .generics
+                .where_clause
+                .iter()
+                .flat_map(|w| w.predicates.iter())
+                .find_map(|p| match p {
+                    syn::WherePredicate::Type(t)
+                        if param.ident == t.bounded_ty.to_token_stream().to_string() =>
+                    {
+                        bound(&t.bounds)
+                    }
+                    _ => None,
+                });
+            (param.ident.to_string(), inline.or(in_where))
+        })
+        .collect()
+}
+
+/// A valid node id for both formats
+fn id(s: &str) -> String {
+    s.chars()
+        .map(|c| match c.is_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+
+    #[test]
+    fn builds_graph() {
+        let pc = ProjectConfig::parse(
+            r#"
+endpoints:
+  - name: auth
+    routes:
+      - method: POST
+        path: /auth/logout
+        handler:
+          name: logout
+          bound: null
+        middleware: [auth_guard]
+        service: Authentication
+        input: null
+"#
+            .to_string(),
+        )
+        .unwrap();
+        let setup = syn::parse_file(
+            r#"
+pub(crate) fn routes(pg: Arc<Postgres>, rd: Arc<Redis>, cfg: &mut web::ServiceConfig) {
+    let service = Authentication {
+        repository: Repository {
+            user_repo: PgUserAdapter { client: pg.clone() },
+        },
+        cache: Cache { client: rd.clone() },
+    };
+    let auth_guard = interceptor::AuthGuard::new(pg, rd, Role::User);
+    cfg.service(
+        web::resource("/auth/logout")
+            .route(web::post().to(handler::logout::<Authentication<Repository<PgUserAdapter>, Cache>>))
+            .wrap(auth_guard),
+    );
+}
+"#,
+        )
+        .unwrap();
+        let domain = syn::parse_file(
+            "pub(super) struct Authentication<R: RepositoryContract, C> where C: CacheContract + Send { pub repository: R, pub cache: C }",
+        )
+        .unwrap();
+        let infrastructure = syn::parse_file(
+            "pub(super) struct Repository<UR> where UR: UserRepository { pub user_repo: UR }",
+        )
+        .unwrap();
+
+        let mut graph = Graph::default();
+        graph.endpoint(
+            &pc.endpoints[0],
+            Some(&setup),
+            Some(&domain),
+            Some(&infrastructure),
+        );
+        let edges = graph
+            .edges
+            .iter()
+            .map(|(f, t, l)| {
+                format!(
+                    "{f} -> {t}{}",
+                    l.as_deref().map(|l| format!(" ({l})")).unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            [
+                "auth_route_0 -> mw_AuthGuard (wrap)",
+                "mw_AuthGuard -> client_Postgres",
+                "mw_AuthGuard -> client_Redis",
+                "auth_route_0 -> auth_handler_logout",
+                "auth_handler_logout -> auth_service_Authentication",
+                "auth_service_Authentication -> auth_contract_RepositoryContract",
+                "auth_contract_RepositoryContract -> auth_infra_Repository (impl)",
+                "auth_infra_Repository -> adapter_PgUserAdapter (UserRepository)",
+                "adapter_PgUserAdapter -> client_Postgres",
+                "auth_service_Authentication -> auth_contract_CacheContract",
+                "auth_contract_CacheContract -> auth_infra_Cache (impl)",
+                "auth_infra_Cache -> client_Redis",
+            ]
+        );
+        let mermaid = graph.render(GraphFormat::Mermaid);
+        assert!(mermaid.contains(
+            "    subgraph auth[\"auth\"]\n        auth_route_0[\"POST /auth/logout\"]\n"
+        ));
+        assert!(mermaid.contains("    client_Postgres[(\"Postgres\")]\n"));
+        let dot = graph.render(GraphFormat::Dot);
+        assert!(dot.contains(
+            "    auth_infra_Repository -> adapter_PgUserAdapter [label=\"UserRepository\"];\n"
+        ));
+    }
+}
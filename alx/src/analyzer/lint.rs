@@ -0,0 +1,500 @@
+//! Checks the files of each route keep to the layers of the ports and adapters architecture. The
+//! domain only depends on its contracts and data, handlers only call the service contract, contracts
+//! don't name concrete adapters and the infrastructure doesn't build responses.
+use crate::{error::AlxError, settings::settings};
+use quote::ToTokens;
+use serde::Deserialize;
+use std::{collections::HashSet, fmt::Display, fs, path::PathBuf};
+use syn::{spanned::Spanned, visit::Visit};
+
+/// The lint config, by default read from `.alx/lint.yaml`. Rules missing from it keep their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LintConfig {
+    pub domain_dependencies: RuleConfig,
+    pub handler_calls: RuleConfig,
+    pub contract_types: RuleConfig,
+    pub infrastructure_responses: RuleConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    pub enabled: bool,
+    /// Replaces the default forbidden paths of the rule. `a::b` matches paths containing the
+    /// segments in order, a leading or trailing `*` in a segment matches by suffix or prefix.
+    pub forbidden: Option<Vec<String>>,
+}
+
+impl Default for RuleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            forbidden: None,
+        }
+    }
+}
+
+impl LintConfig {
+    fn rule(&self, rule: Rule) -> &RuleConfig {
+        match rule {
+            Rule::DomainDependencies => &self.domain_dependencies,
+            Rule::HandlerCalls => &self.handler_calls,
+            Rule::ContractTypes => &self.contract_types,
+            Rule::InfrastructureResponses => &self.infrastructure_responses,
+        }
+    }
+}
+
+/// Patterns for the infrastructure the outer layers shouldn't reach into
+const ADAPTERS: [&str; 6] = [
+    "infrastructure::clients",
+    "infrastructure::store::adapters",
+    "*Adapter",
+    "redis::*Commands",
+    "diesel",
+    "lettre",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// domain.rs depends only on contract.rs and data.rs, not on clients or adapters
+    DomainDependencies,
+    /// handler.rs only calls the service through its contract
+    HandlerCalls,
+    /// contract.rs doesn't name concrete adapter types
+    ContractTypes,
+    /// infrastructure.rs doesn't return HTTP responses
+    InfrastructureResponses,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 4] = [
+        Rule::DomainDependencies,
+        Rule::HandlerCalls,
+        Rule::ContractTypes,
+        Rule::InfrastructureResponses,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::DomainDependencies => "domain-dependencies",
+            Rule::HandlerCalls => "handler-calls",
+            Rule::ContractTypes => "contract-types",
+            Rule::InfrastructureResponses => "infrastructure-responses",
+        }
+    }
+
+    /// The route file the rule applies to
+    fn file(&self) -> &'static str {
+        match self {
+            Rule::DomainDependencies => "domain.rs",
+            Rule::HandlerCalls => "handler.rs",
+            Rule::ContractTypes => "contract.rs",
+            Rule::InfrastructureResponses => "infrastructure.rs",
+        }
+    }
+
+    fn default_forbidden(&self) -> Vec<String> {
+        let patterns: &[&str] = match self {
+            Rule::DomainDependencies => {
+                &["super::infrastructure", "super::handler", "super::setup"]
+            }
+            Rule::HandlerCalls => &["super::infrastructure", "super::domain"],
+            Rule::ContractTypes => &["super::infrastructure"],
+            Rule::InfrastructureResponses => {
+                return ["HttpResponse", "HttpResponseBuilder", "*Responder"]
+                    .map(String::from)
+                    .to_vec()
+            }
+        };
+        patterns
+            .iter()
+            .chain(ADAPTERS.iter())
+            .map(|p| p.to_string())
+            .collect()
+    }
+}
+
+/// A rule violation at a line of a file
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} [{}]",
+            self.file,
+            self.line,
+            self.message,
+            self.rule.id()
+        )
+    }
+}
+
+pub fn config(path: &str) -> Result<LintConfig, AlxError> {
+    match fs::read_to_string(path) {
+        Ok(src) => Ok(serde_yaml::from_str(&src)?),
+        Err(_) => Ok(LintConfig::default()),
+    }
+}
+
+/// Lints every route in the router, sorted by file and line
+pub fn lint(api_path: &str, config: &LintConfig) -> Result<Vec<Finding>, AlxError> {
+    let router = PathBuf::from(settings().paths.router_of(api_path));
+    let mut dirs = fs::read_dir(&router)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect::<Vec<_>>();
+    dirs.sort();
+
+    let mut findings = vec![];
+    for dir in dirs {
+        for rule in Rule::ALL {
+            let rule_config = config.rule(rule);
+            if !rule_config.enabled {
+                continue;
+            }
+            let path = dir.join(rule.file());
+            let Ok(src) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let file = syn::parse_file(&src)?;
+            let forbidden = rule_config
+                .forbidden
+                .clone()
+                .unwrap_or_else(|| rule.default_forbidden());
+            let contract = match rule {
+                Rule::HandlerCalls => fs::read_to_string(dir.join("contract.rs"))
+                    .ok()
+                    .and_then(|src| syn::parse_file(&src).ok()),
+                _ => None,
+            };
+            findings.extend(check(
+                rule,
+                &path.display().to_string(),
+                &file,
+                &forbidden,
+                contract.as_ref(),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+/// Checks the file against the rule. `contract` is the route's contract.rs, used by the handler rule.
+pub fn check(
+    rule: Rule,
+    path: &str,
+    file: &syn::File,
+    forbidden: &[String],
+    contract: Option<&syn::File>,
+) -> Vec<Finding> {
+    let mut paths = Paths::default();
+    paths.visit_file(file);
+
+    let mut findings = vec![];
+    let mut seen = HashSet::new();
+    for (segments, line) in paths.0 {
+        let Some(pattern) = forbidden.iter().find(|p| matches(p, &segments)) else {
+            continue;
+        };
+        let path_str = segments.join("::");
+        if !seen.insert((line, path_str.clone())) {
+            continue;
+        }
+        let message = match rule {
+            Rule::DomainDependencies => format!("domain depends on `{path_str}` ({pattern})"),
+            Rule::HandlerCalls => format!("handler depends on `{path_str}` ({pattern})"),
+            Rule::ContractTypes => format!("contract names concrete type `{path_str}` ({pattern})"),
+            Rule::InfrastructureResponses => {
+                format!("infrastructure uses `{path_str}`, responses belong in the domain")
+            }
+        };
+        findings.push(Finding {
+            rule,
+            file: path.to_string(),
+            line,
+            message,
+        });
+    }
+
+    if let (Rule::HandlerCalls, Some(contract)) = (rule, contract) {
+        let methods = service_methods(contract);
+        let mut calls = ServiceCalls::default();
+        calls.visit_file(file);
+        for (method, line) in calls.calls {
+            if !methods.contains(&method) {
+                findings.push(Finding {
+                    rule,
+                    file: path.to_string(),
+                    line,
+                    message: format!("handler calls `{method}` which is not in ServiceContract"),
+                });
+            }
+        }
+        for (field, line) in calls.fields {
+            findings.push(Finding {
+                rule,
+                file: path.to_string(),
+                line,
+                message: format!("handler reaches into the service's `{field}`"),
+            });
+        }
+    }
+    findings.sort_by_key(|f| f.line);
+    findings
+}
+
+/// Whether the pattern's segments appear in order in the path
+pub(crate) fn matches(pattern: &str, path: &[String]) -> bool {
+    let pattern = pattern.split("::").collect::<Vec<_>>();
+    path.windows(pattern.len()).any(|window| {
+        window.iter().zip(pattern.iter()).all(|(seg, pat)| {
+            match (pat.strip_prefix('*'), pat.strip_suffix('*')) {
+                (Some(suffix), _) => seg.ends_with(suffix),
+                (_, Some(prefix)) => seg.starts_with(prefix),
+                _ => seg == pat,
+            }
+        })
+    })
+}
+
+/// Every path in the file with its line. `use` trees are expanded to one path per imported item.
+#[derive(Default)]
+struct Paths(Vec<(Vec<String>, usize)>);
+
+impl Paths {
+    fn use_tree(&mut self, prefix: &mut Vec<String>, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                self.use_tree(prefix, &p.tree);
+                prefix.pop();
+            }
+            syn::UseTree::Name(n) => self.push_use(prefix, &n.ident),
+            syn::UseTree::Rename(r) => self.push_use(prefix, &r.ident),
+            syn::UseTree::Glob(g) => self.0.push((prefix.clone(), g.span().start().line)),
+            syn::UseTree::Group(g) => g.items.iter().for_each(|t| self.use_tree(prefix, t)),
+        }
+    }
+
+    fn push_use(&mut self, prefix: &[String], ident: &syn::Ident) {
+        let mut path = prefix.to_vec();
+        if ident != "self" {
+            path.push(ident.to_string());
+        }
+        self.0.push((path, ident.span().start().line));
+    }
+}
+
+impl<'ast> Visit<'ast> for Paths {
+    fn visit_item_use(&mut self, u: &'ast syn::ItemUse) {
+        self.use_tree(&mut vec![], &u.tree);
+    }
+
+    fn visit_path(&mut self, p: &'ast syn::Path) {
+        let segments = p.segments.iter().map(|s| s.ident.to_string()).collect();
+        self.0.push((segments, p.span().start().line));
+        syn::visit::visit_path(self, p);
+    }
+}
+
+/// The methods declared by the ServiceContract trait
+fn service_methods(contract: &syn::File) -> Vec<String> {
+    contract
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Trait(t) if t.ident == "ServiceContract" => Some(t),
+            _ => None,
+        })
+        .flat_map(|t| t.items.iter())
+        .filter_map(|item| match item {
+            syn::TraitItem::Method(m) => Some(m.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Method calls on handler parameters whose type is bound by ServiceContract, i.e. `service` in
+/// `fn handler<T: ServiceContract>(service: web::Data<T>)`
+#[derive(Default)]
+struct ServiceCalls {
+    services: Vec<String>,
+    calls: Vec<(String, usize)>,
+    fields: Vec<(String, usize)>,
+}
+
+/// Methods of the `web::Data` wrapping the service
+const DATA_METHODS: [&str; 3] = ["get_ref", "into_inner", "clone"];
+
+impl ServiceCalls {
+    fn is_service(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Path(p) => p
+                .path
+                .get_ident()
+                .is_some_and(|i| self.services.contains(&i.to_string())),
+            syn::Expr::MethodCall(m) if DATA_METHODS.contains(&m.method.to_string().as_str()) => {
+                self.is_service(&m.receiver)
+            }
+            syn::Expr::Paren(p) => self.is_service(&p.expr),
+            syn::Expr::Reference(r) => self.is_service(&r.expr),
+            _ => false,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ServiceCalls {
+    fn visit_item_fn(&mut self, f: &'ast syn::ItemFn) {
+        let mut bound = vec![];
+        for param in f.sig.generics.type_params() {
+            if bounded_by_contract(param.bounds.iter()) {
+                bound.push(param.ident.to_string());
+            }
+        }
+        if let Some(ref where_clause) = f.sig.generics.where_clause {
+            for pred in where_clause.predicates.iter() {
+                if let syn::WherePredicate::Type(t) = pred {
+                    if bounded_by_contract(t.bounds.iter()) {
+                        if let syn::Type::Path(ref p) = t.bounded_ty {
+                            bound.extend(p.path.get_ident().map(|i| i.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+        self.services = f
+            .sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Typed(t) => Some(t),
+                syn::FnArg::Receiver(_) => None,
+            })
+            .filter(|t| {
+                let mut paths = Paths::default();
+                paths.visit_type(&t.ty);
+                paths
+                    .0
+                    .iter()
+                    .any(|(p, _)| p.len() == 1 && bound.contains(&p[0]))
+            })
+            .filter_map(|t| match *t.pat {
+                syn::Pat::Ident(ref i) => Some(i.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        syn::visit::visit_item_fn(self, f);
+        self.services.clear();
+    }
+
+    fn visit_expr_method_call(&mut self, m: &'ast syn::ExprMethodCall) {
+        let method = m.method.to_string();
+        if self.is_service(&m.receiver) && !DATA_METHODS.contains(&method.as_str()) {
+            self.calls.push((method, m.method.span().start().line));
+        }
+        syn::visit::visit_expr_method_call(self, m);
+    }
+
+    fn visit_expr_field(&mut self, f: &'ast syn::ExprField) {
+        if self.is_service(&f.base) {
+            let field = f.member.to_token_stream().to_string();
+            self.fields.push((field, f.member.span().start().line));
+        }
+        syn::visit::visit_expr_field(self, f);
+    }
+}
+
+fn bounded_by_contract<'a>(mut bounds: impl Iterator<Item = &'a syn::TypeParamBound>) -> bool {
+    bounds.any(|b| match b {
+        syn::TypeParamBound::Trait(t) => t
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "ServiceContract"),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_layer_violations_with_lines() {
+        let domain = syn::parse_file(
+            "use super::{contract::RepositoryContract, infrastructure::Cache};\n\
+             use infrastructure::store::adapters::postgres::user::PgUserAdapter;\n\
+             use infrastructure::clients::store::redis::Redis;\n\
+             fn f(a: &PgUserAdapter) -> crate::error::Error { todo!() }\n",
+        )
+        .unwrap();
+        let lines = |findings: Vec<Finding>| findings.iter().map(|f| f.line).collect::<Vec<_>>();
+        let findings = check(
+            Rule::DomainDependencies,
+            "domain.rs",
+            &domain,
+            &Rule::DomainDependencies.default_forbidden(),
+            None,
+        );
+        assert_eq!(lines(findings), [1, 2, 3, 4]);
+
+        let contract = syn::parse_file(
+            "#[async_trait]\npub(super) trait ServiceContract {\n\
+             async fn login(&self, c: Credentials) -> Result<HttpResponse, Error>;\n}\n",
+        )
+        .unwrap();
+        let handler = syn::parse_file(
+            "pub(super) async fn login<T>(service: web::Data<T>) -> Result<impl Responder, Error>\n\
+             where\n    T: ServiceContract,\n{\n\
+             service.login(c).await;\n\
+             service.get_ref().repository.user_repo.get(1);\n\
+             service.cache.delete().await\n}\n",
+        )
+        .unwrap();
+        let findings = check(
+            Rule::HandlerCalls,
+            "handler.rs",
+            &handler,
+            &[],
+            Some(&contract),
+        );
+        assert_eq!(lines(findings), [6, 7]);
+        let handler = syn::parse_file(
+            "async fn verify<T: ServiceContract>(service: web::Data<T>) {\n\
+             service.verify(c).await\n}\n",
+        )
+        .unwrap();
+        let findings = check(
+            Rule::HandlerCalls,
+            "handler.rs",
+            &handler,
+            &[],
+            Some(&contract),
+        );
+        assert_eq!(
+            findings[0].to_string(),
+            "handler.rs:2: handler calls `verify` which is not in ServiceContract [handler-calls]"
+        );
+
+        let infrastructure = syn::parse_file("fn a() -> Result<HttpResponse, Error> {}").unwrap();
+        let forbidden = vec!["HttpResponse".to_string()];
+        let findings = check(
+            Rule::InfrastructureResponses,
+            "infrastructure.rs",
+            &infrastructure,
+            &forbidden,
+            None,
+        );
+        assert_eq!(lines(findings), [1]);
+    }
+}
@@ -1,3 +1,10 @@
 pub(super) mod analyze;
-pub(super) mod scanners;
-pub(super) mod util;
+pub(crate) mod audit;
+pub(crate) mod diagnostic;
+pub(super) mod diff;
+pub(crate) mod doctor;
+pub(crate) mod env;
+pub(crate) mod graph;
+pub(crate) mod lint;
+pub(crate) mod scanners;
+pub(crate) mod util;
@@ -1,229 +1,466 @@
 use crate::{
-    analyzer::util::{analyze_call_recursive, analyze_path_recursive},
-    config::{Data, Field, Handler, HandlerInput, Route},
-    print,
+    analyzer::diagnostic::Diagnostics,
+    analyzer::util::{
+        call_name, guard_kind, handler_ref, join_path, method_chain, middleware_name, rename_field,
+        serde_flag, serde_options, serde_value, str_arg, type_name,
+    },
+    config::{Data, Field, Handler, HandlerInput, Route, ANY_METHOD, HTTP_METHODS},
+    pascal_case, print,
 };
 use colored::Colorize;
+use quote::ToTokens;
 use std::collections::HashMap;
-use std::fmt::Write;
-
-/// Scan a setup.rs file for route info
-pub(super) fn scan_setup(functions: Vec<syn::ItemFn>) -> Vec<Route> {
-    // Get all the statements from its block. This will include all
-    // the service initializations and cfg.service() calls
-    let mut routes_fn_inner = match functions.first() {
-        Some(calls) => calls.block.stmts.clone(),
-        None => vec![],
-    };
+use syn::spanned::Spanned;
+
+/// Name of the service config parameter of the routes function. Usually `cfg`, but it doesn't have to be.
+pub(crate) fn config_param(routes_fn: &syn::ItemFn) -> String {
+    routes_fn
+        .sig
+        .inputs
+        .iter()
+        .find_map(|arg| match arg {
+            syn::FnArg::Typed(pt)
+                if pt
+                    .ty
+                    .to_token_stream()
+                    .to_string()
+                    .contains("ServiceConfig") =>
+            {
+                match *pt.pat {
+                    syn::Pat::Ident(ref id) => Some(id.ident.to_string()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| "cfg".to_string())
+}
 
-    // Filter out the cfg.service calls
-    let inner_calls = routes_fn_inner
-        .drain(..)
-        .filter(|stmt| matches!(stmt, syn::Stmt::Semi(_, _)))
-        .collect::<Vec<syn::Stmt>>();
+/// Scan a setup.rs file for route info. Routes whose handler can't be named are kept with a warning.
+pub(crate) fn scan_setup(functions: Vec<syn::ItemFn>, diagnostics: &mut Diagnostics) -> Vec<Route> {
+    // Only the routes() function should be in the setup file
+    let Some(routes_fn) = functions.first() else {
+        return vec![];
+    };
 
-    print(&format!(
-        "Found {} inner cfg.service calls",
-        inner_calls.len()
-    ));
+    let cfg = config_param(routes_fn);
 
     let mut setup = Vec::<Route>::new();
 
-    for call in inner_calls {
-        // cfg.service() calls will always be method calls
-        if let syn::Stmt::Semi(syn::Expr::MethodCall(cfg_service_call), _) = call {
-            // The target should always be either the cfg or a *scope -- *todo
-            if let syn::Expr::Path(ref service_path) = *cfg_service_call.receiver {
-                let target = &service_path
-                    .path
-                    .segments
-                    .first()
-                    .unwrap()
-                    .ident
-                    .to_string();
-
-                if target == "cfg" && cfg_service_call.method == "service" {
-                    // These calls always have one argument
-                    let arg = cfg_service_call.args.first().unwrap();
-
-                    // And it's probably going to have nested route calls
-                    // inside, so we check for those. If the need arises we'll
-                    // check for regular expr calls and expr paths as well.
-                    if let syn::Expr::MethodCall(ref service_call) = arg {
-                        let mut level = 0;
-                        let mut route_config = HashMap::<usize, Vec<String>>::new();
-
-                        analyze_call_recursive(service_call.clone(), &mut route_config, &mut level);
-
-                        // Entry at key 100 will always contain the route path
-                        let mut path = route_config.get(&100).unwrap().clone();
-                        let path = path.pop().unwrap();
-
-                        // Create temporary vec in case of multiple middleware wraps
-                        let mut temp_routes: Vec<Route> = vec![];
-                        let mut index = 0;
-                        while let Some(item) = route_config.get(&index) {
-                            let mut mw = vec![];
-                            let j = check_next(&route_config, &mut index, &mut mw);
-                            if j == index + 1 {
-                                // Since middleware wraps everything before it we have to add it
-                                // to the preceding routes
-                                if !mw.is_empty() {
-                                    temp_routes.iter_mut().for_each(|route| {
-                                        if let Some(ref mut mdlw) = route.middleware {
-                                            for m in mw.iter() {
-                                                mdlw.push(m.clone())
-                                            }
-                                        } else {
-                                            route.middleware = Some(mw.clone());
-                                        }
-                                    });
-                                }
-                                let route = Route {
-                                    method: item[0].to_string(),
-                                    handler_name: item[2].to_string(),
-                                    path: path.clone(),
-                                    middleware: if mw.is_empty() {
-                                        None
-                                    } else {
-                                        Some(mw.clone())
-                                    },
-                                    service: if item[1].is_empty() {
-                                        None
-                                    } else {
-                                        Some(item[1].to_string())
-                                    },
-                                };
-                                temp_routes.push(route);
-                                index += 1;
-                                mw.clear();
-                            } else {
-                                index = j;
-                            }
-                        }
-                        for tr in temp_routes {
-                            setup.push(tr);
+    // Scopes and resources can be bound to variables before being registered
+    let mut locals = HashMap::<String, syn::Expr>::new();
+
+    for stmt in routes_fn.block.stmts.iter() {
+        let call = match stmt {
+            syn::Stmt::Local(local) => {
+                if let (syn::Pat::Ident(ref id), Some((_, ref init))) = (&local.pat, &local.init) {
+                    locals.insert(id.ident.to_string(), *init.clone());
+                }
+                continue;
+            }
+            syn::Stmt::Semi(syn::Expr::MethodCall(call), _) => call,
+            syn::Stmt::Expr(syn::Expr::MethodCall(call)) => call,
+            _ => continue,
+        };
+
+        // Registrations happen directly on the service config
+        match *call.receiver {
+            syn::Expr::Path(ref p) if p.path.is_ident(&cfg) => {}
+            _ => continue,
+        }
+
+        let args = call.args.iter().collect::<Vec<_>>();
+        match (call.method.to_string().as_str(), args.as_slice()) {
+            ("service", [service]) => {
+                setup.append(&mut scan_service(service, "", &locals, diagnostics));
+            }
+            ("route", [path, route]) => {
+                if let Some(path) = str_arg([*path]) {
+                    setup.push(scan_route(route, &path, diagnostics));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    print(&format!("Found {} routes in setup", setup.len()));
+    setup
+}
+
+/// Scan a service registration, i.e. the argument of `cfg.service()` or `scope.service()`. Services are
+/// either resources, scopes or handlers annotated with actix route macros. The prefix is the path of the
+/// enclosing scopes.
+fn scan_service(
+    expr: &syn::Expr,
+    prefix: &str,
+    locals: &HashMap<String, syn::Expr>,
+    diagnostics: &mut Diagnostics,
+) -> Vec<Route> {
+    let (base, calls) = method_chain(expr);
+
+    // Attribute macro handlers, i.e. `cfg.service(handler::get_thing)`, or variables we've seen
+    if calls.is_empty() {
+        if let syn::Expr::Path(ref p) = base {
+            if let Some(local) = p
+                .path
+                .get_ident()
+                .and_then(|id| locals.get(&id.to_string()))
+            {
+                return scan_service(local, prefix, locals, diagnostics);
+            }
+            let (handler_name, _) = handler_ref(base);
+            // The method and path are filled in from the macro when assembling the config
+            return vec![Route {
+                handler_name,
+                path: prefix.to_string(),
+                ..Default::default()
+            }];
+        }
+    }
+
+    let mut routes = vec![];
+    let mut middleware = vec![];
+    let mut guards = vec![];
+    let mut method = None;
+
+    match call_name(base).as_deref() {
+        Some("resource") => {
+            let syn::Expr::Call(ref res) = base else {
+                unreachable!()
+            };
+            let path = join_path(prefix, &str_arg(&res.args).unwrap_or_default());
+            for call in calls {
+                let arg = call.args.first();
+                match (call.method.to_string().as_str(), arg) {
+                    ("route", Some(route)) => routes.push(scan_route(route, &path, diagnostics)),
+                    // Shorthand for `.route(web::route().to(handler))`
+                    ("to", Some(handler)) => {
+                        let (handler_name, service) = handler_ref(handler);
+                        if handler_name.is_empty() {
+                            unresolved_handler(diagnostics, handler, &path);
                         }
+                        routes.push(Route {
+                            method: ANY_METHOD.to_string(),
+                            handler_name,
+                            path: path.clone(),
+                            middleware: None,
+                            guards: None,
+                            service,
+                        })
+                    }
+                    ("wrap", Some(mw)) | ("wrap_fn", Some(mw)) => {
+                        middleware.push(middleware_name(mw))
                     }
+                    ("guard", Some(guard)) => match guard_kind(guard) {
+                        Ok(m) => method = Some(m),
+                        Err(g) => guards.push(g),
+                    },
+                    _ => {}
                 }
             }
         }
+        Some("scope") => {
+            let syn::Expr::Call(ref scope) = base else {
+                unreachable!()
+            };
+            let path = join_path(prefix, &str_arg(&scope.args).unwrap_or_default());
+            for call in calls {
+                let args = call.args.iter().collect::<Vec<_>>();
+                match (call.method.to_string().as_str(), args.as_slice()) {
+                    ("service", [service]) => {
+                        routes.append(&mut scan_service(service, &path, locals, diagnostics))
+                    }
+                    ("route", [route_path, route]) => {
+                        let route_path = str_arg([*route_path]).unwrap_or_default();
+                        let route_path = join_path(&path, &route_path);
+                        routes.push(scan_route(route, &route_path, diagnostics))
+                    }
+                    ("wrap", [mw]) | ("wrap_fn", [mw]) => middleware.push(middleware_name(mw)),
+                    ("guard", [guard]) => match guard_kind(guard) {
+                        Ok(m) => method = Some(m),
+                        Err(g) => guards.push(g),
+                    },
+                    _ => {}
+                }
+            }
+        }
+        _ => {
+            print(&format!(
+                "{} {}",
+                "Skipping unrecognised service".yellow(),
+                expr.to_token_stream()
+            ));
+            return vec![];
+        }
+    }
+
+    // Wraps and guards apply to everything in the resource or scope regardless of
+    // where they are called in the chain
+    for route in routes.iter_mut() {
+        extend_route(route, method.as_deref(), &middleware, &guards);
+    }
+    routes
+}
+
+/// Scan a route builder, i.e. `web::get().to(handler)` or `web::route().guard(guard::Post()).to(handler)`
+fn scan_route(expr: &syn::Expr, path: &str, diagnostics: &mut Diagnostics) -> Route {
+    let (base, calls) = method_chain(expr);
+    let mut route = Route {
+        path: path.to_string(),
+        ..Default::default()
+    };
+
+    // `web::get()` and friends, `web::route()` has no method unless guarded
+    if let Some(name) = call_name(base) {
+        let name = name.to_uppercase();
+        if HTTP_METHODS.contains(&name.as_str()) {
+            route.method = name;
+        }
+    }
+
+    let mut middleware = vec![];
+    let mut guards = vec![];
+    for call in calls {
+        let Some(arg) = call.args.first() else {
+            continue;
+        };
+        match call.method.to_string().as_str() {
+            "to" => {
+                let (handler_name, service) = handler_ref(arg);
+                if handler_name.is_empty() {
+                    unresolved_handler(diagnostics, arg, path);
+                }
+                route.handler_name = handler_name;
+                route.service = service;
+            }
+            "method" => {
+                if let syn::Expr::Path(p) = arg {
+                    if let Some(m) = p.path.segments.last() {
+                        route.method = m.ident.to_string().to_uppercase();
+                    }
+                }
+            }
+            "guard" => match guard_kind(arg) {
+                Ok(m) => route.method = m,
+                Err(g) => guards.push(g),
+            },
+            "wrap" | "wrap_fn" => middleware.push(middleware_name(arg)),
+            _ => {}
+        }
+    }
+    if route.method.is_empty() {
+        route.method = ANY_METHOD.to_string();
+    }
+    extend_route(&mut route, None, &middleware, &guards);
+    route
+}
+
+/// Warns about a `.to()` argument that isn't a path to a function, i.e. a closure or a call returning
+/// the handler
+fn unresolved_handler(diagnostics: &mut Diagnostics, handler: &syn::Expr, path: &str) {
+    diagnostics.warn(
+        handler.span(),
+        format!(
+            "the handler of {path} can't be resolved: `{}`",
+            diagnostics.snippet(handler.span())
+        ),
+        "pass a function from handler.rs by path, i.e. `.to(handler::name)`, to have the route documented",
+    );
+}
+
+/// Applies the method, middleware and guards of an enclosing resource or scope to the route.
+/// Methods set on the route take precedence.
+fn extend_route(route: &mut Route, method: Option<&str>, middleware: &[String], guards: &[String]) {
+    if let Some(m) = method {
+        if route.method == ANY_METHOD {
+            route.method = m.to_string();
+        }
+    }
+    if !middleware.is_empty() {
+        route
+            .middleware
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(middleware);
+    }
+    if !guards.is_empty() {
+        route
+            .guards
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(guards);
     }
-    setup
 }
 
-/// Check if the next hashmap entry is a middleware and call this recursively
-/// until we find a route entry. Populates the given mw vector with the found
-/// middleware. Returns the index of the first found route entry if any.
-fn check_next(
-    hm: &HashMap<usize, Vec<String>>,
-    current: &mut usize,
-    mw: &mut Vec<String>,
-) -> usize {
-    match hm.get(&(*current + 1)) {
-        Some(entry) if entry.len() == 1 => {
-            mw.push(entry[0].clone());
-            *current += 1;
-            check_next(hm, current, mw)
+/// Routes declared with actix attribute macros, e.g. `#[get("/path")]` or
+/// `#[route("/path", method = "GET", method = "POST", wrap = "Logger::default()")]`
+fn scan_route_macros(name: &str, attrs: &[syn::Attribute]) -> Vec<Route> {
+    let mut routes = vec![];
+    for attr in attrs.iter() {
+        let Some(macro_name) = attr.path.segments.last().map(|s| s.ident.to_string()) else {
+            continue;
+        };
+        let macro_method = macro_name.to_uppercase();
+        if macro_name != "route" && !HTTP_METHODS.contains(&macro_method.as_str()) {
+            continue;
+        }
+        let Ok(syn::Meta::List(list)) = attr.parse_meta() else {
+            continue;
+        };
+
+        let mut path = String::new();
+        let mut methods = vec![];
+        let mut middleware = vec![];
+        let mut guards = vec![];
+        for nested in list.nested.iter() {
+            match nested {
+                syn::NestedMeta::Lit(syn::Lit::Str(p)) => path = p.value(),
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
+                    let syn::Lit::Str(ref value) = nv.lit else {
+                        continue;
+                    };
+                    if nv.path.is_ident("method") {
+                        methods.push(value.value().to_uppercase());
+                    } else if nv.path.is_ident("wrap") {
+                        match value.parse::<syn::Expr>() {
+                            Ok(mw) => middleware.push(middleware_name(&mw)),
+                            Err(_) => middleware.push(value.value()),
+                        }
+                    } else if nv.path.is_ident("guard") {
+                        guards.push(value.value());
+                    }
+                }
+                _ => {}
+            }
+        }
+        if macro_name != "route" {
+            methods.push(macro_method);
+        }
+        for method in methods {
+            routes.push(Route {
+                method,
+                handler_name: name.to_string(),
+                path: path.clone(),
+                middleware: (!middleware.is_empty()).then(|| middleware.clone()),
+                guards: (!guards.is_empty()).then(|| guards.clone()),
+                service: None,
+            });
         }
-        _ => *current + 1,
     }
+    routes
 }
 
-/// Scan the handler.rs file for handler info
-pub(super) fn scan_handlers(functions: Vec<syn::ItemFn>) -> Vec<Handler> {
+/// Scan the handler.rs file for handler info. Inputs alx can't make sense of are skipped with a
+/// warning, the rest of the handler is still recorded.
+pub(super) fn scan_handlers(
+    functions: Vec<syn::ItemFn>,
+    diagnostics: &mut Diagnostics,
+) -> Vec<Handler> {
     let mut handlers = Vec::<Handler>::new();
 
     for hand in functions {
         // Grab the name of the handler
         let name = hand.sig.ident.to_string();
 
-        // Check if it has any bounds
-        let bound = match hand.sig.generics.params.first() {
-            Some(param) => match param {
-                // For now only check type bounds since it's
-                // unlikely handlers will have lifetime or const bounds
-                syn::GenericParam::Type(ty) => {
-                    let mut typ = ty.ident.to_string();
-                    if let Some(bound) = ty.bounds.first() {
-                        match bound {
-                            syn::TypeParamBound::Trait(tb) => {
-                                typ = format!(
-                                    "{}: {}",
-                                    typ,
-                                    tb.path.segments.first().unwrap().ident,
-                                );
-                                Some(typ)
-                            }
-                            syn::TypeParamBound::Lifetime(_) => todo!(),
-                        }
-                    } else {
-                        Some(typ)
-                    }
-                }
-                syn::GenericParam::Lifetime(_) => todo!(),
-                syn::GenericParam::Const(_) => todo!(),
-            },
-            None => None,
-        };
+        // Check if it has any bounds. Only type bounds are of interest since lifetimes and
+        // consts don't say anything about the service the handler uses.
+        let bound = hand.sig.generics.type_params().next().map(|ty| {
+            let bound = ty.bounds.iter().find_map(|b| match b {
+                syn::TypeParamBound::Trait(tb) => tb.path.segments.last(),
+                syn::TypeParamBound::Lifetime(_) => None,
+            });
+            match bound {
+                Some(bound) => format!("{}: {}", ty.ident, bound.ident),
+                None => ty.ident.to_string(),
+            }
+        });
 
         let mut handler = Handler {
+            macro_routes: scan_route_macros(&name, &hand.attrs),
             name,
             inputs: vec![],
             bound,
         };
 
-        hand.sig.inputs.into_iter().for_each(|fn_arg| match fn_arg {
-            // Skip references to self in handlers
-            syn::FnArg::Receiver(f) => {
-                println!("{} {:?}", "Found self in handler?".red(), f)
-            }
+        for fn_arg in hand.sig.inputs.iter() {
+            let args = match fn_arg {
+                syn::FnArg::Typed(args) => args,
+                // Handlers are free functions, there's no self to extract
+                syn::FnArg::Receiver(r) => {
+                    diagnostics.warn(
+                        r.span(),
+                        format!("handler {} takes self", handler.name),
+                        "move the handler out of the impl block, actix can't call it with self",
+                    );
+                    continue;
+                }
+            };
+            let syn::Type::Path(ref p) = *args.ty else {
+                diagnostics.warn(
+                    args.ty.span(),
+                    format!(
+                        "unsupported input of handler {}: `{}`",
+                        handler.name,
+                        diagnostics.snippet(args.ty.span())
+                    ),
+                    format!(
+                        "take an extractor by value, i.e. `web::Json<{}Data>`, to have the input documented",
+                        pascal_case(&handler.name)
+                    ),
+                );
+                continue;
+            };
             // And iterate through all the args of the function
-            syn::FnArg::Typed(args) => {
-                if let syn::Type::Path(p) = *args.ty {
-                    // Iterate through all the type params
-                    for seg in p.path.segments {
-                        // We don't care about the web prefix
-                        if seg.ident != "web" {
-                            // The identity is the extractor type which holds the inputs type,
-                            // usually in angle bracket argument form
-                            let ext_type = seg.ident.to_string();
-                            let data_type = match seg.arguments {
-                                syn::PathArguments::AngleBracketed(arg) => {
-                                    // There's usually just one angle bracketed arg since all the data
-                                    // should come from some kind of wrapper struct from data.rs
-                                    match arg.args.first().unwrap() {
-                                        syn::GenericArgument::Type(t) => {
-                                            // The same goes for the path, this is where we'll find the
-                                            // data type
-                                            if let syn::Type::Path(t) = t {
-                                                t.path.segments.first().unwrap().ident.to_string()
-                                            } else {
-                                                panic!("Found a funky syn Type")
-                                            }
-                                        }
-                                        _ => panic!("Found some funky angle bracket arguments"),
-                                    }
-                                }
-                                syn::PathArguments::Parenthesized(_) => String::new(),
-                                syn::PathArguments::None => String::new(),
-                            };
-                            handler.inputs.push(HandlerInput {
-                                ext_type,
-                                data_type,
-                            })
-                        }
-                    }
+            for seg in p.path.segments.iter() {
+                // We don't care about the web prefix
+                if seg.ident == "web" {
+                    continue;
                 }
+                // The identity is the extractor type which holds the inputs type,
+                // usually in angle bracket argument form
+                let ext_type = seg.ident.to_string();
+                let data_type = match seg.arguments {
+                    // There's usually just one angle bracketed arg since all the data
+                    // should come from some kind of wrapper struct from data.rs
+                    syn::PathArguments::AngleBracketed(ref arg) => match arg.args.first() {
+                        Some(syn::GenericArgument::Type(syn::Type::Path(t))) => t
+                            .path
+                            .segments
+                            .last()
+                            .map(|s| s.ident.to_string())
+                            .unwrap_or_default(),
+                        _ => {
+                            diagnostics.warn(
+                                arg.span(),
+                                format!(
+                                    "unsupported {ext_type} input of handler {}: `{}`",
+                                    handler.name,
+                                    diagnostics.snippet(args.ty.span())
+                                ),
+                                format!(
+                                    "extract a struct from data.rs, i.e. `{ext_type}<{}Data>`, to have the input documented",
+                                    pascal_case(&handler.name)
+                                ),
+                            );
+                            continue;
+                        }
+                    },
+                    syn::PathArguments::Parenthesized(_) => String::new(),
+                    syn::PathArguments::None => String::new(),
+                };
+                handler.inputs.push(HandlerInput {
+                    ext_type,
+                    data_type,
+                })
             }
-        });
+        }
         print(&format!("👌 Found handler {}", handler.name));
         handlers.push(handler);
     }
     handlers
 }
 
-/// Scan the data.rs file for data info
+/// Scans the request data structs. Fields keep their identifier and get the name serde deserializes
+/// them as when `rename_all` or `rename` change it, skipped fields are left out. Fields are required
+/// unless they're optional or have a default.
 pub(super) fn scan_data(items: Vec<syn::Item>) -> Vec<Data> {
     let mut inputs = vec![];
     for item in items.iter() {
@@ -236,30 +473,37 @@ pub(super) fn scan_data(items: Vec<syn::Item>) -> Vec<Data> {
                 continue;
             }
             input.id = strct.ident.to_string();
-            // Iterate through struct fields
+            let options = serde_options(&strct.attrs);
+            let rename_all = serde_value(&options, "rename_all");
+            let defaults = serde_flag(&options, "default");
+
+            // Iterate through struct fields, tuple structs have nothing to name
             for field in strct.fields.iter() {
-                let name = field.ident.as_ref().unwrap().to_string();
+                let Some(ref ident) = field.ident else {
+                    continue;
+                };
+                let options = serde_options(&field.attrs);
+                if serde_flag(&options, "skip") || serde_flag(&options, "skip_deserializing") {
+                    continue;
+                }
+                let ident = ident.to_string();
+                let ident = ident.trim_start_matches("r#");
+                let rename = match (serde_value(&options, "rename"), rename_all.as_ref()) {
+                    (Some(name), _) => Some(name),
+                    (None, Some(rule)) => Some(rename_field(ident, rule)),
+                    (None, None) => None,
+                };
+                let ty = type_name(&field.ty);
+                let default = defaults || serde_flag(&options, "default");
                 let mut f = Field {
-                    name,
-                    ty: String::new(),
-                    required: false,
+                    rename: rename.filter(|name| name != ident),
+                    name: ident.to_string(),
+                    required: !ty.starts_with("Option<") && !default,
+                    default,
+                    ty,
                     validation: vec![],
                 };
 
-                // Struct fields will always be Path types
-                if let syn::Type::Path(ref ty) = field.ty {
-                    let mut nested: Vec<String> = vec![];
-                    analyze_path_recursive(ty, &mut f, &mut nested);
-                    if !nested.is_empty() {
-                        let mut typ = nested.join("<");
-                        write!(typ, "{}", ">".repeat(nested.len() - 1)).unwrap();
-                        if typ.contains("Option") {
-                            f.required = false;
-                        }
-                        f.ty = typ;
-                    }
-                }
-
                 // And search for field attributes for validation
                 for attr in field.attrs.iter() {
                     let validation = attr.path.is_ident("validate");
@@ -280,3 +524,232 @@ pub(super) fn scan_data(items: Vec<syn::Item>) -> Vec<Data> {
     }
     inputs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(src: &str) -> Vec<Route> {
+        let file = syn::parse_file(src).unwrap();
+        let functions = file
+            .items
+            .into_iter()
+            .filter_map(|i| match i {
+                syn::Item::Fn(f) => Some(f),
+                _ => None,
+            })
+            .collect();
+        let mut diagnostics = Diagnostics::new(std::path::Path::new("setup.rs"), src);
+        scan_setup(functions, &mut diagnostics)
+    }
+
+    #[test]
+    fn scans_scopes_and_routes() {
+        let routes = setup(
+            r#"
+            pub(crate) fn routes(pg: Arc<Postgres>, config: &mut web::ServiceConfig) {
+                let auth_guard = interceptor::AuthGuard::new(pg, Role::Admin);
+                config.route("/ping", web::get().to(handler::ping));
+                config.service(
+                    web::scope("/admin")
+                        .service(web::resource("/users").route(web::get().to(handler::list::<Service>)))
+                        .service(
+                            web::scope("/stats/")
+                                .route("/daily", web::route().guard(guard::Post()).to(handler::daily))
+                                .wrap(Logger::default()),
+                        )
+                        .service(web::resource("/any").to(handler::any).guard(guard::Header("x", "y")))
+                        .service(handler::by_macro)
+                        .wrap(auth_guard.clone()),
+                );
+            }
+            "#,
+        );
+        let summary = routes
+            .iter()
+            .map(|r| {
+                (
+                    r.method.as_str(),
+                    r.path.as_str(),
+                    r.handler_name.as_str(),
+                    r.middleware.clone().unwrap_or_default().join(","),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("GET", "/ping", "ping", String::new()),
+                ("GET", "/admin/users", "list", "auth_guard".to_string()),
+                (
+                    "POST",
+                    "/admin/stats/daily",
+                    "daily",
+                    "Logger,auth_guard".to_string()
+                ),
+                ("ANY", "/admin/any", "any", "auth_guard".to_string()),
+                ("", "/admin", "by_macro", "auth_guard".to_string()),
+            ]
+        );
+        assert_eq!(routes[1].service.as_deref(), Some("Service"));
+        assert_eq!(routes[3].guards, Some(vec!["guard::Header".to_string()]));
+    }
+
+    #[test]
+    fn scans_route_macros() {
+        let file = syn::parse_file(
+            r#"
+            #[get("/one")]
+            async fn one() -> impl Responder { "" }
+
+            #[route("/two", method = "PUT", method = "PATCH", wrap = "Logger::default()")]
+            async fn two() -> impl Responder { "" }
+            "#,
+        )
+        .unwrap();
+        let routes = file
+            .items
+            .iter()
+            .filter_map(|i| match i {
+                syn::Item::Fn(f) => Some(scan_route_macros(&f.sig.ident.to_string(), &f.attrs)),
+                _ => None,
+            })
+            .flatten()
+            .map(|r| (r.method, r.path, r.middleware))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            routes,
+            vec![
+                ("GET".to_string(), "/one".to_string(), None),
+                (
+                    "PUT".to_string(),
+                    "/two".to_string(),
+                    Some(vec!["Logger".to_string()])
+                ),
+                (
+                    "PATCH".to_string(),
+                    "/two".to_string(),
+                    Some(vec!["Logger".to_string()])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_data_as_serde_deserializes_it() {
+        let file = syn::parse_file(
+            r#"
+            #[derive(Deserialize, Validate)]
+            #[serde(rename_all = "camelCase")]
+            pub(super) struct GetUsers {
+                #[validate(range(min = 1, max = 65_535))]
+                pub per_page: Option<u16>,
+                pub sort_by: Vec<SortOptions>,
+                #[serde(rename = "q", default)]
+                pub query: String,
+                #[serde(skip)]
+                pub cache: HashMap<String, chrono::NaiveDate>,
+            }
+            "#,
+        )
+        .unwrap();
+        let data = scan_data(file.items);
+        let fields = data[0]
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.wire_name(), f.ty.as_str(), f.required))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("per_page", "perPage", "Option<u16>", false),
+                ("sort_by", "sortBy", "Vec<SortOptions>", true),
+                ("query", "q", "String", false),
+            ]
+        );
+        assert!(data[0].fields[2].default);
+        assert_eq!(
+            data[0].fields[0].validation,
+            ["range (min = 1 , max = 65_535)"]
+        );
+    }
+
+    #[test]
+    fn skips_odd_handler_inputs_with_a_warning() {
+        let src = r#"
+            pub(super) async fn pair(pair: web::Json<(String, u16)>) -> Result<HttpResponse, Error> {}
+
+            pub(super) async fn login<'a, T: ServiceContract>(
+                service: web::Data<T>,
+                credentials: web::Json<data::Credentials>,
+            ) -> Result<HttpResponse, Error> {}
+
+            pub(super) async fn logout(req: &HttpRequest) -> Result<HttpResponse, Error> {}
+        "#;
+        let file = syn::parse_file(src).unwrap();
+        let functions = file
+            .items
+            .into_iter()
+            .filter_map(|i| match i {
+                syn::Item::Fn(f) => Some(f),
+                _ => None,
+            })
+            .collect();
+        let mut diagnostics = Diagnostics::new(std::path::Path::new("handler.rs"), src);
+        let handlers = scan_handlers(functions, &mut diagnostics);
+
+        assert!(handlers[0].inputs.is_empty());
+        assert_eq!(handlers[1].bound.as_deref(), Some("T: ServiceContract"));
+        assert_eq!(
+            handlers[1].inputs[1],
+            HandlerInput {
+                ext_type: "Json".to_string(),
+                data_type: "Credentials".to_string(),
+            }
+        );
+        let found = diagnostics.into_vec();
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].line, found[0].column), (2, 53));
+        assert!(found[0].message.ends_with("`web::Json<(String, u16)>`"));
+        assert_eq!((found[1].line, found[1].column), (9, 45));
+        assert_eq!(
+            found[1].message,
+            "unsupported input of handler logout: `&HttpRequest`"
+        );
+    }
+
+    #[test]
+    fn warns_about_unresolved_route_handlers() {
+        let src = r#"
+            pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
+                cfg.route("/health", web::get().to(health_handler()));
+                cfg.service(web::resource("/metrics").to(|| async { "ok" }));
+            }
+        "#;
+        let file = syn::parse_file(src).unwrap();
+        let syn::Item::Fn(routes_fn) = file.items[0].clone() else {
+            unreachable!()
+        };
+        let mut diagnostics = Diagnostics::new(std::path::Path::new("setup.rs"), src);
+        let routes = scan_setup(vec![routes_fn], &mut diagnostics);
+        assert!(routes.iter().all(|r| r.handler_name.is_empty()));
+        let found = diagnostics
+            .into_vec()
+            .into_iter()
+            .map(|d| (d.line, d.message))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (
+                    3,
+                    "the handler of /health can't be resolved: `health_handler()`".to_string()
+                ),
+                (
+                    4,
+                    "the handler of /metrics can't be resolved: `|| async { \"ok\" }`".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,165 +1,225 @@
-use crate::config::Field;
-use std::collections::HashMap;
-use syn::{ExprMethodCall, TypePath};
+use crate::{config::HTTP_METHODS, uppercase};
+use quote::ToTokens;
+use syn::{
+    Expr, ExprMethodCall, GenericArgument, Lit, Meta, NestedMeta, PathArguments, Type, TypePath,
+};
 
-/// Read a method call recursively and map all its arguments and receivers. The route_config values after
-/// this will always be in the following order: `["METHOD", "SERVICE", "HANDLER"]`, excluding the 0th
-/// value which will have the path as the 0th element and the rest as described (4 in total). The way
-/// the setup file is written is such that the arguments will almost always be method calls so this function
-/// is mostly concerned with that. The method calls will most likely be nested if the cfg.service() call has
-/// more than one route or is wrapped by a middleware. If the need arises this function will have to be refined
-/// but for now it works pretty good. If a route is mapped at some key and if the next value's length is 1, we can
-/// almost be certain that value is a name, since middleware is usually parameterised as a path with a single element.
-pub(super) fn analyze_call_recursive(
-    method_call: ExprMethodCall,
-    route_config: &mut HashMap<usize, Vec<String>>,
-    level: &mut usize,
-) {
-    // If the receiver is another method call, scan it recursively.
-    if let syn::Expr::MethodCall(ref meth_call) = *method_call.receiver {
-        analyze_call_recursive(meth_call.clone(), route_config, level);
+/// Flatten a builder chain such as `web::resource("/x").route(..).wrap(..)` into its base expression
+/// and the method calls applied to it, in the order they are called.
+pub(crate) fn method_chain(expr: &Expr) -> (&Expr, Vec<&ExprMethodCall>) {
+    let mut calls = vec![];
+    let mut current = expr;
+    while let Expr::MethodCall(call) = current {
+        calls.push(call);
+        current = &call.receiver;
     }
+    calls.reverse();
+    (current, calls)
+}
 
-    // This checks for the web::resource("/path") string literal. Since this will be common to all
-    // the service routes we map this to 100.
-    if let syn::Expr::Call(ref call) = *method_call.receiver {
-        if let Some(syn::Expr::Lit(ref p)) = call.args.first() {
-            if let syn::Lit::Str(ref path) = p.lit {
-                route_config
-                    .entry(100)
-                    .and_modify(|e| e.push(path.value()))
-                    .or_insert_with(|| vec![path.value()]);
-            }
-        }
+/// Returns the name of the function being called, i.e. `resource` for `web::resource("/x")`
+pub(crate) fn call_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Call(call) => match *call.func {
+            Expr::Path(ref p) => p.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the value of the first argument if it's a string literal
+pub(crate) fn str_arg<'a>(args: impl IntoIterator<Item = &'a Expr>) -> Option<String> {
+    match args.into_iter().next() {
+        Some(Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        })) => Some(s.value()),
+        _ => None,
+    }
+}
+
+/// Joins a scope prefix with a path, avoiding double slashes
+pub(super) fn join_path(prefix: &str, path: &str) -> String {
+    match (prefix.ends_with('/'), path.starts_with('/')) {
+        (true, true) => format!("{}{}", prefix, &path[1..]),
+        _ => format!("{prefix}{path}"),
     }
+}
 
-    // Iterate through all the method call arguments
-    for mut arg in method_call.args {
-        // Middleware wrappers, i.e. some_guard in `.wrap(some_guard)` will be a path argument
-        if let syn::Expr::Path(ref path) = arg {
-            if let Some(wrapper) = path.path.get_ident() {
-                route_config
-                    .entry(*level)
-                    .and_modify(|e| e.push(wrapper.to_string()))
-                    .or_insert_with(|| vec![wrapper.to_string()]);
+/// Extracts the handler name and the service it's bound to from a `.to()` argument,
+/// e.g. `handler::login::<Authentication<..>>` yields `("login", Some("Authentication"))`.
+/// Closures and anything that isn't a path yields an empty handler name.
+pub(super) fn handler_ref(expr: &Expr) -> (String, Option<String>) {
+    let Expr::Path(path) = expr else {
+        return (String::new(), None);
+    };
+    let mut service = None;
+    for seg in path.path.segments.iter() {
+        // Get the service associated with the handler if any
+        if let PathArguments::AngleBracketed(ref args) = seg.arguments {
+            for arg in args.args.iter() {
+                if let GenericArgument::Type(Type::Path(p)) = arg {
+                    service = p.path.segments.first().map(|s| s.ident.to_string());
+                }
             }
         }
+    }
+    let name = path
+        .path
+        .segments
+        .last()
+        .map(|s| s.ident.to_string())
+        .unwrap_or_default();
+    (name, service)
+}
 
-        // Check for more method calls
-        if let syn::Expr::MethodCall(ref mut meth_call) = arg {
-            // And if the receiver is another one scan recursively
-            if let syn::Expr::MethodCall(ref call) = *meth_call.receiver {
-                analyze_call_recursive(call.clone(), route_config, level);
+/// Names the middleware in a `.wrap()` call. Bindings such as `auth_guard` or `auth_guard.clone()`
+/// yield the binding name, constructors such as `Logger::default()` yield the type.
+pub(super) fn middleware_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Path(p) => p
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident.to_string())
+            .unwrap_or_default(),
+        Expr::MethodCall(call) => middleware_name(&call.receiver),
+        Expr::Reference(r) => middleware_name(&r.expr),
+        Expr::Paren(p) => middleware_name(&p.expr),
+        Expr::Call(call) => match *call.func {
+            Expr::Path(ref p) => {
+                let segments = p.path.segments.iter().collect::<Vec<_>>();
+                let seg = if segments.len() > 1 {
+                    segments[segments.len() - 2]
+                } else {
+                    segments[0]
+                };
+                seg.ident.to_string()
             }
+            _ => middleware_name(&call.func),
+        },
+        _ => expr.to_token_stream().to_string(),
+    }
+}
 
-            // Otherwise check if the receiver is a function call
-            if let syn::Expr::Call(ref mut call) = *meth_call.receiver {
-                // Look for a web::method() call
-                if let syn::Expr::Path(ref mut call) = *call.func {
-                    let method = &mut call
-                        .path
-                        .segments
-                        .pop()
-                        .unwrap()
-                        .value()
-                        .ident
-                        .to_string()
-                        .to_uppercase();
-
-                    route_config
-                        .entry(*level)
-                        .and_modify(|e| e.push(method.clone()))
-                        .or_insert_with(|| vec![method.clone()]);
-                }
-                // Look for a path literal i.e. web::resource("/something")
-                if let Some(syn::Expr::Lit(ref p)) = call.args.first() {
-                    if let syn::Lit::Str(ref path) = p.lit {
-                        route_config
-                            .entry(*level)
-                            .and_modify(|e| e.push(path.value()))
-                            .or_insert_with(|| vec![path.value()]);
+/// Describes the guard in a `.guard()` call. Returns `Ok` with the HTTP method for method guards,
+/// i.e. `guard::Post()` or `guard::Method(Method::POST)`, and `Err` with the guard name for any other.
+pub(super) fn guard_kind(expr: &Expr) -> Result<String, String> {
+    if let Expr::Call(call) = expr {
+        if let Expr::Path(ref p) = *call.func {
+            let name = p
+                .path
+                .segments
+                .last()
+                .map(|s| s.ident.to_string())
+                .unwrap_or_default();
+            if name == "Method" {
+                if let Some(Expr::Path(m)) = call.args.first() {
+                    if let Some(m) = m.path.segments.last() {
+                        return Ok(m.ident.to_string().to_uppercase());
                     }
                 }
             }
-
-            // We also have to check for wrappers in method call arguments
-            if let syn::Expr::Path(ref path) = *meth_call.receiver {
-                if let Some(wrapper) = path.path.get_ident() {
-                    route_config
-                        .entry(*level)
-                        .and_modify(|e| e.push(wrapper.to_string()))
-                        .or_insert_with(|| vec![wrapper.to_string()]);
-                }
+            if HTTP_METHODS.contains(&name.to_uppercase().as_str()) {
+                return Ok(name.to_uppercase());
             }
+            let full = p
+                .path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::");
+            return Err(full);
+        }
+    }
+    Err(expr.to_token_stream().to_string())
+}
 
-            // Get the name of the handler
-            if let Some(syn::Expr::Path(route_path)) = meth_call.args.first() {
-                let mut service = None;
-                let mut handlers = route_path
-                    .path
-                    .segments
+/// The type as the lock file records it, i.e. `Option<Vec<NaiveDate>>` for
+/// `Option<Vec<chrono::NaiveDate>>`. Paths are shortened to their last segment and references to
+/// what they refer to.
+pub(super) fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(TypePath { path, .. }) => {
+            let Some(last) = path.segments.last() else {
+                return ty.to_token_stream().to_string();
+            };
+            let args = match last.arguments {
+                PathArguments::AngleBracketed(ref args) => args
+                    .args
                     .iter()
-                    .filter_map(|p| {
-                        if p.ident != "handler" {
-                            // Get the service associated with the handler if any
-                            if let syn::PathArguments::AngleBracketed(ref args) = p.arguments {
-                                for arg in &args.args {
-                                    if let syn::GenericArgument::Type(syn::Type::Path(p)) = arg {
-                                        service = Some(
-                                            p.path.segments.first().unwrap().ident.to_string(),
-                                        );
-                                    }
-                                }
-                            }
-                            return Some(p.ident.to_string());
-                        }
-                        None
+                    .filter_map(|arg| match arg {
+                        GenericArgument::Type(t) => Some(type_name(t)),
+                        _ => None,
                     })
-                    .collect::<Vec<String>>();
-
-                let h = handlers.pop().unwrap();
-                let s = service.clone().unwrap_or_default();
-
-                // Insert route_config into the map
-                route_config
-                    .entry(*level)
-                    .and_modify(|e| e.push(s.clone()))
-                    .or_insert_with(|| vec![s.clone()]);
-                route_config
-                    .entry(*level)
-                    .and_modify(|e| e.push(h.to_string()))
-                    .or_insert_with(|| vec![h.to_string()]);
+                    .collect::<Vec<_>>(),
+                _ => vec![],
+            };
+            match args.is_empty() {
+                true => last.ident.to_string(),
+                false => format!("{}<{}>", last.ident, args.join(", ")),
             }
         }
+        Type::Reference(r) => type_name(&r.elem),
+        Type::Paren(p) => type_name(&p.elem),
+        Type::Group(g) => type_name(&g.elem),
+        other => other.to_token_stream().to_string(),
     }
-    *level += 1;
 }
 
-pub(super) fn analyze_path_recursive(ty: &TypePath, f: &mut Field, buf: &mut Vec<String>) {
-    // First check for a simple identifier, i.e. a field with a single primitive
-    if let Some(ref id) = ty.path.get_ident() {
-        f.required = true;
-        f.ty = id.to_string();
-    } else {
-        // Otherwise check for angle bracket nested types such as Options and Vecs
-        // Grab the top level identifier of the type in question
-        let typ = ty.path.segments.first().unwrap().ident.to_string();
-        buf.push(typ);
+/// The `#[serde(..)]` options on the item, i.e. `rename_all = "camelCase"` and `default`
+pub(super) fn serde_options(attrs: &[syn::Attribute]) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("serde"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|n| match n {
+            NestedMeta::Meta(m) => Some(m),
+            NestedMeta::Lit(_) => None,
+        })
+        .collect()
+}
 
-        // Here we can safely unwrap because we know it contains something since we entered the else block
-        match ty.path.segments.first().unwrap().arguments {
-            // I've yet to see a type that contains params without angle brackets
-            syn::PathArguments::AngleBracketed(ref args) => {
-                // Check again for a simple type, if it's not simple go through the function once more
-                if let syn::GenericArgument::Type(syn::Type::Path(t)) = args.args.first().unwrap() {
-                    if let Some(id) = t.path.get_ident() {
-                        buf.push(id.to_string());
-                    } else {
-                        analyze_path_recursive(t, f, buf);
-                    }
-                }
-            }
-            _ => panic!("Not yet supported"),
+/// The string value of the serde option, i.e. `camelCase` for `rename_all = "camelCase"`
+pub(super) fn serde_value(options: &[Meta], name: &str) -> Option<String> {
+    options.iter().find_map(|m| match m {
+        Meta::NameValue(nv) if nv.path.is_ident(name) => match nv.lit {
+            Lit::Str(ref s) => Some(s.value()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Whether the serde option is set, i.e. `default` or `default = "path"`
+pub(super) fn serde_flag(options: &[Meta], name: &str) -> bool {
+    options.iter().any(|m| m.path().is_ident(name))
+}
+
+/// The name of the snake case field as serde renames it with `rename_all`
+pub(super) fn rename_field(name: &str, rule: &str) -> String {
+    let words = name.split('_').filter(|w| !w.is_empty());
+    let pascal = || words.clone().map(uppercase).collect::<String>();
+    match rule {
+        "lowercase" => name.to_lowercase(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => name.to_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|c| c.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
         }
+        "kebab-case" => name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => name.replace('_', "-").to_uppercase(),
+        _ => name.to_string(),
     }
 }
@@ -0,0 +1,367 @@
+//! Typed API client generation
+use crate::{
+    analyzer::audit::Guards,
+    config::{Data, Extractor, ProjectConfig, RouteHandler, ANY_METHOD},
+    export::schema::generic_inner,
+    INDENT,
+};
+use std::{collections::HashMap, fmt::Write};
+
+/// Languages the client can be generated in
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ClientLang {
+    #[value(name = "ts", alias = "typescript")]
+    TypeScript,
+}
+
+impl ClientLang {
+    /// The name of the generated file
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::TypeScript => "api.ts",
+        }
+    }
+}
+
+/// Writes a TypeScript module containing interfaces for all the request data and an `ApiClient` class with
+/// one method per route. Methods of routes the guards require a session for send the CSRF token.
+pub fn typescript(buf: &mut String, pc: &ProjectConfig, guards: &Guards) {
+    writeln!(
+        buf,
+        "// Generated by alx from the analyzed router. Do not edit by hand.\n"
+    )
+    .unwrap();
+
+    // Interfaces, deduplicated since multiple routes can use the same data
+    let mut data = pc
+        .endpoints
+        .iter()
+        .flat_map(|ep| ep.routes.iter().filter_map(|r| r.input.clone()))
+        .collect::<Vec<Data>>();
+    data.sort_by(|a, b| a.id.cmp(&b.id));
+    data.dedup_by(|a, b| a.id == b.id);
+    for d in data.iter() {
+        ts_interface(buf, d, &data);
+    }
+
+    writeln!(buf, "{TS_RUNTIME}").unwrap();
+
+    let mut names = HashMap::<String, usize>::new();
+    for ep in pc.endpoints.iter() {
+        for route in ep.routes.iter() {
+            let Some(ref handler) = route.handler else {
+                continue;
+            };
+            // We can't know which method the caller wants
+            if route.method == ANY_METHOD {
+                continue;
+            }
+            // Handler names are only unique per endpoint
+            let count = names.entry(handler.name.clone()).or_insert(0);
+            let name = if *count == 0 {
+                camel_case(&handler.name)
+            } else {
+                camel_case(&format!("{}_{}", ep.name, handler.name))
+            };
+            *count += 1;
+            ts_method(buf, &name, route, guards.is_guarded(&ep.name, route), &data);
+        }
+    }
+    writeln!(buf, "}}").unwrap();
+}
+
+fn ts_interface(buf: &mut String, data: &Data, known: &[Data]) {
+    writeln!(buf, "export interface {} {{", data.id).unwrap();
+    for field in data.fields.iter() {
+        let optional = if field.required { "" } else { "?" };
+        writeln!(
+            buf,
+            "{INDENT}{}{optional}: {};",
+            field.wire_name(),
+            ts_type(&field.ty, known)
+        )
+        .unwrap();
+    }
+    writeln!(buf, "}}\n").unwrap();
+}
+
+fn ts_method(buf: &mut String, name: &str, route: &RouteHandler, guarded: bool, known: &[Data]) {
+    let mut params = vec![];
+    let mut options = vec![];
+
+    // Path params are interpolated in to the path
+    let path_params = path_params(&route.path);
+    let mut path = route.path.clone();
+    if !path_params.is_empty() {
+        let ty = match input_of(route, Extractor::Path, known) {
+            Some(data) => data.id.clone(),
+            None => format!(
+                "{{ {} }}",
+                path_params
+                    .iter()
+                    .map(|(name, _)| format!("{name}: string | number"))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+        };
+        params.push(format!("path: {ty}"));
+        for (name, segment) in path_params.iter() {
+            path = path.replace(
+                segment,
+                &format!("${{encodeURIComponent(String(path.{name}))}}"),
+            );
+        }
+    }
+
+    if let Some(ty) = route.extractor_data(Extractor::Query) {
+        params.push(format!("query: {}", ts_type(ty, known)));
+        options.push("query");
+    }
+    if let Some(ty) = route.extractor_data(Extractor::Json) {
+        params.push(format!("body: {}", ts_type(ty, known)));
+        options.push("body");
+    } else if let Some(ty) = route.extractor_data(Extractor::Form) {
+        params.push(format!("form: {}", ts_type(ty, known)));
+        options.push("form");
+    }
+    if guarded {
+        options.push("auth: true");
+    }
+
+    writeln!(buf, "\n{INDENT}/** `{} {}` */", route.method, route.path).unwrap();
+    writeln!(
+        buf,
+        "{INDENT}async {name}({}): Promise<Response> {{",
+        params.join(", ")
+    )
+    .unwrap();
+    let options = if options.is_empty() {
+        String::from("{}")
+    } else {
+        format!("{{ {} }}", options.join(", "))
+    };
+    writeln!(
+        buf,
+        "{INDENT}{INDENT}return this.request(\"{}\", `{path}`, {options});",
+        route.method,
+    )
+    .unwrap();
+    writeln!(buf, "{INDENT}}}").unwrap();
+}
+
+/// Maps a rust type as written by the analyzer to a TypeScript type
+pub fn ts_type(ty: &str, known: &[Data]) -> String {
+    let ty = ty.trim();
+    if let Some(inner) = generic_inner(ty, "Option") {
+        return format!("{} | null", ts_type(inner, known));
+    }
+    if let Some(inner) = generic_inner(ty, "Vec").or_else(|| generic_inner(ty, "HashSet")) {
+        let inner = ts_type(inner, known);
+        return if inner.contains(' ') {
+            format!("({inner})[]")
+        } else {
+            format!("{inner}[]")
+        };
+    }
+    if let Some(inner) = generic_inner(ty, "HashMap").or_else(|| generic_inner(ty, "BTreeMap")) {
+        let value = inner.split_once(',').map_or(inner, |(_, v)| v);
+        return format!("Record<string, {}>", ts_type(value, known));
+    }
+    match ty {
+        "String" | "str" | "&str" | "char" | "Uuid" | "NaiveDateTime" | "NaiveDate"
+        | "DateTime" => "string".to_string(),
+        "bool" => "boolean".to_string(),
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "isize" | "f32" | "f64" => "number".to_string(),
+        other if known.iter().any(|d| d.id == other) => other.to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn input_of<'a>(route: &RouteHandler, extractor: Extractor, known: &'a [Data]) -> Option<&'a Data> {
+    let ty = route.extractor_data(extractor)?;
+    known.iter().find(|d| d.id == ty)
+}
+
+/// Returns the names and full segments, e.g. `("id", "{id:\d+}")`, of the path params
+fn path_params(path: &str) -> Vec<(String, String)> {
+    path.split('{')
+        .skip(1)
+        .filter_map(|s| s.split_once('}'))
+        .map(|(inner, _)| {
+            let name = inner.split(':').next().unwrap_or(inner).to_string();
+            (name, format!("{{{inner}}}"))
+        })
+        .collect()
+}
+
+fn camel_case(s: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for c in s.chars() {
+        if c == '_' || c == '-' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The request plumbing shared by all the generated methods. Keeps track of the CSRF token the server
+/// sends on login and attaches it to guarded requests. The `S_ID` session cookie is handled by the
+/// browser as long as credentials are included.
+const TS_RUNTIME: &str = r#"export class ApiError extends Error {
+    constructor(public readonly status: number, public readonly body: unknown) {
+        super(`Request failed with status ${status}`);
+    }
+}
+
+interface RequestOptions {
+    query?: object;
+    body?: unknown;
+    form?: object;
+    auth?: boolean;
+}
+
+export class ApiClient {
+    private csrfToken: string | null = null;
+
+    constructor(
+        private readonly baseUrl: string,
+        private readonly fetchFn: typeof fetch = fetch.bind(globalThis),
+    ) {}
+
+    /** The `x-csrf-token` received on login, sent with every request that requires a session. */
+    get csrf(): string | null {
+        return this.csrfToken;
+    }
+
+    set csrf(token: string | null) {
+        this.csrfToken = token;
+    }
+
+    private async request(method: string, path: string, opts: RequestOptions): Promise<Response> {
+        let url = this.baseUrl + path;
+        if (opts.query) {
+            const params = new URLSearchParams();
+            for (const [key, value] of Object.entries(opts.query)) {
+                if (value !== undefined && value !== null) params.append(key, String(value));
+            }
+            const qs = params.toString();
+            if (qs) url += `?${qs}`;
+        }
+
+        const headers: Record<string, string> = {};
+        let body: BodyInit | undefined;
+        if (opts.body !== undefined) {
+            headers["Content-Type"] = "application/json";
+            body = JSON.stringify(opts.body);
+        } else if (opts.form) {
+            headers["Content-Type"] = "application/x-www-form-urlencoded";
+            body = new URLSearchParams(opts.form as Record<string, string>).toString();
+        }
+        if (opts.auth && this.csrfToken) {
+            headers["x-csrf-token"] = this.csrfToken;
+        }
+
+        const res = await this.fetchFn(url, { method, headers, body, credentials: "include" });
+
+        const csrf = res.headers.get("x-csrf-token");
+        if (csrf) this.csrfToken = csrf;
+
+        if (!res.ok) {
+            const text = await res.text();
+            let err: unknown = text;
+            try {
+                err = JSON.parse(text);
+            } catch {
+                // Not JSON, keep the text
+            }
+            throw new ApiError(res.status, err);
+        }
+        return res;
+    }"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_typescript_client() {
+        let pc = ProjectConfig::parse(
+            r#"
+endpoints:
+  - name: users
+    routes:
+      - method: GET
+        path: /users/{id}
+        handler:
+          name: get_user
+          inputs:
+            - extractor: Path
+              data: UserId
+          bound: null
+        middleware: null
+        service: Users
+        input:
+          id: UserId
+          fields:
+            - name: id
+              ty: u32
+      - method: PATCH
+        path: /users/{id}
+        handler:
+          name: update_user
+          inputs:
+            - extractor: Path
+              data: UserId
+            - extractor: Json
+              data: UpdateUser
+          bound: null
+        middleware: [auth_guard]
+        service: Users
+        input:
+          id: UpdateUser
+          fields:
+            - name: display_name
+              rename: displayName
+              ty: Option<String>
+              required: false
+"#
+            .to_string(),
+        )
+        .unwrap();
+        let mut ts = String::new();
+        typescript(&mut ts, &pc, &Guards::default());
+        let expected = format!(
+            r#"// Generated by alx from the analyzed router. Do not edit by hand.
+
+export interface UpdateUser {{
+    displayName?: string | null;
+}}
+
+export interface UserId {{
+    id: number;
+}}
+
+{TS_RUNTIME}
+
+    /** `GET /users/{{id}}` */
+    async getUser(path: UserId): Promise<Response> {{
+        return this.request("GET", `/users/${{encodeURIComponent(String(path.id))}}`, {{}});
+    }}
+
+    /** `PATCH /users/{{id}}` */
+    async updateUser(path: UserId, body: UpdateUser): Promise<Response> {{
+        return this.request("PATCH", `/users/${{encodeURIComponent(String(path.id))}}`, {{ body, auth: true }});
+    }}
+}}
+"#
+        );
+        assert_eq!(ts, expected);
+    }
+}
@@ -0,0 +1,301 @@
+//! Edits to `configure.rs`, where the `setup::routes` of every route get called with the clients
+//! created for the app
+use super::edit::{imported_names, line_indent, range, Edits};
+use crate::{error::AlxError, INDENT};
+use quote::ToTokens;
+use syn::{Expr, FnArg, ItemFn, Pat, Stmt};
+
+/// A client created in `configure` and passed to the routes needing it
+#[derive(Debug)]
+pub struct Client {
+    /// The type wrapped in an `Arc`
+    pub ty: &'static str,
+    /// Import path of the type
+    pub path: &'static str,
+    /// Name of the local in `configure`
+    pub local: &'static str,
+    /// Name of the parameter in `setup::routes`
+    pub param: &'static str,
+    /// Contracts with any of these in their name are implemented with the client
+    keywords: &'static [&'static str],
+}
+
+pub const CLIENTS: [Client; 3] = [
+    Client {
+        ty: "Postgres",
+        path: "infrastructure::clients::store::postgres::Postgres",
+        local: "pg",
+        param: "pg",
+        keywords: &["repo", "pg", "postgres", "db", "store"],
+    },
+    Client {
+        ty: "Redis",
+        path: "infrastructure::clients::store::redis::Redis",
+        local: "rd",
+        param: "rd",
+        keywords: &["cache", "redis", "rd", "session"],
+    },
+    Client {
+        ty: "SmtpTransport",
+        path: "infrastructure::clients::email::lettre::SmtpTransport",
+        local: "email_client",
+        param: "email",
+        keywords: &["email", "mail", "smtp"],
+    },
+];
+
+/// The clients the contracts need, in the order of `CLIENTS`. A contract needs a client if a word of
+/// its name starts with one of the client's keywords, i.e. `repo` and `user_cache` need the Postgres
+/// and Redis clients.
+pub fn contract_clients(contracts: &[&str]) -> Vec<&'static Client> {
+    CLIENTS
+        .iter()
+        .filter(|client| {
+            contracts.iter().any(|c| {
+                c.to_lowercase()
+                    .split('_')
+                    .any(|w| client.keywords.iter().any(|k| w.starts_with(k)))
+            })
+        })
+        .collect()
+}
+
+/// Whether `router::<route>::setup::routes` is called in the configure function
+pub fn is_wired(src: &str, route: &str) -> Result<bool, AlxError> {
+    let file = syn::parse_file(src)?;
+    let configure = configure_fn(&file)?;
+    Ok(configure
+        .block
+        .stmts
+        .iter()
+        .any(|stmt| is_route_call(stmt, route)))
+}
+
+/// Adds a call to `router::<route>::setup::routes` to the configure function. The arguments are
+/// picked from the parameters of `routes` in the route's setup file, the service config for the
+/// config and the clients in `configure` by their type for the rest. The call is placed before the
+/// first statement moving one of the clients, otherwise at the end. Returns `None` if the call
+/// already exists.
+pub fn wire(src: &str, route: &str, setup: &str) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let configure = configure_fn(&file)?;
+    if configure
+        .block
+        .stmts
+        .iter()
+        .any(|stmt| is_route_call(stmt, route))
+    {
+        return Ok(None);
+    }
+
+    let locals = configure
+        .block
+        .stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Local(l) => match l.pat {
+                Pat::Ident(ref id) => Some(id.ident.to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let cfg = configure
+        .sig
+        .inputs
+        .iter()
+        .find_map(|arg| typed_param(arg).filter(|(_, ty)| ty.contains("ServiceConfig")))
+        .map(|(name, _)| name)
+        .ok_or_else(|| AlxError::Edit("configure takes no ServiceConfig".to_string()))?;
+
+    let setup = syn::parse_file(setup)?;
+    let routes = setup
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Fn(f) if f.sig.ident == "routes" => Some(f),
+            _ => None,
+        })
+        .ok_or_else(|| AlxError::Edit(format!("{route}/setup.rs has no routes function")))?;
+
+    let mut args = vec![];
+    let mut clients = vec![];
+    for (name, ty) in routes.sig.inputs.iter().filter_map(typed_param) {
+        if ty.contains("ServiceConfig") {
+            args.push(cfg.clone());
+            continue;
+        }
+        let client = CLIENTS
+            .iter()
+            .find(|client| {
+                ty.split(|c: char| !c.is_alphanumeric())
+                    .any(|t| t == client.ty)
+            })
+            .map(|client| client.local.to_string())
+            .filter(|local| locals.contains(local))
+            .ok_or_else(|| {
+                AlxError::Edit(format!(
+                    "no client in configure.rs to pass to `{name}: {ty}` of {route}::setup::routes"
+                ))
+            })?;
+        args.push(format!("{client}.clone()"));
+        clients.push(client);
+    }
+
+    let router = match imported_names(&file.items).contains("router") {
+        true => "router",
+        false => "crate::api::router",
+    };
+    let call = format!("{router}::{route}::setup::routes({});", args.join(", "));
+
+    let mut edits = Edits::default();
+    let moving = configure
+        .block
+        .stmts
+        .iter()
+        .find(|stmt| moved(stmt).iter().any(|m| clients.contains(m)));
+    match moving {
+        Some(stmt) => {
+            let (start, _) = range(src, syn::spanned::Spanned::span(stmt));
+            let indent = line_indent(src, start);
+            edits.insert(start, format!("{call}\n{indent}"));
+        }
+        None => {
+            let (open, end) = range(src, configure.block.brace_token.span);
+            let inner = &src[open + 1..end - 1];
+            let outer = line_indent(src, open);
+            edits.insert(
+                open + 1 + inner.trim_end().len(),
+                format!("\n{outer}{INDENT}{call}"),
+            );
+        }
+    }
+    Ok(Some(edits.apply(src)))
+}
+
+/// Removes the calls to `router::<route>::setup::routes` from the configure function. Returns
+/// `None` if there are none.
+pub fn unwire(src: &str, route: &str) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let configure = configure_fn(&file)?;
+    let mut edits = Edits::default();
+    for stmt in configure
+        .block
+        .stmts
+        .iter()
+        .filter(|stmt| is_route_call(stmt, route))
+    {
+        let (start, end) = range(src, syn::spanned::Spanned::span(stmt));
+        edits.remove(src, start, end);
+    }
+    match edits.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(edits.apply(src))),
+    }
+}
+
+fn configure_fn(file: &syn::File) -> Result<&ItemFn, AlxError> {
+    file.items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Fn(f) if f.sig.ident == "configure" => Some(f),
+            _ => None,
+        })
+        .ok_or_else(|| AlxError::Edit("no configure function found".to_string()))
+}
+
+/// Name and type of a function parameter
+fn typed_param(arg: &FnArg) -> Option<(String, String)> {
+    match arg {
+        FnArg::Typed(t) => match *t.pat {
+            Pat::Ident(ref id) => Some((
+                id.ident.to_string(),
+                t.ty.to_token_stream().to_string().replace(' ', ""),
+            )),
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    }
+}
+
+fn stmt_call(stmt: &Stmt) -> Option<&syn::ExprCall> {
+    match stmt {
+        Stmt::Semi(Expr::Call(c), _) | Stmt::Expr(Expr::Call(c)) => Some(c),
+        _ => None,
+    }
+}
+
+/// Matches `router::<route>::setup::routes(..)` with any prefix
+fn is_route_call(stmt: &Stmt, route: &str) -> bool {
+    let Some(Expr::Path(p)) = stmt_call(stmt).map(|c| &*c.func) else {
+        return false;
+    };
+    let segments = p
+        .path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>();
+    segments.ends_with(&[route.to_string(), "setup".to_string(), "routes".to_string()])
+}
+
+/// Locals passed by value to the call in the statement
+fn moved(stmt: &Stmt) -> Vec<String> {
+    stmt_call(stmt)
+        .map(|c| {
+            c.args
+                .iter()
+                .filter_map(|arg| match arg {
+                    Expr::Path(p) => p.path.get_ident().map(|id| id.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIGURE: &str = r#"use crate::api::router;
+
+pub(super) fn configure(cfg: &mut ServiceConfig) {
+    let pg = Arc::new(Postgres::new());
+    let rd = Arc::new(Redis::new());
+
+    router::users::setup::routes(pg.clone(), rd.clone(), cfg);
+    router::health::route(pg, rd, cfg);
+}
+"#;
+
+    #[test]
+    fn wires_routes() {
+        let setup = "pub(crate) fn routes(cfg: &mut web::ServiceConfig) {}";
+        let out = wire(CONFIGURE, "posts", setup).unwrap().unwrap();
+        assert!(out.ends_with(
+            "    router::health::route(pg, rd, cfg);\n    router::posts::setup::routes(cfg);\n}\n"
+        ));
+        assert!(is_wired(&out, "posts").unwrap());
+        assert!(wire(&out, "posts", setup).unwrap().is_none());
+
+        let setup = "pub(crate) fn routes(pg: Arc<Postgres>, cfg: &mut ServiceConfig) {}";
+        let out = wire(CONFIGURE, "posts", setup).unwrap().unwrap();
+        assert!(out.contains(
+            "    router::posts::setup::routes(pg.clone(), cfg);\n    router::health::route(pg, rd, cfg);"
+        ));
+
+        let setup = "pub(crate) fn routes(mongo: Arc<Mongo>, cfg: &mut ServiceConfig) {}";
+        assert!(wire(CONFIGURE, "posts", setup).is_err());
+
+        assert_eq!(unwire(&out, "posts").unwrap().unwrap(), CONFIGURE);
+        assert!(unwire(CONFIGURE, "posts").unwrap().is_none());
+        assert_eq!(
+            contract_clients(&["repository", "user_cache", "orders"])
+                .iter()
+                .map(|c| c.local)
+                .collect::<Vec<_>>(),
+            ["pg", "rd"]
+        );
+    }
+}
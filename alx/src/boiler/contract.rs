@@ -0,0 +1,492 @@
+//! Adds a contract to an existing route. Each function edits one of the route files in place and
+//! returns `None` if the file already has what it would add.
+use super::edit::{line_indent, offset, range, span_text, Edits};
+use crate::{error::AlxError, pascal_case, INDENT};
+use syn::{spanned::Spanned, visit::Visit};
+
+/// Names derived from the contract name given on the command line, i.e. `cache`
+pub struct Contract {
+    /// The service field, `cache`
+    pub field: String,
+    /// The infrastructure struct, `Cache`, `EmailClient` for `email_client`
+    pub adapter: String,
+    /// The trait, `CacheContract`
+    pub name: String,
+}
+
+impl Contract {
+    pub fn new(name: &str) -> Self {
+        Self {
+            field: name.to_string(),
+            adapter: pascal_case(name),
+            name: format!("{}Contract", pascal_case(name)),
+        }
+    }
+}
+
+/// Adds the contract trait to contract.rs, with the same visibility and attributes as the `ServiceContract`
+pub fn contract_file(src: &str, contract: &Contract) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let traits = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Trait(t) => Some(t),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if traits.iter().any(|t| t.ident == contract.name) {
+        return Ok(None);
+    }
+
+    let service = traits.iter().find(|t| t.ident == "ServiceContract");
+    let vis = match service {
+        Some(t) => span_text(src, t.vis.span()).to_string(),
+        None => "pub(super)".to_string(),
+    };
+    let mock = service.is_some_and(|t| {
+        t.attrs
+            .iter()
+            .any(|a| span_text(src, a.span()).contains("automock"))
+    });
+
+    let mut edits = Edits::default();
+    edits.add_use(src, &file.items, 0, &["async_trait"], "async_trait");
+    let mut item = String::from("\n");
+    if mock {
+        item.push_str("#[cfg_attr(test, mockall::automock)]\n");
+    }
+    item.push_str(&format!(
+        "#[async_trait]\n{vis} trait {} {{}}\n",
+        contract.name
+    ));
+    append(&mut edits, src, &item);
+    Ok(Some(edits.apply(src)))
+}
+
+/// Adds the adapter struct and its contract implementation to infrastructure.rs
+pub fn infrastructure_file(src: &str, contract: &Contract) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let exists = file
+        .items
+        .iter()
+        .any(|item| matches!(item, syn::Item::Struct(s) if s.ident == contract.adapter));
+    if exists {
+        return Ok(None);
+    }
+
+    let mut edits = Edits::default();
+    edits.add_use(src, &file.items, 0, &["super", "contract"], &contract.name);
+    edits.add_use(src, &file.items, 0, &["async_trait"], "async_trait");
+    append(
+        &mut edits,
+        src,
+        &format!(
+            "\npub(super) struct {0} {{}}\n\n#[async_trait]\nimpl {1} for {0} {{}}\n",
+            contract.adapter, contract.name
+        ),
+    );
+    Ok(Some(edits.apply(src)))
+}
+
+/// The name of the struct implementing the `ServiceContract` in domain.rs
+pub fn service_name(file: &syn::File) -> Option<String> {
+    file.items.iter().find_map(|item| match item {
+        syn::Item::Impl(i) if is_impl_of(i, "ServiceContract") => self_ident(i),
+        _ => None,
+    })
+}
+
+/// Adds a generic parameter bound by the contract and a field of that type to the service struct in
+/// domain.rs, along with the parameter on all of its impls
+pub fn domain_file(src: &str, contract: &Contract) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let Some(service) = service_name(&file) else {
+        return Err(AlxError::Edit(
+            "no ServiceContract implementation found in domain.rs".to_string(),
+        ));
+    };
+    let Some(strct) = file.items.iter().find_map(|item| match item {
+        syn::Item::Struct(s) if s.ident == service => Some(s),
+        _ => None,
+    }) else {
+        return Err(AlxError::Edit(format!(
+            "struct {service} not found in domain.rs"
+        )));
+    };
+    if strct
+        .fields
+        .iter()
+        .any(|f| f.ident.as_ref().is_some_and(|i| *i == contract.field))
+    {
+        return Ok(None);
+    }
+
+    // The boilerplate uses the first letter of the contract, fall back to the whole name if it's taken
+    let taken = strct
+        .generics
+        .params
+        .iter()
+        .map(|p| span_text(src, p.span()).split(':').next().unwrap().trim())
+        .collect::<Vec<_>>();
+    let first = contract.adapter.chars().take(1).collect::<String>();
+    let param = if taken.contains(&first.as_str()) {
+        contract.adapter.clone()
+    } else {
+        first
+    };
+
+    let mut edits = Edits::default();
+    edits.add_use(src, &file.items, 0, &["super", "contract"], &contract.name);
+
+    add_generic(
+        &mut edits,
+        src,
+        &strct.generics,
+        strct.ident.span(),
+        &param,
+        &contract.name,
+    );
+    match strct.fields {
+        syn::Fields::Named(ref fields) => {
+            let (open, end) = range(src, fields.brace_token.span);
+            edits.push_to_list(
+                src,
+                open + 1,
+                end - 1,
+                &format!("pub {}: {param}", contract.field),
+            );
+        }
+        syn::Fields::Unit => {
+            let (start, end) = range(src, strct.semi_token.unwrap().span);
+            edits.replace(
+                start,
+                end,
+                format!(" {{\n{INDENT}pub {}: {param},\n}}", contract.field),
+            );
+        }
+        syn::Fields::Unnamed(_) => {
+            return Err(AlxError::Edit(format!(
+                "{service} is a tuple struct, add the contract by hand"
+            )))
+        }
+    }
+
+    for item in file.items.iter() {
+        let syn::Item::Impl(imp) = item else {
+            continue;
+        };
+        if self_ident(imp).as_deref() != Some(service.as_str()) {
+            continue;
+        }
+        // Trait implementations get the same bounds the boilerplate writes
+        let bound = if imp.trait_.is_some() {
+            format!("{} + Send + Sync", contract.name)
+        } else {
+            contract.name.clone()
+        };
+        add_generic(
+            &mut edits,
+            src,
+            &imp.generics,
+            imp.impl_token.span,
+            &param,
+            &bound,
+        );
+        if let syn::Type::Path(ref ty) = *imp.self_ty {
+            add_type_arg(&mut edits, src, ty, &param);
+        }
+    }
+    Ok(Some(edits.apply(src)))
+}
+
+/// Constructs the adapter in setup.rs wherever the service is constructed and adds it to every mention
+/// of the service type, i.e. the handler turbofishes
+pub fn setup_file(
+    src: &str,
+    contract: &Contract,
+    service: &str,
+) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let mut edits = Edits::default();
+    let value = format!("{} {{}}", contract.adapter);
+    if !add_to_service(
+        &mut edits,
+        src,
+        &file,
+        contract,
+        service,
+        &value,
+        &contract.adapter,
+    ) {
+        return Ok(None);
+    }
+    edits.add_use(
+        src,
+        &file.items,
+        0,
+        &["super", "infrastructure"],
+        &contract.adapter,
+    );
+    Ok(Some(edits.apply(src)))
+}
+
+/// Passes the contract's mock wherever the tests in mod.rs construct the service. Only applicable if the
+/// contracts are mocked with mockall.
+pub fn tests_file(
+    src: &str,
+    contract: &Contract,
+    service: &str,
+) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let Some((tests, items)) = file.items.iter().find_map(|item| match item {
+        syn::Item::Mod(m) if m.ident == "tests" => m.content.as_ref().map(|(b, items)| (b, items)),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+    let mock = format!("Mock{}", contract.name);
+    let mut edits = Edits::default();
+    let value = format!("{mock}::new()");
+    if !add_to_service(&mut edits, src, &file, contract, service, &value, &mock) {
+        return Ok(None);
+    }
+    let start = offset(src, tests.span.start()) + 1;
+    edits.add_use(src, items, start, &["super", "contract"], &mock);
+    Ok(Some(edits.apply(src)))
+}
+
+/// Adds the contract field with the given value to every construction of the service and the type to
+/// every mention of the service type. Returns false if there was nothing to add it to or it's already there.
+fn add_to_service(
+    edits: &mut Edits,
+    src: &str,
+    file: &syn::File,
+    contract: &Contract,
+    service: &str,
+    value: &str,
+    ty: &str,
+) -> bool {
+    let mut visitor = ServiceVisitor {
+        service,
+        structs: vec![],
+        units: vec![],
+        types: vec![],
+    };
+    visitor.visit_file(file);
+
+    let already = visitor.structs.iter().any(|s| {
+        s.fields.iter().any(|f| match f.member {
+            syn::Member::Named(ref id) => *id == contract.field,
+            syn::Member::Unnamed(_) => false,
+        })
+    });
+    if already || visitor.structs.len() + visitor.units.len() + visitor.types.len() == 0 {
+        return false;
+    }
+
+    let construct = format!("{}: {value}", contract.field);
+    for s in visitor.structs.iter() {
+        let (open, end) = range(src, s.brace_token.span);
+        edits.push_to_list(src, open + 1, end - 1, &construct);
+    }
+    for u in visitor.units.iter() {
+        let (start, end) = range(src, u.span());
+        let indent = line_indent(src, start);
+        edits.replace(
+            start,
+            end,
+            format!("{service} {{\n{indent}{INDENT}{construct},\n{indent}}}"),
+        );
+    }
+    for t in visitor.types.iter() {
+        add_type_arg(edits, src, t, ty);
+    }
+    true
+}
+
+/// Collects the constructions and type mentions of the service
+struct ServiceVisitor<'a, 'ast> {
+    service: &'a str,
+    structs: Vec<&'ast syn::ExprStruct>,
+    units: Vec<&'ast syn::ExprPath>,
+    types: Vec<&'ast syn::TypePath>,
+}
+
+impl<'a, 'ast> Visit<'ast> for ServiceVisitor<'a, 'ast> {
+    fn visit_expr_struct(&mut self, s: &'ast syn::ExprStruct) {
+        if s.path
+            .segments
+            .last()
+            .is_some_and(|l| l.ident == self.service)
+        {
+            self.structs.push(s);
+        }
+        syn::visit::visit_expr_struct(self, s);
+    }
+
+    fn visit_expr_path(&mut self, p: &'ast syn::ExprPath) {
+        if p.path.is_ident(self.service) {
+            self.units.push(p);
+        }
+        syn::visit::visit_expr_path(self, p);
+    }
+
+    fn visit_type_path(&mut self, t: &'ast syn::TypePath) {
+        if t.path
+            .segments
+            .last()
+            .is_some_and(|l| l.ident == self.service)
+        {
+            self.types.push(t);
+        }
+        syn::visit::visit_type_path(self, t);
+    }
+}
+
+/// Adds a generic parameter to an item. Goes in to the where clause if the item has one, otherwise it's
+/// bound inline. `after` is the span of the token the parameter list follows if there isn't one yet.
+fn add_generic(
+    edits: &mut Edits,
+    src: &str,
+    generics: &syn::Generics,
+    after: proc_macro2::Span,
+    param: &str,
+    bound: &str,
+) {
+    let item = match generics.where_clause {
+        Some(ref wc) => {
+            let open = offset(src, wc.where_token.span.end());
+            let close = offset(src, wc.span().end());
+            edits.push_to_list(src, open, close, &format!("{param}: {bound}"));
+            param.to_string()
+        }
+        None => format!("{param}: {bound}"),
+    };
+    match (generics.lt_token, generics.gt_token) {
+        (Some(lt), Some(gt)) => {
+            let open = offset(src, lt.spans[0].end());
+            let close = offset(src, gt.spans[0].start());
+            edits.push_to_list(src, open, close, &item);
+        }
+        _ => edits.insert(offset(src, after.end()), format!("<{item}>")),
+    }
+}
+
+/// Adds a type argument to the last segment of the path, i.e. `Service<R>` to `Service<R, C>`
+fn add_type_arg(edits: &mut Edits, src: &str, ty: &syn::TypePath, arg: &str) {
+    let Some(last) = ty.path.segments.last() else {
+        return;
+    };
+    match last.arguments {
+        syn::PathArguments::AngleBracketed(ref args) => {
+            let open = offset(src, args.lt_token.spans[0].end());
+            let close = offset(src, args.gt_token.spans[0].start());
+            edits.push_to_list(src, open, close, arg);
+        }
+        _ => edits.insert(offset(src, last.ident.span().end()), format!("<{arg}>")),
+    }
+}
+
+pub(super) fn is_impl_of(imp: &syn::ItemImpl, trait_name: &str) -> bool {
+    imp.trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .is_some_and(|s| s.ident == trait_name)
+}
+
+fn self_ident(imp: &syn::ItemImpl) -> Option<String> {
+    match *imp.self_ty {
+        syn::Type::Path(ref p) => p.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Appends the item to the end of the file
+fn append(edits: &mut Edits, src: &str, item: &str) {
+    // Items in empty files only need separating from the imports added to them
+    let item = if src.trim().is_empty() && edits.is_empty() {
+        item.trim_start()
+    } else {
+        item
+    };
+    if !src.is_empty() && !src.ends_with('\n') {
+        edits.insert(src.len(), format!("\n{item}"));
+    } else {
+        edits.insert(src.len(), item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_contract_to_domain() {
+        let src = r#"use super::contract::{RepositoryContract, ServiceContract};
+
+pub(super) struct UserService<R: RepositoryContract> {
+    pub repository: R,
+}
+
+#[async_trait]
+impl<R> ServiceContract for UserService<R>
+where
+    R: RepositoryContract + Send + Sync,
+{
+    async fn get(&self) {}
+}
+"#;
+        let out = domain_file(src, &Contract::new("cache")).unwrap().unwrap();
+        assert_eq!(
+            out,
+            r#"use super::contract::{CacheContract, RepositoryContract, ServiceContract};
+
+pub(super) struct UserService<R: RepositoryContract, C: CacheContract> {
+    pub repository: R,
+    pub cache: C,
+}
+
+#[async_trait]
+impl<R, C> ServiceContract for UserService<R, C>
+where
+    R: RepositoryContract + Send + Sync,
+    C: CacheContract + Send + Sync,
+{
+    async fn get(&self) {}
+}
+"#
+        );
+        assert!(domain_file(&out, &Contract::new("cache"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn names_contracts_in_pascal_case() {
+        let contract = Contract::new("email_client");
+        assert_eq!(contract.field, "email_client");
+        assert_eq!(contract.adapter, "EmailClient");
+        assert_eq!(contract.name, "EmailClientContract");
+    }
+
+    #[test]
+    fn adds_contract_to_unit_service() {
+        let domain = "#[derive(Debug)]\npub(super) struct Things;\n\n#[async_trait]\nimpl ServiceContract for Things {}\n";
+        let out = domain_file(domain, &Contract::new("cache"))
+            .unwrap()
+            .unwrap();
+        assert!(out.contains("pub(super) struct Things<C: CacheContract> {\n    pub cache: C,\n}"));
+        assert!(
+            out.contains("impl<C: CacheContract + Send + Sync> ServiceContract for Things<C> {}")
+        );
+
+        let setup = "pub(crate) fn routes(cfg: &mut web::ServiceConfig) {\n    let service = Things;\n    cfg.service(web::resource(\"/\").route(web::get().to(handler::get::<Things>)));\n}\n";
+        let out = setup_file(setup, &Contract::new("cache"), "Things")
+            .unwrap()
+            .unwrap();
+        assert!(out.starts_with("use super::infrastructure::Cache;\n"));
+        assert!(out.contains("let service = Things {\n        cache: Cache {},\n    };"));
+        assert!(out.contains("handler::get::<Things<Cache>>"));
+    }
+}
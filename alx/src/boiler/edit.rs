@@ -0,0 +1,407 @@
+//! Helpers for editing existing source files in place. Items are located with syn and the changes are
+//! spliced in to the original text so the code around them keeps its formatting and comments.
+use crate::{error::AlxError, INDENT};
+use proc_macro2::{LineColumn, Span};
+use std::collections::HashSet;
+use syn::spanned::Spanned;
+
+/// Byte offset of a span location in the source. Span lines are 1-based and columns count chars.
+pub fn offset(src: &str, loc: LineColumn) -> usize {
+    let line_start = src
+        .split_inclusive('\n')
+        .take(loc.line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    let line = &src[line_start..];
+    line_start
+        + line
+            .char_indices()
+            .nth(loc.column)
+            .map_or(line.len(), |(i, _)| i)
+}
+
+/// Byte offsets of the start and end of the span
+pub fn range(src: &str, span: Span) -> (usize, usize) {
+    (offset(src, span.start()), offset(src, span.end()))
+}
+
+/// The source text the span covers
+pub fn span_text(src: &str, span: Span) -> &str {
+    let (start, end) = range(src, span);
+    &src[start..end]
+}
+
+/// Offset of the closing brace of a braced group, e.g. a function body
+pub fn closing_brace(src: &str, span: Span) -> usize {
+    offset(src, span.end()) - 1
+}
+
+/// Offset right after the last `use` item, or the start of the file if there are none
+pub fn after_uses(src: &str, items: &[syn::Item]) -> usize {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Use(u) => Some(offset(src, u.semi_token.span.end())),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// All the names brought in to scope by the `use` items
+pub fn imported_names(items: &[syn::Item]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for item in items.iter() {
+        if let syn::Item::Use(u) = item {
+            collect_use_names(&u.tree, &mut names);
+        }
+    }
+    names
+}
+
+fn collect_use_names(tree: &syn::UseTree, names: &mut HashSet<String>) {
+    match tree {
+        syn::UseTree::Path(p) => collect_use_names(&p.tree, names),
+        syn::UseTree::Name(n) => {
+            names.insert(n.ident.to_string());
+        }
+        syn::UseTree::Rename(r) => {
+            names.insert(r.rename.to_string());
+        }
+        syn::UseTree::Group(g) => g.items.iter().for_each(|t| collect_use_names(t, names)),
+        syn::UseTree::Glob(_) => {}
+    }
+}
+
+/// Leading whitespace of the line the offset is on
+pub fn line_indent(src: &str, at: usize) -> &str {
+    let start = src[..at].rfind('\n').map_or(0, |i| i + 1);
+    let line = &src[start..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Adds `<vis> mod name;` after the other module declarations. Returns `None` if it's already declared.
+pub fn add_mod(src: &str, vis: &str, name: &str) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let mods = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Mod(m) => Some(m),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if mods.iter().any(|m| m.ident == name) {
+        return Ok(None);
+    }
+    let mut edits = Edits::default();
+    match mods.last() {
+        Some(m) => {
+            let (_, end) = range(src, m.semi.as_ref().map_or(m.ident.span(), |s| s.span));
+            edits.insert(end, format!("\n{vis} mod {name};"));
+        }
+        None => edits.insert(0, format!("{vis} mod {name};\n")),
+    }
+    Ok(Some(edits.apply(src)))
+}
+
+/// Adds `#[cfg(test)] mod name;` after the other module declarations. Returns `None` if it's already
+/// declared.
+pub fn add_test_mod(src: &str, name: &str) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    if file
+        .items
+        .iter()
+        .any(|item| matches!(item, syn::Item::Mod(m) if m.ident == name))
+    {
+        return Ok(None);
+    }
+    let mut declared = file.items.iter().filter_map(|item| match item {
+        syn::Item::Mod(m) if m.content.is_none() => m.semi.as_ref(),
+        _ => None,
+    });
+    let mut edits = Edits::default();
+    match declared.next_back() {
+        Some(semi) => {
+            let (_, end) = range(src, semi.span);
+            edits.insert(end, format!("\n\n#[cfg(test)]\nmod {name};"));
+        }
+        None => edits.insert(0, format!("#[cfg(test)]\nmod {name};\n\n")),
+    }
+    Ok(Some(edits.apply(src)))
+}
+
+/// Removes the `mod name;` declaration. Returns `None` if it isn't declared.
+pub fn remove_mod(src: &str, name: &str) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let Some(m) = file.items.iter().find_map(|item| match item {
+        syn::Item::Mod(m) if m.ident == name && m.content.is_none() => Some(m),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+    let (start, end) = range(src, syn::spanned::Spanned::span(m));
+    let mut edits = Edits::default();
+    edits.remove(src, start, end);
+    Ok(Some(edits.apply(src)))
+}
+
+/// A set of changes to apply to a source file in one go. Offsets always refer to the original
+/// source, insertions at the same offset keep the order they were added in.
+#[derive(Debug, Default)]
+pub struct Edits {
+    changes: Vec<(usize, usize, String)>,
+}
+
+impl Edits {
+    pub fn insert(&mut self, at: usize, text: impl Into<String>) {
+        self.changes.push((at, at, text.into()));
+    }
+
+    /// Replaces the text between the offsets
+    pub fn replace(&mut self, start: usize, end: usize, text: impl Into<String>) {
+        self.changes.push((start, end, text.into()));
+    }
+
+    /// Adds an item to the end of a comma separated list, e.g. struct fields or generic params. `open` is the
+    /// offset right after the opening delimiter and `close` the offset of the closing one. Lists spanning
+    /// multiple lines get the item on its own line with the indentation of the other items.
+    pub fn push_to_list(&mut self, src: &str, open: usize, close: usize, item: &str) {
+        let inner = &src[open..close];
+        if inner.trim().is_empty() {
+            if inner.contains('\n') || src[..open].ends_with('{') {
+                let outer = line_indent(src, open);
+                self.replace(open, close, format!("\n{outer}{INDENT}{item},\n{outer}"));
+            } else {
+                self.replace(open, close, item);
+            }
+            return;
+        }
+        let at = open + inner.trim_end().len();
+        let comma = inner.trim_end().ends_with(',');
+        if inner.contains('\n') {
+            let indent = line_indent(src, at);
+            let sep = if comma { "" } else { "," };
+            self.insert(at, format!("{sep}\n{indent}{item},"));
+        } else if comma {
+            self.insert(at, format!(" {item},"));
+        } else {
+            self.insert(at, format!(", {item}"));
+        }
+    }
+
+    /// Adds an item, i.e. a method, to the end of a braced block such as a trait or impl body. `open` and
+    /// `close` are the offsets of the braces. The item is indented one level deeper than the block.
+    pub fn push_to_block(&mut self, src: &str, open: usize, close: usize, item: &str) {
+        let outer = line_indent(src, open);
+        let item = item
+            .lines()
+            .map(|l| match l.is_empty() {
+                true => String::new(),
+                false => format!("{outer}{INDENT}{l}"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let inner = &src[open + 1..close];
+        if inner.trim().is_empty() {
+            self.replace(open + 1, close, format!("\n{item}\n{outer}"));
+        } else {
+            self.insert(open + 1 + inner.trim_end().len(), format!("\n\n{item}"));
+        }
+    }
+
+    /// Adds `name` to the imports from `module`, e.g. `["super", "contract"]`. Extends an existing import
+    /// from the module if there is one, otherwise adds a new `use` after the existing ones or at `start` if
+    /// there are none. The items are either the file's or an inline module's.
+    pub fn add_use(
+        &mut self,
+        src: &str,
+        items: &[syn::Item],
+        start: usize,
+        module: &[&str],
+        name: &str,
+    ) {
+        if imported_names(items).contains(name) {
+            return;
+        }
+        for item in items.iter() {
+            let syn::Item::Use(u) = item else {
+                continue;
+            };
+            if self.extend_use(src, &u.tree, module, name) {
+                return;
+            }
+        }
+        let path = format!("{}::{name}", module.join("::"));
+        let stmt = format!("use {path};");
+        // Before the first import sorting after it, keeping rustfmt's order
+        let next = items.iter().find_map(|item| match item {
+            syn::Item::Use(u)
+                if path_order(&span_text(src, u.tree.span()).replace(char::is_whitespace, ""))
+                    > path_order(&path) =>
+            {
+                Some(u)
+            }
+            _ => None,
+        });
+        if let Some(next) = next {
+            let at = offset(src, next.span().start());
+            let indent = line_indent(src, at);
+            return self.insert(at, format!("{stmt}\n{indent}"));
+        }
+        match after_uses(src, items) {
+            0 if start == 0 => self.insert(0, format!("{stmt}\n")),
+            0 => {
+                let indent = line_indent(src, start);
+                self.insert(start, format!("\n{indent}{INDENT}{stmt}"))
+            }
+            at => {
+                let indent = line_indent(src, at);
+                self.insert(at, format!("\n{indent}{stmt}"))
+            }
+        }
+    }
+
+    fn extend_use(&mut self, src: &str, tree: &syn::UseTree, module: &[&str], name: &str) -> bool {
+        match (tree, module) {
+            (syn::UseTree::Path(p), [first, rest @ ..]) if p.ident == first => {
+                self.extend_use(src, &p.tree, rest, name)
+            }
+            (syn::UseTree::Group(g), []) => {
+                let (open, end) = range(src, g.brace_token.span);
+                // Before the first item sorting after it, keeping rustfmt's order
+                match g.items.iter().find(|t| use_order(t) > name_order(name)) {
+                    Some(next) => {
+                        let (at, _) = range(src, syn::spanned::Spanned::span(next));
+                        match src[open..end].contains('\n') {
+                            true => {
+                                let indent = line_indent(src, at);
+                                self.insert(at, format!("{name},\n{indent}"))
+                            }
+                            false => self.insert(at, format!("{name}, ")),
+                        }
+                    }
+                    None => self.push_to_list(src, open + 1, end - 1, name),
+                }
+                true
+            }
+            (syn::UseTree::Name(_), []) | (syn::UseTree::Rename(_), []) => {
+                let (start, end) = range(src, syn::spanned::Spanned::span(tree));
+                let existing = &src[start..end];
+                let group = match use_order(tree) > name_order(name) {
+                    true => format!("{{{name}, {existing}}}"),
+                    false => format!("{{{existing}, {name}}}"),
+                };
+                self.replace(start, end, group);
+                true
+            }
+            (syn::UseTree::Group(g), _) => g
+                .items
+                .iter()
+                .any(|t| self.extend_use(src, t, module, name)),
+            _ => false,
+        }
+    }
+
+    /// Removes the text between the offsets, along with the line it's on if nothing else is left on it
+    pub fn remove(&mut self, src: &str, start: usize, end: usize) {
+        let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = src[end..].find('\n').map_or(src.len(), |i| end + i + 1);
+        match src[line_start..start].trim().is_empty() && src[end..line_end].trim().is_empty() {
+            true => self.replace(line_start, line_end, ""),
+            false => self.replace(start, end, ""),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn apply(mut self, src: &str) -> String {
+        // Stable sort, so same offset changes stay in order
+        self.changes.sort_by_key(|(start, _, _)| *start);
+        let mut out = String::with_capacity(src.len());
+        let mut last = 0;
+        for (start, end, text) in self.changes.iter() {
+            out.push_str(&src[last..*start]);
+            out.push_str(text);
+            last = *end;
+        }
+        out.push_str(&src[last..]);
+        out
+    }
+}
+
+/// Where rustfmt sorts a `use` among the others, `self`, `super` and `crate` first, then the crates
+fn path_order(path: &str) -> (u8, &str) {
+    let first = path.split("::").next().unwrap_or_default();
+    let rank = match first {
+        "self" => 0,
+        "super" => 1,
+        "crate" => 2,
+        _ => 3,
+    };
+    (rank, path)
+}
+
+/// Where rustfmt sorts the import in a group, `self` first, then modules, then types and constants
+fn use_order(tree: &syn::UseTree) -> (u8, String) {
+    match tree {
+        syn::UseTree::Path(p) => name_order(&p.ident.to_string()),
+        syn::UseTree::Name(n) => name_order(&n.ident.to_string()),
+        syn::UseTree::Rename(r) => name_order(&r.ident.to_string()),
+        syn::UseTree::Glob(_) => (3, String::new()),
+        syn::UseTree::Group(_) => (4, String::new()),
+    }
+}
+
+fn name_order(name: &str) -> (u8, String) {
+    match name {
+        "self" => (0, String::new()),
+        _ if name.starts_with(char::is_lowercase) => (1, name.to_string()),
+        _ => (2, name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splices_in_to_function_body() {
+        let src = "use a::b;\n\nfn routes(cfg: &mut Config) {\n    // keep me\n    cfg.x();\n}\n";
+        let file = syn::parse_file(src).unwrap();
+        let syn::Item::Fn(ref f) = file.items[1] else {
+            panic!()
+        };
+        let mut edits = Edits::default();
+        edits.insert(
+            closing_brace(src, f.block.brace_token.span),
+            "    cfg.y();\n",
+        );
+        edits.insert(after_uses(src, &file.items), "\nuse c::d;");
+        assert_eq!(
+            edits.apply(src),
+            "use a::b;\nuse c::d;\n\nfn routes(cfg: &mut Config) {\n    // keep me\n    cfg.x();\n    cfg.y();\n}\n"
+        );
+        assert_eq!(span_text(src, f.sig.ident.span()), "routes");
+        assert!(imported_names(&file.items).contains("b"));
+    }
+
+    #[test]
+    fn extends_imports() {
+        let src = "use super::{\n    contract::{A, B},\n    data::C,\n};\nuse x::y::Z;\n";
+        let file = syn::parse_file(src).unwrap();
+        let mut edits = Edits::default();
+        edits.add_use(src, &file.items, 0, &["super", "contract"], "D");
+        edits.add_use(src, &file.items, 0, &["super", "contract"], "AB");
+        edits.add_use(src, &file.items, 0, &["super"], "config");
+        edits.add_use(src, &file.items, 0, &["x", "y"], "W");
+        edits.add_use(src, &file.items, 0, &["super", "data"], "C");
+        edits.add_use(src, &file.items, 0, &["std", "sync"], "Arc");
+        assert_eq!(
+            edits.apply(src),
+            "use super::{\n    config,\n    contract::{A, AB, B, D},\n    data::C,\n};\nuse std::sync::Arc;\nuse x::y::{W, Z};\n"
+        );
+    }
+}
@@ -1,64 +1,267 @@
+//! Everything alx writes goes through here so it can be previewed with `--dry-run` and so existing
+//! directories and files are overwritten according to `--force` and `--no-overwrite`. In a dry run
+//! nothing touches the disk. Written files are kept in memory instead so later edits in the same run
+//! read them back, and a unified diff is printed for each.
+use clap::Args;
 use colored::Colorize;
+use similar::TextDiff;
 use std::{
-    fs,
-    io::{stdin, Write},
+    collections::HashMap,
+    fs, io,
+    io::{stdin, BufRead},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Mutex,
+    },
 };
 
 use crate::print;
 
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+static OVERWRITE: AtomicU8 = AtomicU8::new(Overwrite::Ask as u8);
+/// Files written during a dry run
+static PENDING: Mutex<Option<HashMap<PathBuf, String>>> = Mutex::new(None);
+
+/// Arguments for commands that write files
+#[derive(Debug, Args)]
+pub struct WriteArgs {
+    /// Print a diff of the files that would be created or changed instead of writing them
+    #[arg(long, action)]
+    pub dry_run: bool,
+    /// Overwrite existing directories and files without asking
+    #[arg(long, action, conflicts_with = "no_overwrite")]
+    pub force: bool,
+    /// Abort instead of asking when an existing directory or file would be overwritten
+    #[arg(long, action)]
+    pub no_overwrite: bool,
+}
+
+/// What to do when something would be overwritten
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overwrite {
+    Ask,
+    Always,
+    Never,
+}
+
+impl WriteArgs {
+    pub fn overwrite(&self) -> Overwrite {
+        match (self.force, self.no_overwrite) {
+            (true, _) => Overwrite::Always,
+            (_, true) => Overwrite::Never,
+            _ => Overwrite::Ask,
+        }
+    }
+}
+
+/// Sets the write mode for the rest of the run
+pub fn write_mode(args: &WriteArgs) {
+    DRY_RUN.store(args.dry_run, Ordering::SeqCst);
+    OVERWRITE.store(args.overwrite() as u8, Ordering::SeqCst);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::SeqCst)
+}
+
+fn overwrite() -> Overwrite {
+    match OVERWRITE.load(Ordering::SeqCst) {
+        1 => Overwrite::Always,
+        2 => Overwrite::Never,
+        _ => Overwrite::Ask,
+    }
+}
+
+/// Reads the file, or what was written to it earlier in a dry run
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    if let Some(contents) = pending(path.as_ref()) {
+        return Ok(contents);
+    }
+    fs::read_to_string(path)
+}
+
+fn pending(path: &Path) -> Option<String> {
+    let pending = PENDING.lock().unwrap();
+    pending.as_ref()?.get(path).cloned()
+}
+
+/// Writes the file or prints the diff of the change in a dry run
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<str>) -> io::Result<()> {
+    let (path, contents) = (path.as_ref(), contents.as_ref());
+    if !dry_run() {
+        return fs::write(path, contents);
+    }
+    let old = read_to_string(path).ok();
+    print_diff(path, old.as_deref(), contents);
+    PENDING
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(path.to_path_buf(), contents.to_string());
+    Ok(())
+}
+
+/// Writes a file that replaces whatever is there. Returns false if the file exists and nothing may be
+/// overwritten.
+pub fn create(path: impl AsRef<Path>, contents: impl AsRef<str>) -> io::Result<bool> {
+    let path = path.as_ref();
+    if overwrite() == Overwrite::Never && (path.exists() || pending(path).is_some()) {
+        println!(
+            "{}{}",
+            "Not overwriting existing file ".yellow(),
+            path.display()
+        );
+        return Ok(false);
+    }
+    write(path, contents)?;
+    Ok(true)
+}
+
+pub fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    match dry_run() {
+        true => Ok(()),
+        false => fs::create_dir_all(path),
+    }
+}
+
+/// Prints a unified diff of the change to the file
+pub fn print_diff(path: &Path, old: Option<&str>, new: &str) {
+    let path = path.display().to_string();
+    let from = match old {
+        Some(_) => path.as_str(),
+        None => "/dev/null",
+    };
+    let old = old.unwrap_or_default();
+    if old == new {
+        return;
+    }
+    let diff = TextDiff::from_lines(old, new);
+    let diff = diff.unified_diff().header(from, &path).to_string();
+    for line in diff.lines() {
+        match line.chars().next() {
+            _ if line.starts_with("---") || line.starts_with("+++") => println!("{}", line.bold()),
+            Some('+') => println!("{}", line.green()),
+            Some('-') => println!("{}", line.red()),
+            Some('@') => println!("{}", line.cyan()),
+            _ => println!("{line}"),
+        }
+    }
+}
+
 /// Returns false if the user aborted the process
 pub fn handle_create_dir(path: &str) -> bool {
-    match fs::create_dir(path) {
-        Ok(_) => {}
-        Err(e) => {
-            println!(
-                "An error occurred: {:?}\n\
-                Would you like to continue anyway?\
-                \n\u{26A0} {} \u{26A0}\n\
-                This will completely overwrite {}",
-                e.kind(),
-                "WARNING".red(),
-                path
-            );
-            let mut buf = String::new();
-            loop {
-                println!("Press [y]es or [n]o to continue or abort");
-                stdin().read_line(&mut buf).expect("Couldn't parse input");
-                match buf.trim() {
-                    "y" | "yes" => {
-                        println!("Overwriting {}", path);
-                        fs::remove_dir_all(path).expect("Couldn't remove directory");
-                        fs::create_dir(path).expect("Couldn't create directory");
-                        break;
-                    }
-                    "n" | "no" => {
-                        println!("Aborting");
-                        return false;
-                    }
-                    _ => {
-                        buf.clear();
-                    }
+    let exists = Path::new(path).exists();
+    if exists {
+        match overwrite() {
+            Overwrite::Never => {
+                println!("{}{}", "Not overwriting existing directory ".yellow(), path);
+                return false;
+            }
+            Overwrite::Always => println!("Overwriting {}", path),
+            Overwrite::Ask if dry_run() => println!("Would overwrite {}", path),
+            Overwrite::Ask => {
+                println!(
+                    "{} already exists\n\
+                    Would you like to continue anyway?\
+                    \n\u{26A0} {} \u{26A0}\n\
+                    This will completely overwrite {}",
+                    path,
+                    "WARNING".red(),
+                    path
+                );
+                if !confirm() {
+                    println!("Aborting");
+                    return false;
                 }
+                println!("Overwriting {}", path);
             }
         }
-    };
+    }
+    if dry_run() {
+        return true;
+    }
+    if exists {
+        fs::remove_dir_all(path).expect("Couldn't remove directory");
+    }
+    fs::create_dir(path).expect("Couldn't create directory");
     true
 }
 
-pub fn write_to_mod_file(file_path: &str, mod_name: &str) {
+/// Asks on stdin whether to continue, see [answer]
+pub fn confirm() -> bool {
+    answer(stdin().lock())
+}
+
+/// Asks until the answer is yes or no. Closed input, i.e. when alx isn't run in a terminal, aborts.
+fn answer(mut input: impl BufRead) -> bool {
+    let mut buf = String::new();
+    loop {
+        println!("Press [y]es or [n]o to continue or abort");
+        match input.read_line(&mut buf) {
+            Ok(0) | Err(_) => {
+                println!("{}", "No answer, aborting".yellow());
+                return false;
+            }
+            Ok(_) => {}
+        }
+        match buf.trim() {
+            "y" | "yes" => return true,
+            "n" | "no" => return false,
+            _ => buf.clear(),
+        }
+    }
+}
+
+pub fn write_to_mod_file(file_path: &str, vis: &str, mod_name: &str) {
     print(&format!(
         "{} Adding {} to {}",
         "\u{270E}".green(),
         mod_name,
         file_path
     ));
-    let f = fs::read_to_string(file_path).unwrap();
-    let mut new_file_contents = format!("pub(crate) mod {};\n", mod_name);
+    let f = read_to_string(file_path).unwrap();
+    let mut new_file_contents = format!("{} mod {};\n", vis, mod_name);
     if !f.contains(&new_file_contents) {
         new_file_contents.push_str(&f);
-        fs::remove_file(file_path).expect("Couldn't remove old mod.rs file");
+        write(file_path, new_file_contents).expect("Couldn't write mod.rs");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_keeps_writes_in_memory() {
+        write_mode(&WriteArgs {
+            dry_run: true,
+            force: false,
+            no_overwrite: true,
+        });
+        let path = std::env::temp_dir().join("alx_dry_run_test/mod.rs");
+        write(&path, "pub(crate) mod a;\n").unwrap();
+        write_to_mod_file(path.to_str().unwrap(), "pub(crate)", "b");
+        assert!(!path.exists());
+        assert_eq!(
+            read_to_string(&path).unwrap(),
+            "pub(crate) mod b;\npub(crate) mod a;\n"
+        );
+        assert!(!create(&path, "").unwrap());
+
+        // Back to the defaults for the other tests
+        write_mode(&WriteArgs {
+            dry_run: false,
+            force: false,
+            no_overwrite: false,
+        });
+        PENDING.lock().unwrap().take();
+    }
 
-        let mut new_file = fs::File::create(file_path).expect("Couldn't create file");
-        new_file.write_all(new_file_contents.as_bytes()).unwrap();
+    #[test]
+    fn closed_input_aborts() {
+        assert!(answer("maybe\nyes\n".as_bytes()));
+        assert!(!answer("maybe\n".as_bytes()));
+        assert!(!answer(io::empty()));
     }
 }
@@ -0,0 +1,566 @@
+//! Integration tests for the analyzed routes. Every endpoint gets an `integration.rs` running requests
+//! through the app as it's set up in configure.rs. Routes taking validated data get a request per
+//! `#[validate]` rule, each violating that rule and nothing else, and expect the `VALIDATION` error.
+//! Routes wrapped in an auth guard are expected to respond with 401 to requests missing the session
+//! cookie or the csrf header.
+use crate::{
+    analyzer::audit::Guards,
+    config::{Endpoint, RouteHandler},
+    validation::Validation,
+    INDENT,
+};
+use quote::ToTokens;
+use std::{collections::HashMap, fmt::Write};
+
+/// The generated tests of an endpoint and the routes that couldn't be tested
+#[derive(Debug, Default)]
+pub struct IntegrationTests {
+    pub contents: String,
+    pub tests: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+/// A value of a request field
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Str(String),
+    Num(String),
+    Bool(bool),
+    List,
+}
+
+impl Value {
+    fn json(&self) -> String {
+        match self {
+            Self::Str(s) => format!("{s:?}"),
+            Self::Num(n) => n.clone(),
+            Self::Bool(b) => b.to_string(),
+            Self::List => "[]".to_string(),
+        }
+    }
+
+    /// The value in a url, which only ever has characters that don't need escaping
+    fn raw(&self) -> String {
+        match self {
+            Self::Str(s) => s.clone(),
+            Self::Num(n) => n.clone(),
+            Self::Bool(b) => b.to_string(),
+            Self::List => String::new(),
+        }
+    }
+}
+
+/// What a field deserializes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Str,
+    Int { min: i128, max: i128 },
+    Float,
+    Bool,
+    List,
+    Unknown,
+}
+
+impl Kind {
+    /// The kind of the type, looking through `Option`
+    fn of(ty: &str) -> Self {
+        let Ok(syn::Type::Path(p)) = syn::parse_str::<syn::Type>(ty) else {
+            return Self::Unknown;
+        };
+        let Some(last) = p.path.segments.last() else {
+            return Self::Unknown;
+        };
+        if last.ident == "Option" {
+            if let syn::PathArguments::AngleBracketed(ref args) = last.arguments {
+                if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                    return Self::of(&inner.to_token_stream().to_string());
+                }
+            }
+        }
+        let int = |bits: u32, signed: bool| match signed {
+            true => Self::Int {
+                min: -(1 << (bits - 1)),
+                max: (1 << (bits - 1)) - 1,
+            },
+            false => Self::Int {
+                min: 0,
+                max: (1 << bits) - 1,
+            },
+        };
+        match last.ident.to_string().as_str() {
+            "String" | "str" | "Cow" => Self::Str,
+            "u8" => int(8, false),
+            "u16" => int(16, false),
+            "u32" => int(32, false),
+            "u64" | "usize" => int(64, false),
+            "i8" => int(8, true),
+            "i16" => int(16, true),
+            "i32" => int(32, true),
+            "i64" | "isize" => int(64, true),
+            "f32" | "f64" => Self::Float,
+            "bool" => Self::Bool,
+            "Vec" | "HashSet" | "BTreeSet" => Self::List,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn num(&self, n: f64) -> Option<Value> {
+        match self {
+            Self::Int { min, max } => {
+                let n = n as i128;
+                (n >= *min && n <= *max).then(|| Value::Num(n.to_string()))
+            }
+            Self::Float => Some(Value::Num(n.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// A value passing every rule of the field, if one can be made up
+fn valid(kind: Kind, rules: &[Validation]) -> Option<Value> {
+    let mut value = None;
+    for rule in rules {
+        value = match (kind, rule) {
+            (Kind::Str, Validation::Email) => Some(Value::Str("user@example.com".to_string())),
+            (Kind::Str, Validation::Regex(name)) if name.to_lowercase().contains("email") => {
+                Some(Value::Str("user@example.com".to_string()))
+            }
+            (Kind::Str, Validation::Url) => Some(Value::Str("https://example.com".to_string())),
+            (Kind::Str, Validation::Length { min, max, equal }) => {
+                let len = equal
+                    .or(*min)
+                    .unwrap_or(1)
+                    .max(1)
+                    .min(max.unwrap_or(u64::MAX));
+                Some(Value::Str("a".repeat(len as usize)))
+            }
+            (Kind::Int { .. } | Kind::Float, Validation::Range { min, max }) => {
+                kind.num(min.unwrap_or(1f64.min(max.unwrap_or(1.))))
+            }
+            _ => continue,
+        };
+    }
+    match (kind, value) {
+        (_, Some(value)) => Some(value),
+        (Kind::Str, None) => Some(Value::Str("a".to_string())),
+        (Kind::Int { .. } | Kind::Float, None) => kind.num(1.),
+        (Kind::Bool, None) => Some(Value::Bool(true)),
+        (Kind::List, None) => Some(Value::List),
+        (Kind::Unknown, None) => None,
+    }
+}
+
+/// Values breaking the rule, with a description of the rule
+fn violations(kind: Kind, rule: &Validation) -> Vec<(String, Value)> {
+    let a = |n: u64| Value::Str("a".repeat(n as usize));
+    let mut violations = vec![];
+    match (kind, rule) {
+        (Kind::Str, Validation::Length { min, max, equal }) => {
+            if let Some(min) = min.filter(|m| *m > 0) {
+                violations.push((format!("length(min = {min})"), a(min - 1)));
+            }
+            if let Some(max) = max {
+                violations.push((format!("length(max = {max})"), a(max + 1)));
+            }
+            if let Some(equal) = equal {
+                violations.push((format!("length(equal = {equal})"), a(equal + 1)));
+            }
+        }
+        (Kind::Int { .. } | Kind::Float, Validation::Range { min, max }) => {
+            if let Some(v) = min.and_then(|min| kind.num(min - 1.)) {
+                violations.push((format!("range(min = {})", min.unwrap_or_default()), v));
+            }
+            if let Some(v) = max.and_then(|max| kind.num(max + 1.)) {
+                violations.push((format!("range(max = {})", max.unwrap_or_default()), v));
+            }
+        }
+        (Kind::Str, Validation::Email) => {
+            violations.push(("email".to_string(), Value::Str("invalid".to_string())))
+        }
+        (Kind::Str, Validation::Url) => {
+            violations.push(("url".to_string(), Value::Str("invalid".to_string())))
+        }
+        (Kind::Str, Validation::Regex(name)) => {
+            violations.push((format!("regex = {name:?}"), Value::Str(String::new())))
+        }
+        _ => {}
+    }
+    violations
+}
+
+/// Generates the integration tests of the endpoint
+pub fn tests(ep: &Endpoint, guards: &Guards) -> IntegrationTests {
+    let mut out = IntegrationTests::default();
+    let mut tests = String::new();
+    let (mut guarded, mut validated) = (false, false);
+    let mut handlers = HashMap::<&str, usize>::new();
+
+    for route in ep.routes.iter() {
+        let Some(ref handler) = route.handler else {
+            continue;
+        };
+        // Handlers registered on multiple routes get numbered
+        let count = handlers.entry(&handler.name).or_default();
+        *count += 1;
+        let name = match count {
+            1 => handler.name.clone(),
+            n => format!("{}_{n}", handler.name),
+        };
+
+        if guards.is_guarded(&ep.name, route) {
+            guarded = true;
+            let test = format!("{name}_requires_session");
+            session_test(&mut tests, &test, route);
+            out.tests.push(test);
+            // The guard rejects requests before they're validated
+            continue;
+        }
+
+        let Some(ref input) = route.input else {
+            continue;
+        };
+        let ext = handler
+            .inputs
+            .iter()
+            .find(|i| i.data_type == input.id)
+            .map(|i| i.ext_type.as_str())
+            .unwrap_or("Json");
+        let fields = input
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    f.wire_name().to_string(),
+                    f,
+                    Kind::of(&f.ty),
+                    Validation::parse_all(&f.validation),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut base = vec![];
+        let mut unknown = None;
+        for (name, field, kind, rules) in fields.iter() {
+            if !field.required {
+                continue;
+            }
+            match valid(*kind, rules) {
+                Some(v) => base.push((name.as_str(), v)),
+                None => unknown = Some(*field),
+            }
+        }
+        if let Some(field) = unknown {
+            out.skipped.push(format!(
+                "{} {}: no value for {}: {}",
+                route.method, route.path, field.name, field.ty
+            ));
+            continue;
+        }
+
+        let mut cases = vec![];
+        for (name, _, kind, rules) in fields.iter() {
+            for rule in rules.iter() {
+                for (desc, value) in violations(*kind, rule) {
+                    let mut payload = base.clone();
+                    match payload.iter_mut().find(|(n, _)| n == name) {
+                        Some(field) => field.1 = value,
+                        None => payload.push((name.as_str(), value)),
+                    }
+                    cases.push((format!("{name}: {desc}"), payload));
+                }
+            }
+        }
+        if cases.is_empty() {
+            continue;
+        }
+        validated = true;
+        let test = format!("{name}_validation");
+        validation_test(&mut tests, &test, route, ext, &cases);
+        out.tests.push(test);
+    }
+
+    if out.tests.is_empty() {
+        return out;
+    }
+    let buf = &mut out.contents;
+    writeln!(
+        buf,
+        "//! Integration tests for the {} routes generated by alx. They run against the app as it's set\n\
+        //! up in configure.rs, so the clients it connects to have to be available.",
+        ep.name
+    )
+    .unwrap();
+    writeln!(buf, "use crate::configure::configure;").unwrap();
+    match guarded {
+        true => writeln!(
+            buf,
+            "use actix_web::{{cookie::Cookie, http::StatusCode, test, App}};"
+        ),
+        false => writeln!(buf, "use actix_web::{{test, App}};"),
+    }
+    .unwrap();
+    match guarded {
+        true => writeln!(
+            buf,
+            "use infrastructure::{{config::env, web::http::cookie::S_ID}};"
+        ),
+        false => writeln!(buf, "use infrastructure::config::env;"),
+    }
+    .unwrap();
+    if validated {
+        writeln!(buf, "use serde_json::{{json, Value}};").unwrap();
+    }
+    buf.push_str(&tests);
+    out
+}
+
+fn test_header(buf: &mut String, name: &str) {
+    writeln!(
+        buf,
+        "\n#[actix_web::test]\n\
+        async fn {name}() {{\n\
+        {INDENT}env::load_from_file(\"../.env\").unwrap();\n\
+        {INDENT}let app = test::init_service(App::new().configure(configure)).await;"
+    )
+    .unwrap();
+}
+
+/// `test::TestRequest::post()` and the like
+fn request(method: &str) -> String {
+    match method {
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" => {
+            format!("test::TestRequest::{}()", method.to_lowercase())
+        }
+        "ANY" => "test::TestRequest::get()".to_string(),
+        _ => format!("test::TestRequest::default().method(actix_web::http::Method::{method})"),
+    }
+}
+
+/// The path with its `{param}` segments filled in from the values, or with `1` if there's none
+fn uri(path: &str, values: &[(&str, Value)]) -> String {
+    let mut uri = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let param = rest[start + 1..start + end]
+            .split(':')
+            .next()
+            .unwrap_or_default();
+        uri.push_str(&rest[..start]);
+        match values.iter().find(|(n, _)| *n == param) {
+            Some((_, v)) => uri.push_str(&v.raw()),
+            None => uri.push('1'),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    uri.push_str(rest);
+    uri
+}
+
+/// The request builder chain, on one line if it's short enough like rustfmt would have it
+fn build_request(method: &str, uri: &str, with: Option<&str>) -> String {
+    let mut calls = vec![format!(".uri({uri:?})")];
+    calls.extend(with.map(str::to_string));
+    calls.push(".to_request()".to_string());
+    let chain = format!("{}{}", request(method), calls.concat());
+    if chain.len() <= 60 {
+        return chain;
+    }
+    let mut chain = request(method);
+    for call in calls {
+        write!(chain, "\n{INDENT}{INDENT}{call}").unwrap();
+    }
+    chain
+}
+
+fn session_test(buf: &mut String, name: &str, route: &RouteHandler) {
+    test_header(buf, name);
+    let uri = uri(&route.path, &[]);
+    let cases = [
+        ("No session cookie or csrf header", None),
+        (
+            "Csrf header without a session",
+            Some(".insert_header((\"x-csrf-token\", \"token\"))"),
+        ),
+        (
+            "Session without a csrf header",
+            Some(".cookie(Cookie::new(S_ID, \"session\"))"),
+        ),
+    ];
+    for (desc, with) in cases {
+        writeln!(
+            buf,
+            "\n{INDENT}// {desc}\n\
+            {INDENT}let req = {};\n\
+            {INDENT}let res = test::call_service(&app, req).await;\n\
+            {INDENT}assert_eq!(res.status(), StatusCode::UNAUTHORIZED);",
+            build_request(&route.method, &uri, with),
+        )
+        .unwrap();
+    }
+    writeln!(buf, "}}").unwrap();
+}
+
+fn validation_test(
+    buf: &mut String,
+    name: &str,
+    route: &RouteHandler,
+    ext: &str,
+    cases: &[(String, Vec<(&str, Value)>)],
+) {
+    test_header(buf, name);
+    for (desc, payload) in cases {
+        let object = || {
+            let fields = payload
+                .iter()
+                .map(|(n, v)| format!("{n:?}: {}", v.json()))
+                .collect::<Vec<_>>();
+            format!("json!({{ {} }})", fields.join(", "))
+        };
+        let (uri, body) = match ext {
+            "Query" => {
+                let query = payload
+                    .iter()
+                    .map(|(n, v)| format!("{n}={}", v.raw()))
+                    .collect::<Vec<_>>();
+                (format!("{}?{}", route.path, query.join("&")), None)
+            }
+            "Path" => (uri(&route.path, payload), None),
+            "Form" => (
+                uri(&route.path, &[]),
+                Some(format!(".set_form({})", object())),
+            ),
+            _ => (
+                uri(&route.path, &[]),
+                Some(format!(".set_json({})", object())),
+            ),
+        };
+        writeln!(
+            buf,
+            "\n{INDENT}// {desc}\n\
+            {INDENT}let req = {};\n\
+            {INDENT}let body: Value = test::call_and_read_body_json(&app, req).await;\n\
+            {INDENT}assert_eq!(body[\"message\"], \"VALIDATION\");",
+            build_request(&route.method, &uri, body.as_deref()),
+        )
+        .unwrap();
+    }
+    writeln!(buf, "}}").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectConfig;
+    use quote::ToTokens;
+
+    #[test]
+    fn generates_tests() {
+        let pc = ProjectConfig::parse(
+            r#"
+endpoints:
+  - name: auth
+    routes:
+      - method: POST
+        path: /auth/register
+        handler:
+          name: start_registration
+          inputs:
+            - extractor: Json
+              data: RegistrationData
+          bound: null
+        middleware: null
+        service: Authentication
+        input:
+          id: RegistrationData
+          fields:
+            - name: email
+              ty: String
+              validation: ['regex = "EMAIL_REGEX"']
+            - name: user_name
+              rename: userName
+              ty: String
+              validation: [length (min = 2)]
+            - name: age
+              ty: Option<u8>
+              required: false
+              validation: [range (min = 18)]
+      - method: GET
+        path: /auth/set-otp
+        handler:
+          name: set_otp_secret
+          bound: null
+        middleware: [session]
+        service: Authentication
+        input: null
+"#
+            .to_string(),
+        )
+        .unwrap();
+        // Bound to a name that doesn't give it away
+        let setup = syn::parse_file(
+            "fn routes(cfg: &mut web::ServiceConfig) { let session = AuthGuard::new(Role::User); }",
+        )
+        .unwrap();
+        let mut guards = Guards::default();
+        guards.insert("auth", &setup);
+
+        let tests = tests(&pc.endpoints[0], &guards);
+        assert_eq!(
+            tests.tests,
+            [
+                "start_registration_validation",
+                "set_otp_secret_requires_session"
+            ]
+        );
+        let contents = tests.contents;
+        assert!(contents.contains(
+            r#"
+    // userName: length(min = 2)
+    let req = test::TestRequest::post()
+        .uri("/auth/register")
+        .set_json(json!({ "email": "user@example.com", "userName": "a" }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["message"], "VALIDATION");
+"#
+        ));
+        assert!(contents
+            .contains(r#"json!({ "email": "user@example.com", "userName": "aa", "age": 17 })"#));
+        assert!(contents.contains(
+            r#"
+    // Session without a csrf header
+    let req = test::TestRequest::get()
+        .uri("/auth/set-otp")
+        .cookie(Cookie::new(S_ID, "session"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+"#
+        ));
+
+        // `test` is imported from actix_web, so every test has to use its attribute
+        let file = syn::parse_file(&contents).unwrap();
+        let attrs = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Fn(f) => Some(&f.attrs),
+                _ => None,
+            })
+            .map(|attrs| {
+                attrs
+                    .iter()
+                    .map(|a| a.path.to_token_stream().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(attrs, [["actix_web :: test"], ["actix_web :: test"]]);
+        assert!(file.items.iter().all(|item| match item {
+            syn::Item::Fn(f) => f.sig.asyncness.is_some(),
+            _ => true,
+        }));
+    }
+}
@@ -0,0 +1,746 @@
+//! Test scaffolding for routes. Every method of the `ServiceContract` in contract.rs gets a test
+//! constructing the service from domain.rs with mockall mocks of its contracts. The calls the
+//! service makes to its contracts are expected and the inputs are built from the structs in
+//! data.rs.
+use super::{
+    contract::{is_impl_of, service_name},
+    edit::{offset, range, Edits},
+};
+use crate::{error::AlxError, INDENT};
+use quote::ToTokens;
+use std::{collections::HashMap, fmt::Write};
+use syn::{spanned::Spanned, visit::Visit};
+
+const AUTOMOCK: &str = "#[cfg_attr(test, mockall::automock)]";
+
+/// A method of a contract trait
+#[derive(Debug)]
+struct Method {
+    name: String,
+    /// Names and types of the parameters following `self`
+    params: Vec<(String, String)>,
+    output: Option<syn::Type>,
+    is_async: bool,
+    /// Whether the method has type parameters, which mockall needs spelled out in expectations
+    generic: bool,
+}
+
+impl Method {
+    fn new(sig: &syn::Signature) -> Self {
+        let params = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Typed(t) => {
+                    Some((t.pat.to_token_stream().to_string(), ty_string(&t.ty)))
+                }
+                syn::FnArg::Receiver(_) => None,
+            })
+            .collect();
+        Self {
+            name: sig.ident.to_string(),
+            params,
+            output: match sig.output {
+                syn::ReturnType::Type(_, ref ty) => Some(*ty.clone()),
+                syn::ReturnType::Default => None,
+            },
+            is_async: sig.asyncness.is_some(),
+            generic: sig
+                .generics
+                .params
+                .iter()
+                .any(|p| matches!(p, syn::GenericParam::Type(_))),
+        }
+    }
+}
+
+/// Adds the mockall attribute to the contract traits that don't have it
+pub fn automock(src: &str) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let mut edits = Edits::default();
+    for item in file.items.iter() {
+        let syn::Item::Trait(t) = item else {
+            continue;
+        };
+        let mocked = t
+            .attrs
+            .iter()
+            .any(|a| a.to_token_stream().to_string().contains("automock"));
+        if !t.ident.to_string().ends_with("Contract") || mocked {
+            continue;
+        }
+        let (start, _) = range(src, t.span());
+        edits.insert(start, format!("{AUTOMOCK}\n"));
+    }
+    match edits.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(edits.apply(src))),
+    }
+}
+
+/// Adds a test for every `ServiceContract` method without one to the tests in mod.rs. The placeholder
+/// test module of generated routes is replaced, other test modules get the tests appended. Returns
+/// the new mod.rs and the names of the tests added, `None` if every method has a test.
+pub fn tests_module(
+    mod_src: &str,
+    contract: &str,
+    domain: &str,
+    data: &str,
+) -> Result<Option<(String, Vec<String>)>, AlxError> {
+    let contract = syn::parse_file(contract)?;
+    let domain = syn::parse_file(domain)?;
+    let data = syn::parse_file(data)?;
+    let file = syn::parse_file(mod_src)?;
+
+    let traits = contract
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Trait(t) => Some((
+                t.ident.to_string(),
+                t.items
+                    .iter()
+                    .filter_map(|i| match i {
+                        syn::TraitItem::Method(m) => Some(Method::new(&m.sig)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let Some(methods) = traits.get("ServiceContract") else {
+        return Err(AlxError::Edit(
+            "no ServiceContract found in contract.rs".to_string(),
+        ));
+    };
+    let Some(service) = service_name(&domain) else {
+        return Err(AlxError::Edit(
+            "no ServiceContract implementation found in domain.rs".to_string(),
+        ));
+    };
+    let fields = service_contracts(&domain, &service);
+    let calls = contract_calls(&domain, &fields);
+    let data = data_structs(&data);
+
+    let tests = file.items.iter().find_map(|item| match item {
+        syn::Item::Mod(m) if m.ident == "tests" => Some(m),
+        _ => None,
+    });
+    let existing = tests
+        .and_then(|m| m.content.as_ref())
+        .map(|(_, items)| {
+            items
+                .iter()
+                .filter_map(|i| match i {
+                    syn::Item::Fn(f) => Some(f.sig.ident.to_string()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let placeholder = tests.is_some_and(is_placeholder);
+
+    let mut gen = TestGen {
+        service: &service,
+        fields: &fields,
+        traits: &traits,
+        data: &data,
+        uses: vec![],
+    };
+    gen.use_item("contract", "ServiceContract");
+    gen.use_item("domain", &service);
+    let mut fns = vec![];
+    let mut names = vec![];
+    for method in methods.iter() {
+        if !placeholder && existing.contains(&method.name) {
+            continue;
+        }
+        let empty = vec![];
+        let calls = calls.get(&method.name).unwrap_or(&empty);
+        fns.push(gen.test(method, calls));
+        names.push(method.name.clone());
+    }
+    if fns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut edits = Edits::default();
+    match (tests, placeholder) {
+        (Some(m), false) => {
+            let (brace, items) = m.content.as_ref().unwrap();
+            let (open, end) = range(mod_src, brace.span);
+            let start = offset(mod_src, brace.span.start()) + 1;
+            for (module, names) in gen.uses.iter() {
+                let path = module.split("::").collect::<Vec<_>>();
+                for name in names {
+                    edits.add_use(mod_src, items, start, &path, name);
+                }
+            }
+            for f in fns {
+                edits.push_to_block(mod_src, open, end - 1, &f);
+            }
+        }
+        (Some(m), true) => {
+            let (start, end) = range(mod_src, m.span());
+            edits.replace(start, end, gen.module(&fns).trim_end());
+        }
+        (None, _) => {
+            let module = gen.module(&fns);
+            match mod_src.trim().is_empty() {
+                true => edits.replace(0, mod_src.len(), module),
+                false => edits.insert(
+                    mod_src.trim_end().len(),
+                    format!("\n\n{}", module.trim_end()),
+                ),
+            }
+        }
+    }
+    Ok(Some((edits.apply(mod_src), names)))
+}
+
+/// The `#[test] fn test() { assert!(true) }` module generated with routes
+fn is_placeholder(m: &syn::ItemMod) -> bool {
+    match m.content {
+        Some((_, ref items)) => match items.as_slice() {
+            [syn::Item::Fn(f)] => {
+                f.sig.ident == "test"
+                    && f.block
+                        .to_token_stream()
+                        .to_string()
+                        .contains("assert ! (true)")
+            }
+            [] => true,
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+/// The service's fields and the contract bounding each, if any
+fn service_contracts(domain: &syn::File, service: &str) -> Vec<(String, Option<String>)> {
+    let Some(s) = domain.items.iter().find_map(|item| match item {
+        syn::Item::Struct(s) if s.ident == service => Some(s),
+        _ => None,
+    }) else {
+        return vec![];
+    };
+
+    // Generic params and the contract they're bound by, inline or in the where clause
+    let mut bounds = HashMap::new();
+    let mut bound = |param: String, b: &syn::TypeParamBound| {
+        if let syn::TypeParamBound::Trait(t) = b {
+            if let Some(last) = t.path.segments.last() {
+                if last.ident.to_string().ends_with("Contract") {
+                    bounds.insert(param, last.ident.to_string());
+                }
+            }
+        }
+    };
+    for param in s.generics.type_params() {
+        param
+            .bounds
+            .iter()
+            .for_each(|b| bound(param.ident.to_string(), b));
+    }
+    for pred in s
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|w| w.predicates.iter())
+    {
+        if let syn::WherePredicate::Type(t) = pred {
+            let param = t.bounded_ty.to_token_stream().to_string();
+            t.bounds.iter().for_each(|b| bound(param.clone(), b));
+        }
+    }
+
+    s.fields
+        .iter()
+        .filter_map(|f| {
+            let name = f.ident.as_ref()?.to_string();
+            Some((
+                name,
+                bounds.get(&f.ty.to_token_stream().to_string()).cloned(),
+            ))
+        })
+        .collect()
+}
+
+/// The `(field, method)` calls each method of the service's `ServiceContract` implementation
+/// makes to the service's fields, including the ones made by the methods it calls on `self`
+fn contract_calls(
+    domain: &syn::File,
+    fields: &[(String, Option<String>)],
+) -> HashMap<String, Vec<(String, String)>> {
+    let Some(imp) = domain.items.iter().find_map(|item| match item {
+        syn::Item::Impl(i) if is_impl_of(i, "ServiceContract") => Some(i),
+        _ => None,
+    }) else {
+        return HashMap::new();
+    };
+    let fields = fields.iter().map(|(f, _)| f.clone()).collect::<Vec<_>>();
+    let direct = imp
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::ImplItem::Method(m) => {
+                let mut visitor = CallVisitor {
+                    fields: &fields,
+                    calls: vec![],
+                    self_calls: vec![],
+                };
+                visitor.visit_block(&m.block);
+                Some((m.sig.ident.to_string(), visitor))
+            }
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    direct
+        .iter()
+        .map(|(name, visitor)| {
+            let mut calls = visitor.calls.clone();
+            for other in visitor.self_calls.iter().filter(|c| *c != name) {
+                if let Some(v) = direct.get(other) {
+                    calls.extend(v.calls.iter().cloned());
+                }
+            }
+            let mut unique = vec![];
+            for call in calls {
+                if !unique.contains(&call) {
+                    unique.push(call);
+                }
+            }
+            (name.clone(), unique)
+        })
+        .collect()
+}
+
+struct CallVisitor<'a> {
+    fields: &'a [String],
+    calls: Vec<(String, String)>,
+    self_calls: Vec<String>,
+}
+
+impl<'a, 'ast> Visit<'ast> for CallVisitor<'a> {
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let method = call.method.to_string();
+        match &*call.receiver {
+            syn::Expr::Field(f) if is_self(&f.base) => {
+                if let syn::Member::Named(ref field) = f.member {
+                    let field = field.to_string();
+                    if self.fields.contains(&field) {
+                        self.calls.push((field, method));
+                    }
+                }
+            }
+            receiver if is_self(receiver) => self.self_calls.push(method),
+            _ => {}
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+}
+
+fn is_self(expr: &syn::Expr) -> bool {
+    matches!(expr, syn::Expr::Path(p) if p.path.is_ident("self"))
+}
+
+/// The structs in data.rs and their named fields, `None` for unit structs
+fn data_structs(data: &syn::File) -> HashMap<String, Option<Vec<String>>> {
+    data.items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Struct(s) => Some((
+                s.ident.to_string(),
+                match s.fields {
+                    syn::Fields::Unit => None,
+                    _ => Some(
+                        s.fields
+                            .iter()
+                            .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+                            .collect(),
+                    ),
+                },
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+struct TestGen<'a> {
+    service: &'a str,
+    fields: &'a [(String, Option<String>)],
+    traits: &'a HashMap<String, Vec<Method>>,
+    data: &'a HashMap<String, Option<Vec<String>>>,
+    /// Modules and the names the tests need from them, i.e. `super::data`
+    uses: Vec<(String, Vec<String>)>,
+}
+
+impl<'a> TestGen<'a> {
+    fn use_item(&mut self, module: &str, name: &str) {
+        let module = match module {
+            "contract" | "data" | "domain" => format!("super::{module}"),
+            _ => module.to_string(),
+        };
+        match self.uses.iter_mut().find(|(m, _)| *m == module) {
+            Some((_, names)) if !names.iter().any(|n| n == name) => names.push(name.to_string()),
+            Some(_) => {}
+            None => self.uses.push((module, vec![name.to_string()])),
+        }
+    }
+
+    /// The test for the service method, unindented
+    fn test(&mut self, method: &Method, calls: &[(String, String)]) -> String {
+        let mut buf = String::new();
+        let (attr, asyncness, dot_await) = match method.is_async {
+            true => ("#[actix_web::main]\n", "async ", ".await"),
+            false => ("", "", ""),
+        };
+        writeln!(buf, "{attr}#[test]\n{asyncness}fn {}() {{", method.name).unwrap();
+
+        // Mocks of the contracts and the calls expected on them
+        let mut construct = vec![];
+        for (field, contract) in self.fields.iter() {
+            let Some(contract) = contract else {
+                construct.push(format!("{field}: todo!()"));
+                continue;
+            };
+            let mock = format!("Mock{contract}");
+            self.use_item("contract", &mock);
+            let expected = calls
+                .iter()
+                .filter(|(f, _)| f == field)
+                .filter_map(|(_, called)| {
+                    self.traits
+                        .get(contract)
+                        .and_then(|methods| methods.iter().find(|m| m.name == *called))
+                })
+                .collect::<Vec<_>>();
+            // Generic methods only get a comment
+            let binding = match expected.iter().all(|m| m.generic) {
+                true => field.to_string(),
+                false => format!("mut {field}"),
+            };
+            writeln!(buf, "{INDENT}let {binding} = {mock}::new();").unwrap();
+            for m in expected {
+                writeln!(buf, "{}", expectation(field, m)).unwrap();
+            }
+            construct.push(field.clone());
+        }
+        self.use_item("domain", self.service);
+        let fields = construct.join(", ");
+        // Same as rustfmt's struct literal width
+        match fields.len() {
+            0 => writeln!(buf, "{INDENT}let service = {};", self.service).unwrap(),
+            1..=18 => writeln!(
+                buf,
+                "{INDENT}let service = {} {{ {fields} }};",
+                self.service
+            )
+            .unwrap(),
+            _ => {
+                writeln!(buf, "{INDENT}let service = {} {{", self.service).unwrap();
+                for field in construct {
+                    writeln!(buf, "{INDENT}{INDENT}{field},").unwrap();
+                }
+                writeln!(buf, "{INDENT}}};").unwrap();
+            }
+        }
+
+        // Inputs
+        let mut args = vec![];
+        for (name, ty) in method.params.iter() {
+            let (annotation, value) = self.value(ty);
+            match annotation {
+                Some(ty) => writeln!(buf, "{INDENT}let {name}: {ty} = {value};").unwrap(),
+                None => writeln!(buf, "{INDENT}let {name} = {value};").unwrap(),
+            }
+            args.push(name.clone());
+        }
+
+        let call = format!("service.{}({}){dot_await}", method.name, args.join(", "));
+        let output = method
+            .output
+            .as_ref()
+            .map(|o| o.to_token_stream().to_string())
+            .unwrap_or_default();
+        let fallible = result_ok(method.output.as_ref()).is_some();
+        let unwrap = if fallible { ".unwrap()" } else { "" };
+        if output.contains("HttpResponse") {
+            self.use_item("actix_web::http", "StatusCode");
+            writeln!(buf, "{INDENT}let res = {call}{unwrap};").unwrap();
+            writeln!(buf, "{INDENT}assert_eq!(res.status(), StatusCode::OK);").unwrap();
+        } else {
+            writeln!(buf, "{INDENT}{call}{unwrap};").unwrap();
+        }
+        write!(buf, "}}").unwrap();
+        buf
+    }
+
+    /// The type annotation, if one is needed, and the value of a parameter of the given type
+    fn value(&mut self, ty: &str) -> (Option<String>, String) {
+        if ty == "&str" {
+            return (None, "\"\"".to_string());
+        }
+        if let Some(inner) = ty.strip_prefix('&') {
+            let (annotation, value) = self.value(inner.trim_start_matches("mut "));
+            return (annotation.map(|_| ty.to_string()), format!("&{value}"));
+        }
+        match self.data.get(ty) {
+            Some(fields) => {
+                self.use_item("data", ty);
+                let value = match fields {
+                    None => ty.to_string(),
+                    Some(fields) if fields.is_empty() => format!("{ty} {{}}"),
+                    Some(fields) => {
+                        let mut value = format!("{ty} {{\n");
+                        for f in fields {
+                            writeln!(value, "{INDENT}{INDENT}{f}: Default::default(),").unwrap();
+                        }
+                        write!(value, "{INDENT}}}").unwrap();
+                        value
+                    }
+                };
+                (None, value)
+            }
+            None => (Some(ty.to_string()), "Default::default()".to_string()),
+        }
+    }
+
+    fn module(&self, fns: &[String]) -> String {
+        let mut buf = String::from("#[cfg(test)]\nmod tests {\n");
+        let (local, external): (Vec<_>, Vec<_>) = self
+            .uses
+            .iter()
+            .partition(|(m, _)| m.starts_with("super::"));
+        let group = |names: &[String]| match names {
+            [name] => name.clone(),
+            _ => format!("{{{}}}", names.join(", ")),
+        };
+        match local.as_slice() {
+            [] => {}
+            [(module, names)] => writeln!(buf, "{INDENT}use {module}::{};", group(names)).unwrap(),
+            _ => {
+                writeln!(buf, "{INDENT}use super::{{").unwrap();
+                for (module, names) in local.iter() {
+                    let module = module.trim_start_matches("super::");
+                    writeln!(buf, "{INDENT}{INDENT}{module}::{},", group(names)).unwrap();
+                }
+                writeln!(buf, "{INDENT}}};").unwrap();
+            }
+        }
+        for (module, names) in external {
+            writeln!(buf, "{INDENT}use {module}::{};", group(names)).unwrap();
+        }
+        for f in fns {
+            writeln!(buf).unwrap();
+            for line in f.lines() {
+                match line.is_empty() {
+                    true => writeln!(buf).unwrap(),
+                    false => writeln!(buf, "{INDENT}{line}").unwrap(),
+                }
+            }
+        }
+        writeln!(buf, "}}").unwrap();
+        buf
+    }
+}
+
+/// `field.expect_method().return_once(|_, _| ..);`, on multiple lines when it's too long
+fn expectation(field: &str, m: &Method) -> String {
+    if m.generic {
+        return format!(
+            "{INDENT}// {field}.expect_{}::<..>(), mockall needs the type parameters of generic methods",
+            m.name
+        );
+    }
+    let args = vec!["_"; m.params.len()].join(", ");
+    let ret = mock_return(m.output.as_ref());
+    let chain = format!("{field}.expect_{}().return_once(|{args}| {ret})", m.name);
+    // Same as rustfmt's chain width
+    match chain.len() > 60 {
+        true => format!(
+            "{INDENT}{field}\n{INDENT}{INDENT}.expect_{}()\n{INDENT}{INDENT}.return_once(|{args}| {ret});",
+            m.name
+        ),
+        false => format!("{INDENT}{chain};"),
+    }
+}
+
+/// A successful return value for a mocked method, `todo!()` if there's no obvious one
+fn mock_return(output: Option<&syn::Type>) -> String {
+    let Some(output) = output else {
+        return "()".to_string();
+    };
+    let (ty, ok) = match result_ok(Some(output)) {
+        Some(ty) => (ty, true),
+        None => (output, false),
+    };
+    let value = match ty {
+        syn::Type::Tuple(t) if t.elems.is_empty() => Some("()"),
+        syn::Type::Path(p) => match p.path.segments.last().map(|s| s.ident.to_string()) {
+            Some(ident) => match ident.as_str() {
+                "Vec" => Some("vec![]"),
+                "Option" => Some("None"),
+                "bool" => Some("true"),
+                "String" => Some("String::new()"),
+                "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => {
+                    Some("0")
+                }
+                _ => None,
+            },
+            None => None,
+        },
+        _ => None,
+    };
+    match (value, ok) {
+        (Some(v), true) => format!("Ok({v})"),
+        (Some(v), false) => v.to_string(),
+        (None, _) => format!("todo!(\"return {}\")", ty_string(output)),
+    }
+}
+
+/// `T` of `Result<T, E>`
+fn result_ok(ty: Option<&syn::Type>) -> Option<&syn::Type> {
+    let syn::Type::Path(p) = ty? else {
+        return None;
+    };
+    let last = p.path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+    match last.arguments {
+        syn::PathArguments::AngleBracketed(ref args) => args.args.iter().find_map(|a| match a {
+            syn::GenericArgument::Type(t) => Some(t),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// The type as it would be written, without lifetimes, i.e. `Option<&str>` for `Option<&'a str>`
+fn ty_string(ty: &syn::Type) -> String {
+    let mut s = ty.to_token_stream().to_string();
+    for (from, to) in [
+        (" < ", "<"),
+        ("< ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        ("& ", "&"),
+        (" ,", ","),
+        (" :: ", "::"),
+        (":: ", "::"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+        (" ;", ";"),
+    ] {
+        s = s.replace(from, to);
+    }
+    // Lifetimes of references
+    while let Some(i) = s.find("&'") {
+        let end = s[i + 2..]
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .map_or(s.len(), |e| i + 2 + e);
+        s.replace_range(i + 1..(end + 1).min(s.len()), "");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_tests() {
+        let contract = r#"
+#[async_trait]
+pub(super) trait ServiceContract {
+    async fn get_paginated(&self, data: GetUsersPaginated) -> Result<HttpResponse, Error>;
+    async fn purge<'a>(&self, user_id: &str, skip: Option<&'a str>) -> Result<(), Error>;
+}
+
+#[async_trait]
+pub(super) trait RepositoryContract {
+    async fn get_paginated(&self, page: u16, per_page: u16) -> Result<Vec<User>, Error>;
+    async fn get_user(&self, id: &str) -> Result<User, Error>;
+}
+"#;
+        let domain = r#"
+pub(super) struct UserService<R: RepositoryContract> {
+    pub repository: R,
+}
+
+#[async_trait]
+impl<R> ServiceContract for UserService<R>
+where
+    R: RepositoryContract + Send + Sync,
+{
+    async fn get_paginated(&self, data: GetUsersPaginated) -> Result<HttpResponse, Error> {
+        let users = self.repository.get_paginated(1, 25).await?;
+        self.purge("", None).await?;
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    async fn purge<'a>(&self, user_id: &str, skip: Option<&'a str>) -> Result<(), Error> {
+        self.repository.get_user(user_id).await?;
+        Ok(())
+    }
+}
+"#;
+        let data = "pub(super) struct GetUsersPaginated {\n    pub page: Option<u16>,\n}\n";
+        let mod_src = "pub(super) mod contract;\n\n#[cfg(test)]\nmod tests {\n\n    #[test]\n    fn test() {\n        assert!(true)\n    }\n}";
+
+        let mocked = automock(contract).unwrap().unwrap();
+        assert_eq!(mocked.matches(AUTOMOCK).count(), 2);
+        assert!(automock(&mocked).unwrap().is_none());
+
+        let (out, names) = tests_module(mod_src, contract, domain, data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(names, ["get_paginated", "purge"]);
+        assert!(out.starts_with(
+            "pub(super) mod contract;\n\n#[cfg(test)]\nmod tests {\n    use super::{\n        contract::{ServiceContract, MockRepositoryContract},\n        domain::UserService,\n        data::GetUsersPaginated,\n    };\n    use actix_web::http::StatusCode;\n"
+        ));
+        assert!(out.contains(
+            r#"    #[actix_web::main]
+    #[test]
+    async fn get_paginated() {
+        let mut repository = MockRepositoryContract::new();
+        repository
+            .expect_get_paginated()
+            .return_once(|_, _| Ok(vec![]));
+        repository
+            .expect_get_user()
+            .return_once(|_| todo!("return Result<User, Error>"));
+        let service = UserService { repository };
+        let data = GetUsersPaginated {
+            page: Default::default(),
+        };
+        let res = service.get_paginated(data).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+"#
+        ));
+        assert!(out.contains(
+            "        let user_id = \"\";\n        let skip: Option<&str> = Default::default();\n        service.purge(user_id, skip).await.unwrap();\n    }\n}"
+        ));
+
+        // Existing tests are kept and only the missing ones added
+        let (again, names) = tests_module(
+            &out.replace("async fn purge()", "async fn other()"),
+            contract,
+            domain,
+            data,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(names, ["purge"]);
+        assert!(again.contains("async fn other()"));
+        assert!(tests_module(&out, contract, domain, data)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,2 +1,14 @@
+pub mod client;
+pub mod configure;
+pub mod contract;
+pub mod edit;
 pub mod files;
+pub mod integration;
+pub mod mock;
 pub mod plate;
+pub mod project;
+pub mod prune;
+pub mod repository;
+pub mod service;
+pub mod spec;
+pub mod template;
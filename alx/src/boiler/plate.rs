@@ -1,51 +1,41 @@
-use crate::{uppercase,  INDENT, MW_FILES, ROUTE_FILES};
+use super::configure::contract_clients;
+use crate::{settings::settings, uppercase,  INDENT};
 use std::fmt::Write;
 
+#[derive(Debug, Clone, Copy)]
 pub enum BoilerType {
     Route,
     MW,
 }
 
-pub fn r#mod(buf: &mut String, ty: BoilerType) {
-    match ty {
-        BoilerType::Route => {
-            for f in ROUTE_FILES {
-                if f != "mod" {
-                    if f == "setup" {
-                        writeln!(buf, "pub(crate) mod {};", f).unwrap();
-                    } else {
-                        writeln!(buf, "pub(super) mod {};", f).unwrap();
-                    }
-                }
-            }
-            write!(buf, "\n#[cfg(test)]\nmod tests {{\n\n{INDENT}#[test]\n{INDENT}fn test() {{\n{INDENT}{INDENT}assert!(true)\n{INDENT}}}\n}}").unwrap();
+impl BoilerType {
+    /// The file the rest of the crate uses the module through
+    pub fn entry(&self) -> &'static str {
+        match self {
+            BoilerType::Route => "setup",
+            BoilerType::MW => "interceptor",
         }
-        BoilerType::MW => {
-            for f in MW_FILES {
-                if f != "mod" {
-                    if f == "interceptor" {
-                        writeln!(buf, "pub(crate) mod {};", f).unwrap();
-                    } else {
-                        writeln!(buf, "pub(super) mod {};", f).unwrap();
-                    }
-                }
-            }
-            write!(buf, "\n#[cfg(test)]\nmod tests {{\n\n{INDENT}#[test]\n{INDENT}fn test() {{\n{INDENT}{INDENT}assert!(true)\n{INDENT}}}\n}}").unwrap();
+    }
+}
+
+pub fn r#mod(buf: &mut String, ty: BoilerType) {
+    let generator = settings().generator(ty);
+    for f in generator.files.iter() {
+        if f != "mod" {
+            writeln!(buf, "{} mod {};", generator.file_visibility(f, ty), f).unwrap();
         }
     }
+    write!(buf, "\n#[cfg(test)]\nmod tests {{\n\n{INDENT}#[test]\n{INDENT}fn test() {{\n{INDENT}{INDENT}assert!(true)\n{INDENT}}}\n}}").unwrap();
 }
 
 pub fn contracts(buf: &mut String, contracts: &[&str], ty: BoilerType) {
-    let vis = match ty {
-        BoilerType::Route => "super",
-        BoilerType::MW => "crate",
-    };
+    let vis = &settings().generator(ty).contract_visibility;
     writeln!(buf, "use async_trait::async_trait;\n").unwrap();
-    writeln!(buf, "#[cfg_attr(test, mockall::automock)]\n#[async_trait]\npub({vis}) trait ServiceContract {{}}").unwrap();
+    writeln!(buf, "#[cfg_attr(test, mockall::automock)]\n#[async_trait]\n{vis} trait ServiceContract {{}}").unwrap();
     for c in contracts {
         writeln!(
             buf,
-            "\n#[cfg_attr(test, mockall::automock)]\n#[async_trait]\npub({vis}) trait {}Contract {{}}",
+            "\n#[cfg_attr(test, mockall::automock)]\n#[async_trait]\n{vis} trait {}Contract {{}}",
             uppercase(c)
         ).unwrap();
     }
@@ -115,9 +105,31 @@ pub fn setup(buf: &mut String, service_name: &str, contracts: &[&str]) {
     } else {
         writeln!(use_stmt, "}};").unwrap();
     }
-    write!(use_stmt, "use actix_web::web;\n\n").unwrap();
+    writeln!(use_stmt, "use actix_web::web;").unwrap();
+
+    // The clients the contracts need get passed in from configure.rs
+    let clients = contract_clients(contracts);
+    for client in clients.iter() {
+        writeln!(use_stmt, "use {};", client.path).unwrap();
+    }
+    if !clients.is_empty() {
+        writeln!(use_stmt, "use std::sync::Arc;").unwrap();
+    }
+    writeln!(use_stmt).unwrap();
 
-    let fn_stmt = String::from("pub(crate) fn routes(cfg: &mut web::ServiceConfig) {}");
+    let mut params = clients
+        .iter()
+        .map(|c| format!("{}: Arc<{}>", c.param, c.ty))
+        .collect::<Vec<_>>();
+    params.push(String::from("cfg: &mut web::ServiceConfig"));
+    let mut fn_stmt = format!("pub(crate) fn routes({}) {{}}", params.join(", "));
+    // Same as rustfmt would
+    if fn_stmt.len() > 100 {
+        fn_stmt = format!(
+            "pub(crate) fn routes(\n{INDENT}{},\n) {{}}",
+            params.join(&format!(",\n{INDENT}"))
+        );
+    }
 
     write!(buf, "{}{}", use_stmt, fn_stmt).unwrap();
 }
@@ -0,0 +1,340 @@
+//! Lays out a new project from the starter workspace. The starter comes with every component, so the
+//! ones that aren't selected are taken out: their files are deleted, their `mod` declarations
+//! removed, the code referring to them pruned and their crates dropped from the manifests.
+use super::{
+    edit::remove_mod,
+    prune::{prune, Removed},
+};
+use crate::{error::AlxError, print};
+use colored::Colorize;
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What's copied from the starter workspace
+pub const COPIED: [&str; 4] = ["infrastructure", "server", "resources", "openssl"];
+pub const MEMBERS: [&str; 2] = ["infrastructure", "server"];
+
+/// Never copied, wherever they are
+const SKIPPED_DIRS: [&str; 1] = ["target"];
+/// Generated for each project
+const SKIPPED_EXTENSIONS: [&str; 3] = ["pem", "csr", "srl"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Component {
+    /// The auth and users routes and the auth guard middleware. Requires redis and email.
+    Auth,
+    /// The redis client and the cache helper
+    Redis,
+    /// The mongo client
+    Mongo,
+    /// The SMTP client, the email service and the email templates
+    Email,
+    /// The websocket session and handlers
+    Websocket,
+}
+
+/// Where a component lives in the starter
+#[derive(Debug)]
+pub struct Parts {
+    /// Files and directories relative to the workspace root
+    pub files: &'static [&'static str],
+    /// Path patterns as in `alx lint` of the code referring to the component
+    pub paths: &'static [&'static str],
+    /// Messages of the log statements about the component
+    pub logs: &'static [&'static str],
+    /// Crates only the component uses, as `(manifest, crate)`
+    pub deps: &'static [(&'static str, &'static str)],
+    pub requires: &'static [Component],
+}
+
+impl Component {
+    pub const ALL: [Self; 5] = [
+        Self::Auth,
+        Self::Redis,
+        Self::Mongo,
+        Self::Email,
+        Self::Websocket,
+    ];
+
+    pub fn parts(&self) -> Parts {
+        match self {
+            Self::Auth => Parts {
+                files: &[
+                    "server/src/api/router/auth",
+                    "server/src/api/router/users",
+                    "server/src/api/middleware/auth",
+                ],
+                paths: &["router::auth", "router::users", "middleware::auth"],
+                logs: &[],
+                deps: &[],
+                requires: &[Self::Redis, Self::Email],
+            },
+            Self::Redis => Parts {
+                files: &[
+                    "infrastructure/src/clients/store/redis.rs",
+                    "server/src/helpers/cache.rs",
+                ],
+                paths: &["clients::store::redis", "helpers::cache", "r2d2_redis"],
+                logs: &["Redis"],
+                deps: &[("infrastructure", "r2d2_redis")],
+                requires: &[],
+            },
+            Self::Mongo => Parts {
+                files: &["infrastructure/src/clients/store/mongo.rs"],
+                paths: &["clients::store::mongo", "mongodb"],
+                logs: &["Mongo"],
+                deps: &[("infrastructure", "mongodb")],
+                requires: &[],
+            },
+            Self::Email => Parts {
+                files: &[
+                    "infrastructure/src/clients/email.rs",
+                    "infrastructure/src/services/email.rs",
+                    "resources/emails",
+                ],
+                paths: &["clients::email", "services::email", "lettre"],
+                logs: &["Email"],
+                deps: &[("infrastructure", "lettre")],
+                requires: &[],
+            },
+            Self::Websocket => Parts {
+                files: &["infrastructure/src/web/websocket"],
+                paths: &["web::websocket", "actix_web_actors"],
+                logs: &[],
+                deps: &[("infrastructure", "actix-web-actors")],
+                requires: &[],
+            },
+        }
+    }
+}
+
+impl Display for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Auth => "auth",
+            Self::Redis => "redis",
+            Self::Mongo => "mongo",
+            Self::Email => "email",
+            Self::Websocket => "websocket",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Auth unless left out, the components asked for and whatever those require, sorted
+pub fn select(with: &[Component], without: &[Component]) -> Result<Vec<Component>, AlxError> {
+    if let Some(c) = with.iter().find(|c| without.contains(c)) {
+        return Err(AlxError::Project(format!(
+            "{c} can't be both included and left out"
+        )));
+    }
+    let mut selected = with.to_vec();
+    if !without.contains(&Component::Auth) {
+        selected.push(Component::Auth);
+    }
+    let mut i = 0;
+    while i < selected.len() {
+        let c = selected[i];
+        for req in c.parts().requires {
+            if without.contains(req) {
+                return Err(AlxError::Project(format!(
+                    "{c} requires {req}, leave out {c} as well with `--without {c},{req}`"
+                )));
+            }
+            if !selected.contains(req) {
+                selected.push(*req);
+            }
+        }
+        i += 1;
+    }
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+/// Copies the directory skipping build output and generated keys and certificates
+pub fn copy(from: &Path, to: &Path) -> Result<(), AlxError> {
+    fs::create_dir_all(to)?;
+    let mut entries = fs::read_dir(from)?
+        .flatten()
+        .map(|e| e.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for path in entries {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let target = to.join(name);
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()) {
+                copy(&path, &target)?;
+            }
+            continue;
+        }
+        let skipped = path
+            .extension()
+            .is_some_and(|ext| SKIPPED_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()));
+        if !skipped {
+            fs::copy(&path, target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Takes the components out of the project at `root`
+pub fn strip(root: &Path, removed: &[Component]) -> Result<(), AlxError> {
+    let parts = removed.iter().map(Component::parts).collect::<Vec<_>>();
+    for file in parts.iter().flat_map(|p| p.files.iter()) {
+        let path = root.join(file);
+        print(&format!("{} Removing {}", "\u{2717}".red(), path.display()));
+        match path.is_dir() {
+            true => fs::remove_dir_all(&path)?,
+            false => fs::remove_file(&path)?,
+        }
+        remove_mod_declaration(&path)?;
+    }
+
+    let paths = parts
+        .iter()
+        .flat_map(|p| p.paths.iter().copied())
+        .collect::<Vec<_>>();
+    let logs = parts
+        .iter()
+        .flat_map(|p| p.logs.iter().copied())
+        .collect::<Vec<_>>();
+    let removed_code = Removed {
+        paths: &paths,
+        logs: &logs,
+    };
+    let mut files = vec![];
+    for member in MEMBERS {
+        rust_files(&root.join(member), &mut files)?;
+    }
+    for file in files {
+        let src = fs::read_to_string(&file)?;
+        if let Some(pruned) = prune(&src, &removed_code)? {
+            print(&format!("{} Pruning {}", "\u{270E}".blue(), file.display()));
+            fs::write(&file, pruned)?;
+        }
+    }
+
+    for (member, dep) in parts.iter().flat_map(|p| p.deps.iter()) {
+        let manifest = root.join(member).join("Cargo.toml");
+        print(&format!(
+            "{} Removing {} from {}",
+            "\u{2717}".red(),
+            dep,
+            manifest.display()
+        ));
+        let src = fs::read_to_string(&manifest)?;
+        let kept = src
+            .lines()
+            .filter(|l| l.split_once('=').map(|(k, _)| k.trim()) != Some(dep))
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(&manifest, drop_empty_sections(&kept))?;
+    }
+    Ok(())
+}
+
+/// Removes the `mod` declaration of the module at the path from its parent, if it has one
+fn remove_mod_declaration(path: &Path) -> Result<(), AlxError> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_stem()) else {
+        return Ok(());
+    };
+    let Some(parent) = ["mod.rs", "lib.rs", "main.rs"]
+        .iter()
+        .map(|f| dir.join(f))
+        .find(|f| f.exists())
+    else {
+        return Ok(());
+    };
+    let src = fs::read_to_string(&parent)?;
+    if let Some(src) = remove_mod(&src, &name.to_string_lossy())? {
+        print(&format!(
+            "{} Writing {}",
+            "\u{270E}".blue(),
+            parent.display()
+        ));
+        fs::write(&parent, src)?;
+    }
+    Ok(())
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), AlxError> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            rust_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Removes the `#` comment headings left with nothing under them, i.e. `# Redis` once the redis
+/// variables are gone, and the blank lines left around them. A heading is empty if the next one has
+/// as many or more `#`s.
+pub fn drop_empty_sections(src: &str) -> String {
+    let level = |l: &str| {
+        let l = l.trim();
+        l.starts_with('#')
+            .then(|| l.chars().take_while(|c| *c == '#').count())
+    };
+    let mut lines = src.lines().collect::<Vec<_>>();
+    loop {
+        let empty = (0..lines.len()).find(|i| {
+            let Some(heading) = level(lines[*i]) else {
+                return false;
+            };
+            match lines[i + 1..].iter().find(|l| !l.trim().is_empty()) {
+                Some(next) => level(next).is_some_and(|n| n >= heading),
+                None => true,
+            }
+        });
+        let Some(i) = empty else {
+            break;
+        };
+        lines.remove(i);
+        // Along with the blank line after it
+        if lines.get(i).is_some_and(|l| l.trim().is_empty()) {
+            lines.remove(i);
+        }
+    }
+    // Whatever was between the blank lines around a removed section is gone too
+    lines.dedup_by(|a, b| a.trim().is_empty() && b.trim().is_empty());
+    while lines.first().is_some_and(|l| l.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_components_and_drops_empty_sections() {
+        use Component::*;
+        assert_eq!(select(&[], &[]).unwrap(), [Auth, Redis, Email]);
+        assert_eq!(select(&[Mongo], &[Auth]).unwrap(), [Mongo]);
+        assert!(select(&[], &[Redis]).is_err());
+        assert!(select(&[Email], &[Email]).is_err());
+
+        let src =
+            "### STORAGE ###\n\n# Postgres\nPG_USER =\n\n# Redis\n\n# Mongo\n\n### EMAIL ###\n";
+        assert_eq!(
+            drop_empty_sections(src),
+            "### STORAGE ###\n\n# Postgres\nPG_USER =\n"
+        );
+    }
+}
@@ -0,0 +1,575 @@
+//! Removes the code referring to modules and crates that are no longer there. Every reference is
+//! removed along with the innermost enclosing import, variant, field, parameter, argument, match arm,
+//! statement or item. Whatever a removed piece of code declares, such as the binding of a `let` or
+//! the name of a variant, is removed in turn until nothing is left referring to removed code.
+use super::edit::range;
+use crate::{analyzer::lint::matches, error::AlxError};
+use proc_macro2::{Span, TokenTree};
+use std::collections::HashSet;
+use syn::{spanned::Spanned, visit::Visit};
+
+/// What to remove from a file
+#[derive(Debug, Default)]
+pub struct Removed<'a> {
+    /// Path patterns as in `alx lint`, i.e. `clients::store::redis` or `lettre`
+    pub paths: &'a [&'a str],
+    /// Log statements with any of these in their message, i.e. `Redis` for
+    /// `info!("Redis pool initialized")`
+    pub logs: &'a [&'a str],
+}
+
+/// Prunes the source. Returns `None` if nothing refers to removed code.
+pub fn prune(src: &str, removed: &Removed) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let mut names = HashSet::new();
+    loop {
+        let mut scan = Scan {
+            removed,
+            names: &names,
+            units: vec![],
+            stack: vec![],
+            fns: vec![],
+        };
+        scan.visit_file(&file);
+        let units = scan
+            .units
+            .into_iter()
+            .filter(|u| u.removed)
+            .collect::<Vec<_>>();
+        let new = units
+            .iter()
+            .flat_map(|u| u.names.iter().map(|n| (u.scope, n.clone())))
+            .filter(|n| !names.contains(n))
+            .collect::<Vec<_>>();
+        if new.is_empty() {
+            return Ok((!units.is_empty()).then(|| apply(src, &units)));
+        }
+        names.extend(new);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Item,
+    /// Comma separated, i.e. variants, fields and parameters
+    Listed,
+    /// Function call arguments and struct literal fields, only removed when the value they pass is.
+    /// Method calls are removed with their statement instead.
+    Value,
+    Stmt,
+    Arm,
+    /// Test functions are removed whole, a test missing statements would test something else
+    Test,
+}
+
+/// Where a declared name can be referred to from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Scope {
+    /// Items, variants and imports
+    File,
+    /// Struct fields, referred to by member accesses only
+    Field,
+    /// Bindings and parameters of the function starting at the line and column
+    Local(usize, usize),
+}
+
+#[derive(Debug)]
+struct Unit {
+    kind: Kind,
+    span: Span,
+    /// What the unit declares
+    names: Vec<String>,
+    scope: Scope,
+    removed: bool,
+}
+
+struct Scan<'a> {
+    removed: &'a Removed<'a>,
+    /// Names declared by removed code
+    names: &'a HashSet<(Scope, String)>,
+    units: Vec<Unit>,
+    /// Indexes of the units enclosing the node being visited
+    stack: Vec<usize>,
+    /// Scopes of the functions enclosing the node being visited
+    fns: Vec<Scope>,
+}
+
+impl<'a> Scan<'a> {
+    fn enter(&mut self, kind: Kind, span: Span, names: Vec<String>) {
+        let scope = match kind {
+            Kind::Stmt => self.local(),
+            _ => Scope::File,
+        };
+        self.enter_scoped(kind, span, names, scope);
+    }
+
+    fn enter_scoped(&mut self, kind: Kind, span: Span, names: Vec<String>, scope: Scope) {
+        self.units.push(Unit {
+            kind,
+            span,
+            names,
+            scope,
+            removed: false,
+        });
+        self.stack.push(self.units.len() - 1);
+    }
+
+    fn exit(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Removes the innermost unit enclosing the reference. References to removed values remove the
+    /// arguments and fields passing them, any other reference the code around them.
+    fn mention(&mut self, value: bool) {
+        if let Some(i) = self
+            .stack
+            .iter()
+            .find(|i| self.units[**i].kind == Kind::Test)
+        {
+            self.units[*i].removed = true;
+            return;
+        }
+        for i in self.stack.iter().rev() {
+            let unit = &mut self.units[*i];
+            if unit.kind == Kind::Value && !value {
+                continue;
+            }
+            unit.removed = true;
+            return;
+        }
+    }
+
+    /// The scope of the bindings of the innermost function
+    fn local(&self) -> Scope {
+        self.fns.last().copied().unwrap_or(Scope::File)
+    }
+
+    fn is_named(&self, scope: Scope, name: &str) -> bool {
+        self.names.contains(&(scope, name.to_string()))
+    }
+
+    /// Whether the name refers to removed code where it's used as a path or in a macro
+    fn is_removed_name(&self, name: &str) -> bool {
+        self.is_named(Scope::File, name) || self.is_named(self.local(), name)
+    }
+
+    fn is_removed(&self, path: &[String]) -> bool {
+        self.removed.paths.iter().any(|p| matches(p, path))
+            || path.iter().any(|s| self.is_named(Scope::File, s))
+            || path.first().is_some_and(|s| self.is_named(self.local(), s))
+    }
+
+    /// Visits a function with its parameters and bindings in their own scope
+    fn function(
+        &mut self,
+        span: Span,
+        attrs: &[syn::Attribute],
+        name: &syn::Ident,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        let start = span.start();
+        let is_test = attrs
+            .iter()
+            .any(|a| a.path.segments.last().is_some_and(|s| s.ident == "test"));
+        let kind = if is_test { Kind::Test } else { Kind::Item };
+        self.enter(kind, span, vec![name.to_string()]);
+        self.fns.push(Scope::Local(start.line, start.column));
+        visit(self);
+        self.fns.pop();
+        self.exit();
+    }
+
+    /// Removes the leaves of the use tree importing removed code. Returns the names imported by the
+    /// tree if all of it is removed, leaving the removal to the parent.
+    fn use_tree(&mut self, prefix: &mut Vec<String>, tree: &syn::UseTree) -> Option<Vec<String>> {
+        match tree {
+            syn::UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                let removed = self.use_tree(prefix, &p.tree);
+                prefix.pop();
+                removed
+            }
+            syn::UseTree::Name(n) => {
+                let mut path = prefix.clone();
+                path.push(n.ident.to_string());
+                self.is_removed(&path).then(|| vec![n.ident.to_string()])
+            }
+            syn::UseTree::Rename(r) => {
+                let mut path = prefix.clone();
+                path.push(r.ident.to_string());
+                self.is_removed(&path).then(|| vec![r.rename.to_string()])
+            }
+            syn::UseTree::Glob(_) => self.is_removed(prefix).then(Vec::new),
+            syn::UseTree::Group(g) => {
+                let items = g
+                    .items
+                    .iter()
+                    .map(|item| (item, self.use_tree(prefix, item)))
+                    .collect::<Vec<_>>();
+                if items.iter().all(|(_, removed)| removed.is_some()) {
+                    return Some(items.into_iter().flat_map(|(_, r)| r.unwrap()).collect());
+                }
+                for (item, removed) in items {
+                    if let Some(names) = removed {
+                        self.units.push(Unit {
+                            kind: Kind::Listed,
+                            span: item.span(),
+                            names,
+                            scope: Scope::File,
+                            removed: true,
+                        });
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    fn macro_tokens(&mut self, mac: &syn::Macro) {
+        let is_log = mac.path.segments.last().is_some_and(|s| {
+            ["trace", "debug", "info", "warn", "error"].contains(&s.ident.to_string().as_str())
+        });
+        let mut tokens = mac.tokens.clone().into_iter().collect::<Vec<_>>();
+        while let Some(token) = tokens.pop() {
+            match token {
+                TokenTree::Ident(i) if self.is_removed_name(&i.to_string()) => {
+                    return self.mention(true)
+                }
+                TokenTree::Literal(l)
+                    if is_log && self.removed.logs.iter().any(|s| l.to_string().contains(s)) =>
+                {
+                    return self.mention(false)
+                }
+                TokenTree::Group(g) => tokens.extend(g.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast, 'a> Visit<'ast> for Scan<'a> {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        if let syn::Item::Use(u) = item {
+            if let Some(names) = self.use_tree(&mut vec![], &u.tree) {
+                self.units.push(Unit {
+                    kind: Kind::Item,
+                    span: u.span(),
+                    names,
+                    scope: Scope::File,
+                    removed: true,
+                });
+            }
+            return;
+        }
+        if let syn::Item::Fn(f) = item {
+            return self.function(f.span(), &f.attrs, &f.sig.ident, |s| {
+                syn::visit::visit_item_fn(s, f)
+            });
+        }
+        let name = match item {
+            syn::Item::Const(i) => Some(&i.ident),
+            syn::Item::Enum(i) => Some(&i.ident),
+            syn::Item::Mod(i) => Some(&i.ident),
+            syn::Item::Static(i) => Some(&i.ident),
+            syn::Item::Struct(i) => Some(&i.ident),
+            syn::Item::Trait(i) => Some(&i.ident),
+            syn::Item::Type(i) => Some(&i.ident),
+            _ => None,
+        };
+        self.enter(
+            Kind::Item,
+            item.span(),
+            name.map(|n| n.to_string()).into_iter().collect(),
+        );
+        syn::visit::visit_item(self, item);
+        self.exit();
+    }
+
+    fn visit_impl_item_method(&mut self, m: &'ast syn::ImplItemMethod) {
+        self.function(m.span(), &m.attrs, &m.sig.ident, |s| {
+            syn::visit::visit_impl_item_method(s, m)
+        });
+    }
+
+    fn visit_trait_item_method(&mut self, m: &'ast syn::TraitItemMethod) {
+        self.function(m.span(), &m.attrs, &m.sig.ident, |s| {
+            syn::visit::visit_trait_item_method(s, m)
+        });
+    }
+
+    fn visit_variant(&mut self, v: &'ast syn::Variant) {
+        self.enter(Kind::Listed, v.span(), vec![v.ident.to_string()]);
+        syn::visit::visit_variant(self, v);
+        self.exit();
+    }
+
+    fn visit_field(&mut self, f: &'ast syn::Field) {
+        // Tuple fields can't be removed without changing the position of the others
+        let Some(ref ident) = f.ident else {
+            return syn::visit::visit_field(self, f);
+        };
+        self.enter_scoped(
+            Kind::Listed,
+            f.span(),
+            vec![ident.to_string()],
+            Scope::Field,
+        );
+        // Fields no longer set in a struct literal
+        if self.is_named(Scope::Field, &ident.to_string()) {
+            self.mention(false);
+        }
+        syn::visit::visit_field(self, f);
+        self.exit();
+    }
+
+    fn visit_field_value(&mut self, f: &'ast syn::FieldValue) {
+        let name = match f.member {
+            syn::Member::Named(ref i) => vec![i.to_string()],
+            syn::Member::Unnamed(_) => vec![],
+        };
+        let removed = name.iter().any(|n| self.is_named(Scope::Field, n));
+        self.enter_scoped(Kind::Value, f.span(), name, Scope::Field);
+        if removed {
+            self.mention(true);
+        }
+        syn::visit::visit_field_value(self, f);
+        self.exit();
+    }
+
+    fn visit_field_pat(&mut self, f: &'ast syn::FieldPat) {
+        if let syn::Member::Named(ref i) = f.member {
+            if self.is_named(Scope::Field, &i.to_string()) {
+                self.enter(Kind::Listed, f.span(), vec![]);
+                self.mention(false);
+                self.exit();
+            }
+        }
+        syn::visit::visit_field_pat(self, f);
+    }
+
+    fn visit_fn_arg(&mut self, arg: &'ast syn::FnArg) {
+        let syn::FnArg::Typed(t) = arg else {
+            return syn::visit::visit_fn_arg(self, arg);
+        };
+        let names = match *t.pat {
+            syn::Pat::Ident(ref i) => vec![i.ident.to_string()],
+            _ => vec![],
+        };
+        self.enter_scoped(Kind::Listed, arg.span(), names, self.local());
+        syn::visit::visit_fn_arg(self, arg);
+        self.exit();
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        self.visit_expr(&call.func);
+        for arg in call.args.iter() {
+            self.enter(Kind::Value, arg.span(), vec![]);
+            self.visit_expr(arg);
+            self.exit();
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast syn::Stmt) {
+        let names = match stmt {
+            syn::Stmt::Local(l) => {
+                let mut idents = PatIdents::default();
+                idents.visit_pat(&l.pat);
+                idents.0
+            }
+            _ => vec![],
+        };
+        self.enter(Kind::Stmt, stmt.span(), names);
+        syn::visit::visit_stmt(self, stmt);
+        self.exit();
+    }
+
+    fn visit_arm(&mut self, arm: &'ast syn::Arm) {
+        self.enter(Kind::Arm, arm.span(), vec![]);
+        syn::visit::visit_arm(self, arm);
+        self.exit();
+    }
+
+    fn visit_path(&mut self, p: &'ast syn::Path) {
+        let segments = p
+            .segments
+            .iter()
+            .map(|s| s.ident.to_string())
+            .collect::<Vec<_>>();
+        if self.is_removed(&segments) {
+            self.mention(segments.len() == 1);
+        }
+        syn::visit::visit_path(self, p);
+    }
+
+    fn visit_expr_field(&mut self, f: &'ast syn::ExprField) {
+        if let syn::Member::Named(ref i) = f.member {
+            if self.is_named(Scope::Field, &i.to_string()) {
+                self.mention(true);
+            }
+        }
+        syn::visit::visit_expr_field(self, f);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.macro_tokens(mac);
+        syn::visit::visit_macro(self, mac);
+    }
+
+    // Attributes such as `#[error("..")]` go with what they're on
+    fn visit_attribute(&mut self, _: &'ast syn::Attribute) {}
+}
+
+/// Identifiers bound by a pattern
+#[derive(Default)]
+struct PatIdents(Vec<String>);
+
+impl<'ast> Visit<'ast> for PatIdents {
+    fn visit_pat_ident(&mut self, p: &'ast syn::PatIdent) {
+        self.0.push(p.ident.to_string());
+        syn::visit::visit_pat_ident(self, p);
+    }
+}
+
+/// Removes the units from the source along with the comma separating listed ones and the lines
+/// left empty
+fn apply(src: &str, units: &[Unit]) -> String {
+    let mut ranges = units
+        .iter()
+        .map(|u| {
+            let (start, end) = range(src, u.span);
+            match u.kind {
+                Kind::Listed | Kind::Value => with_comma(src, start, end),
+                _ => (start, end),
+            }
+        })
+        .map(|(start, end)| whole_lines(src, start, end))
+        .collect::<Vec<_>>();
+    ranges.sort();
+
+    let mut out = String::with_capacity(src.len());
+    let mut last = 0;
+    for (start, end) in ranges {
+        if end <= last {
+            continue;
+        }
+        out.push_str(&src[last..start.max(last)]);
+        last = end;
+    }
+    out.push_str(&src[last..]);
+    while out.contains("\n\n\n") {
+        out = out.replace("\n\n\n", "\n\n");
+    }
+    out
+}
+
+/// Extends the range of a list item over the comma after it, or the one before it if it's last
+fn with_comma(src: &str, start: usize, end: usize) -> (usize, usize) {
+    let after = &src[end..];
+    let trimmed = after.trim_start();
+    if trimmed.starts_with(',') {
+        let mut end = end + after.len() - trimmed.len() + 1;
+        while src[end..].starts_with(' ') {
+            end += 1;
+        }
+        return (start, end);
+    }
+    let before = src[..start].trim_end();
+    match before.ends_with(',') {
+        true => (before.len() - 1, end),
+        false => (start, end),
+    }
+}
+
+/// Extends the range over the lines it's on if nothing else is on them
+fn whole_lines(src: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = src[end..].find('\n').map_or(src.len(), |i| end + i + 1);
+    match src[line_start..start].trim().is_empty() && src[end..line_end].trim().is_empty() {
+        true => (line_start, line_end),
+        false => (start, end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prunes_references_to_removed_code() {
+        let src = r#"use actix_web::web::{self, Data, ServiceConfig};
+use infrastructure::clients::store::{postgres::Postgres, redis::Redis};
+use std::sync::Arc;
+
+pub(crate) fn route(pg: Arc<Postgres>, rd: Arc<Redis>, cfg: &mut ServiceConfig) {
+    let pools = Data::new(Pools { pg, rd });
+    cfg.app_data(pools);
+    info!("Redis pool initialized");
+}
+
+async fn health_check(pools: web::Data<Pools>) -> impl actix_web::Responder {
+    let pg_state = pools.pg.health_check();
+    let rd_state = pools.rd.health_check();
+    HttpResponseBuilder::new(StatusCode::OK).json(HealthCheck {
+        pg_connections: pg_state.connections,
+        rd_connections: rd_state.connections,
+    })
+}
+
+struct Pools {
+    pub pg: Arc<Postgres>,
+    pub rd: Arc<Redis>,
+}
+
+struct HealthCheck {
+    pg_connections: u32,
+    rd_connections: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Redis Error: {0}")]
+    Redis(#[from] redis::RedisError),
+    #[error("Serde Error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+"#;
+        let removed = Removed {
+            paths: &["clients::store::redis", "redis"],
+            logs: &["Redis"],
+        };
+        assert_eq!(
+            prune(src, &removed).unwrap().unwrap(),
+            r#"use actix_web::web::{self, Data, ServiceConfig};
+use infrastructure::clients::store::{postgres::Postgres};
+use std::sync::Arc;
+
+pub(crate) fn route(pg: Arc<Postgres>, cfg: &mut ServiceConfig) {
+    let pools = Data::new(Pools { pg });
+    cfg.app_data(pools);
+}
+
+async fn health_check(pools: web::Data<Pools>) -> impl actix_web::Responder {
+    let pg_state = pools.pg.health_check();
+    HttpResponseBuilder::new(StatusCode::OK).json(HealthCheck {
+        pg_connections: pg_state.connections,
+    })
+}
+
+struct Pools {
+    pub pg: Arc<Postgres>,
+}
+
+struct HealthCheck {
+    pg_connections: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Serde Error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+"#
+        );
+        assert_eq!(prune("use std::sync::Arc;\n", &removed).unwrap(), None);
+    }
+}
@@ -0,0 +1,532 @@
+//! Boilerplate for a new entity in the store. Writes the repository model and trait, the Postgres
+//! adapter implementing it and the migration creating its table, following the user and session ones.
+use super::edit::{range, Edits};
+use crate::{error::AlxError, INDENT};
+use std::fmt::Write;
+
+/// Column names Postgres needs quoted
+const RESERVED: [&str; 16] = [
+    "all",
+    "check",
+    "column",
+    "default",
+    "end",
+    "from",
+    "group",
+    "limit",
+    "offset",
+    "order",
+    "password",
+    "references",
+    "role",
+    "select",
+    "table",
+    "user",
+];
+
+/// Columns every table gets
+const AUTOMATIC: [&str; 3] = ["id", "created_at", "updated_at"];
+
+/// A repository entity, i.e. `post` with the `posts` table
+pub struct Entity {
+    /// The repository module, `post`
+    pub name: String,
+    /// The model, `Post`
+    pub model: String,
+    /// The table, `posts`
+    pub table: String,
+    pub columns: Vec<Column>,
+}
+
+/// A column given on the command line, mapped to its Rust, Diesel and SQL types
+pub struct Column {
+    pub name: String,
+    /// The model field type, `Option<String>`
+    pub rust: String,
+    /// The type the repository takes it by, `Option<&str>`
+    pub param: String,
+    /// The type of the insertable field, `Option<&'a str>`
+    pub insert: String,
+    /// The Diesel SQL type, `Nullable<Varchar>`
+    pub diesel: String,
+    /// The column definition, `VARCHAR(255)` followed by `NOT NULL` for required ones
+    pub sql: String,
+}
+
+impl Column {
+    /// Returns `None` if the type can't be mapped to a Postgres column
+    pub fn new(name: &str, ty: &str) -> Option<Self> {
+        let ty = ty.replace(' ', "");
+        let (inner, nullable) = match ty.strip_prefix("Option<").and_then(|t| t.strip_suffix('>')) {
+            Some(inner) => (inner, true),
+            None => (ty.as_str(), false),
+        };
+        let (rust, param, insert, diesel, sql) = match inner {
+            "String" => ("String", "&str", "&'a str", "Varchar", "VARCHAR(255)"),
+            "bool" => ("bool", "bool", "bool", "Bool", "BOOLEAN"),
+            "i16" => ("i16", "i16", "i16", "Int2", "SMALLINT"),
+            "i32" => ("i32", "i32", "i32", "Int4", "INTEGER"),
+            "i64" => ("i64", "i64", "i64", "Int8", "BIGINT"),
+            "f32" => ("f32", "f32", "f32", "Float4", "REAL"),
+            "f64" => ("f64", "f64", "f64", "Float8", "DOUBLE PRECISION"),
+            "NaiveDateTime" => (
+                "NaiveDateTime",
+                "NaiveDateTime",
+                "NaiveDateTime",
+                "Timestamptz",
+                "TIMESTAMPTZ",
+            ),
+            "NaiveDate" => ("NaiveDate", "NaiveDate", "NaiveDate", "Date", "DATE"),
+            "Value" | "serde_json::Value" => (
+                "serde_json::Value",
+                "&serde_json::Value",
+                "&'a serde_json::Value",
+                "Jsonb",
+                "JSONB",
+            ),
+            "Vec<u8>" => ("Vec<u8>", "&[u8]", "&'a [u8]", "Binary", "BYTEA"),
+            _ => return None,
+        };
+        let wrap = |t: &str| match nullable {
+            true => format!("Option<{t}>"),
+            false => t.to_string(),
+        };
+        Some(Self {
+            name: name.to_string(),
+            rust: wrap(rust),
+            param: wrap(param),
+            insert: wrap(insert),
+            diesel: match nullable {
+                true => format!("Nullable<{diesel}>"),
+                false => diesel.to_string(),
+            },
+            sql: match nullable {
+                true => sql.to_string(),
+                false => format!("{sql} NOT NULL"),
+            },
+        })
+    }
+
+    /// Columns every table gets can't be given
+    pub fn is_automatic(name: &str) -> bool {
+        AUTOMATIC.contains(&name)
+    }
+
+    fn is_borrowed(&self) -> bool {
+        self.insert.contains("'a")
+    }
+
+    fn sql_name(&self) -> String {
+        match RESERVED.contains(&self.name.as_str()) {
+            true => format!("\"{}\"", self.name),
+            false => self.name.clone(),
+        }
+    }
+}
+
+impl Entity {
+    /// The parameter the adapter takes the column by, prefixed so it doesn't clash with the Diesel DSL
+    fn arg(&self, column: &str) -> String {
+        format!("{}_{column}", self.name)
+    }
+}
+
+/// Naive english plural of the entity name for its table
+pub fn plural(name: &str) -> String {
+    if name.ends_with('s') || name.ends_with('x') || name.ends_with("ch") || name.ends_with("sh") {
+        format!("{name}es")
+    } else if name.ends_with('y') && !name.ends_with("ay") && !name.ends_with("ey") {
+        format!("{}ies", &name[..name.len() - 1])
+    } else {
+        format!("{name}s")
+    }
+}
+
+/// The repository module with the model and the repository trait
+pub fn repository(buf: &mut String, entity: &Entity) {
+    let Entity { name, model, .. } = entity;
+    let mut chrono = chrono_types(entity);
+    if !chrono.contains(&"NaiveDateTime") {
+        chrono.push("NaiveDateTime");
+        chrono.sort();
+    }
+    writeln!(buf, "use super::RepositoryError;").unwrap();
+    writeln!(buf, "use async_trait::async_trait;").unwrap();
+    write_chrono_use(buf, &chrono);
+    writeln!(buf, "use diesel::prelude::*;").unwrap();
+    writeln!(buf, "use serde::{{Deserialize, Serialize}};").unwrap();
+    writeln!(buf, "use std::error::Error;\n").unwrap();
+
+    writeln!(buf, "/// The repository {name} model").unwrap();
+    writeln!(
+        buf,
+        "#[derive(Debug, Clone, Deserialize, Serialize, Queryable)]\npub struct {model} {{"
+    )
+    .unwrap();
+    writeln!(buf, "{INDENT}pub id: String,").unwrap();
+    for c in entity.columns.iter() {
+        writeln!(buf, "{INDENT}pub {}: {},", c.name, c.rust).unwrap();
+    }
+    writeln!(buf, "{INDENT}pub created_at: NaiveDateTime,").unwrap();
+    writeln!(buf, "{INDENT}pub updated_at: NaiveDateTime,\n}}\n").unwrap();
+
+    let ret = format!("Result<{model}, Self::Error>");
+    let fields = entity
+        .columns
+        .iter()
+        .map(|c| format!("{}: {}", c.name, c.param))
+        .collect::<Vec<_>>();
+    let mut with_id = vec!["id: &str".to_string()];
+    with_id.extend(fields.iter().cloned());
+
+    writeln!(buf, "#[async_trait]\npub trait {model}Repository {{").unwrap();
+    writeln!(buf, "{INDENT}type Error: Error + Into<RepositoryError>;\n").unwrap();
+    writeln!(buf, "{INDENT}/// Create a {name}").unwrap();
+    write_fn(buf, "create", &fields, &ret, ";");
+    writeln!(buf, "\n{INDENT}/// Get a {name} by its ID").unwrap();
+    write_fn(buf, "get_by_id", &["id: &str".to_string()], &ret, ";");
+    writeln!(buf, "\n{INDENT}/// Update all of the {name}'s fields").unwrap();
+    write_fn(buf, "update", &with_id, &ret, ";");
+    writeln!(buf, "\n{INDENT}/// Delete a {name}").unwrap();
+    write_fn(buf, "delete", &["id: &str".to_string()], &ret, ";");
+    writeln!(buf, "}}").unwrap();
+}
+
+/// The Postgres adapter implementing the repository
+pub fn adapter(buf: &mut String, entity: &Entity) {
+    let Entity {
+        name, model, table, ..
+    } = entity;
+    writeln!(buf, "use super::{{schema::{table}, PgAdapterError}};").unwrap();
+    writeln!(
+        buf,
+        "use crate::{{\n{INDENT}clients::store::postgres::Postgres,\n{INDENT}store::repository::{name}::{{{model}, {model}Repository}},\n}};"
+    )
+    .unwrap();
+    writeln!(buf, "use async_trait::async_trait;").unwrap();
+    write_chrono_use(buf, &chrono_types(entity));
+    writeln!(
+        buf,
+        "use diesel::{{ExpressionMethods, Insertable, QueryDsl, RunQueryDsl}};"
+    )
+    .unwrap();
+    writeln!(buf, "use serde::Serialize;\nuse std::sync::Arc;\n").unwrap();
+
+    // Insertable
+    let lifetime = match entity.columns.iter().any(Column::is_borrowed) {
+        true => "<'a>",
+        false => "",
+    };
+    writeln!(
+        buf,
+        "#[derive(Debug, Serialize, Insertable)]\n#[diesel(table_name = {table})]\nstruct New{model}{lifetime} {{"
+    )
+    .unwrap();
+    for c in entity.columns.iter() {
+        writeln!(buf, "{INDENT}{}: {},", c.name, c.insert).unwrap();
+    }
+    writeln!(buf, "}}\n").unwrap();
+
+    writeln!(
+        buf,
+        "#[derive(Debug, Clone)]\npub struct Pg{model}Adapter {{\n{INDENT}pub client: Arc<Postgres>,\n}}\n"
+    )
+    .unwrap();
+
+    let ret = format!("Result<{model}, Self::Error>");
+    let id = format!("{}: &str", entity.arg("id"));
+    let args = entity
+        .columns
+        .iter()
+        .map(|c| format!("{}: {}", entity.arg(&c.name), c.param))
+        .collect::<Vec<_>>();
+    let mut with_id = vec![id.clone()];
+    with_id.extend(args.iter().cloned());
+    let connect = "(&mut self.client.connect()?)";
+    let dsl = format!("{INDENT}{INDENT}use super::schema::{table}::dsl::*;");
+    let i3 = INDENT.repeat(3);
+    let i4 = INDENT.repeat(4);
+    let pop = format!(
+        "{i3}.map_err(Self::Error::new)?\n{i3}.pop()\n{i3}.ok_or_else(|| PgAdapterError::DoesNotExist(\"{model}\".to_string()))"
+    );
+
+    writeln!(
+        buf,
+        "#[async_trait]\nimpl {model}Repository for Pg{model}Adapter {{"
+    )
+    .unwrap();
+    writeln!(buf, "{INDENT}type Error = PgAdapterError;\n").unwrap();
+
+    writeln!(buf, "{INDENT}/// Inserts a new {name}").unwrap();
+    write_fn(buf, "create", &args, &ret, " {");
+    writeln!(buf, "{dsl}").unwrap();
+    writeln!(
+        buf,
+        "{INDENT}{INDENT}diesel::insert_into({table})\n{i3}.values(New{model} {{"
+    )
+    .unwrap();
+    for c in entity.columns.iter() {
+        writeln!(buf, "{i4}{}: {},", c.name, entity.arg(&c.name)).unwrap();
+    }
+    writeln!(
+        buf,
+        "{i3}}})\n{i3}.get_result::<{model}>{connect}\n{i3}.map_err(Self::Error::new)\n{INDENT}}}\n"
+    )
+    .unwrap();
+
+    writeln!(buf, "{INDENT}/// Fetches a {name} by its ID").unwrap();
+    write_fn(buf, "get_by_id", std::slice::from_ref(&id), &ret, " {");
+    writeln!(buf, "{dsl}").unwrap();
+    writeln!(
+        buf,
+        "{INDENT}{INDENT}{table}\n{i3}.filter(id.eq({}))\n{i3}.first::<{model}>{connect}\n{i3}.map_err(Self::Error::new)\n{INDENT}}}\n",
+        entity.arg("id")
+    )
+    .unwrap();
+
+    writeln!(buf, "{INDENT}/// Updates all of the {name}'s fields").unwrap();
+    write_fn(buf, "update", &with_id, &ret, " {");
+    writeln!(buf, "{dsl}").unwrap();
+    let sets = entity
+        .columns
+        .iter()
+        .map(|c| format!("{}.eq({})", c.name, entity.arg(&c.name)))
+        .collect::<Vec<_>>();
+    let set = match sets.as_slice() {
+        [one] => format!("{i3}.set({one})"),
+        _ if sets.join(", ").len() + i3.len() + 8 <= 100 => {
+            format!("{i3}.set(({}))", sets.join(", "))
+        }
+        _ => format!(
+            "{i3}.set((\n{}\n{i3}))",
+            sets.iter()
+                .map(|s| format!("{i4}{s},"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    };
+    writeln!(
+        buf,
+        "{INDENT}{INDENT}diesel::update({table}.filter(id.eq({})))\n{set}\n{i3}.load::<{model}>{connect}\n{pop}\n{INDENT}}}\n",
+        entity.arg("id")
+    )
+    .unwrap();
+
+    writeln!(buf, "{INDENT}/// Deletes a {name}").unwrap();
+    write_fn(buf, "delete", &[id], &ret, " {");
+    writeln!(buf, "{dsl}").unwrap();
+    writeln!(
+        buf,
+        "{INDENT}{INDENT}diesel::delete({table}.filter(id.eq({})))\n{i3}.load::<{model}>{connect}\n{pop}\n{INDENT}}}\n}}",
+        entity.arg("id")
+    )
+    .unwrap();
+}
+
+/// The migration creating the table, with the `updated_at` trigger and the timestamp indexes
+pub fn migration_up(buf: &mut String, entity: &Entity) {
+    let table = &entity.table;
+    writeln!(buf, "CREATE TABLE {table}(").unwrap();
+    writeln!(
+        buf,
+        "  id VARCHAR(36) UNIQUE DEFAULT uuid_generate_v4() NOT NULL,"
+    )
+    .unwrap();
+    for c in entity.columns.iter() {
+        writeln!(buf, "  {} {},", c.sql_name(), c.sql).unwrap();
+    }
+    writeln!(buf, "  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),").unwrap();
+    writeln!(buf, "  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),").unwrap();
+    writeln!(buf, "  CONSTRAINT pk_{table} PRIMARY KEY (id)\n);").unwrap();
+    writeln!(
+        buf,
+        "-- Diesel helper to automatically adjust the 'updated_at' field\nSELECT diesel_manage_updated_at('{table}');"
+    )
+    .unwrap();
+    for ts in ["created_at", "updated_at"] {
+        writeln!(
+            buf,
+            "CREATE INDEX IF NOT EXISTS {table}_btree_{ts} ON {table} USING BTREE(\"{ts}\");"
+        )
+        .unwrap();
+    }
+}
+
+pub fn migration_down(buf: &mut String, entity: &Entity) {
+    writeln!(buf, "DROP TABLE {};", entity.table).unwrap();
+}
+
+/// Adds the table to schema.rs, before the joins, and allows it to appear in queries with the others.
+/// Returns `None` if the table is already there.
+pub fn update_schema(src: &str, entity: &Entity) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let macros = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Macro(m) => m.mac.path.segments.last().map(|s| (s.ident.to_string(), m)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let tables = macros
+        .iter()
+        .filter(|(name, _)| name == "table")
+        .filter_map(|(_, m)| {
+            m.mac.tokens.clone().into_iter().find_map(|t| match t {
+                proc_macro2::TokenTree::Ident(i) => Some(i.to_string()),
+                _ => None,
+            })
+        })
+        .collect::<Vec<_>>();
+    if tables.contains(&entity.table) {
+        return Ok(None);
+    }
+
+    let mut table = format!("diesel::table! {{\n{INDENT}{} (id) {{\n", entity.table);
+    let i2 = INDENT.repeat(2);
+    writeln!(table, "{i2}id -> Varchar,").unwrap();
+    for c in entity.columns.iter() {
+        writeln!(table, "{i2}{} -> {},", c.name, c.diesel).unwrap();
+    }
+    writeln!(table, "{i2}created_at -> Timestamptz,").unwrap();
+    writeln!(table, "{i2}updated_at -> Timestamptz,\n{INDENT}}}\n}}").unwrap();
+
+    let mut edits = Edits::default();
+    match macros.iter().find(|(name, _)| name != "table") {
+        Some((_, m)) => {
+            let (start, _) = range(src, m.mac.path.segments[0].ident.span());
+            edits.insert(start, format!("{table}\n"));
+        }
+        None => {
+            let sep = if src.trim().is_empty() { "" } else { "\n" };
+            edits.insert(src.len(), format!("{sep}{table}"));
+        }
+    }
+
+    match macros
+        .iter()
+        .find(|(name, _)| name == "allow_tables_to_appear_in_same_query")
+    {
+        Some((_, m)) => {
+            let syn::MacroDelimiter::Paren(ref paren) = m.mac.delimiter else {
+                return Err(AlxError::Edit(
+                    "unexpected allow_tables_to_appear_in_same_query delimiter".to_string(),
+                ));
+            };
+            let (open, end) = range(src, paren.span);
+            edits.push_to_list(src, open + 1, end - 1, &entity.table);
+        }
+        None if !tables.is_empty() => {
+            let mut all = tables;
+            all.push(entity.table.clone());
+            all.sort();
+            let list = all
+                .iter()
+                .map(|t| format!("{INDENT}{t},\n"))
+                .collect::<String>();
+            edits.insert(
+                src.len(),
+                format!("\ndiesel::allow_tables_to_appear_in_same_query!(\n{list});\n"),
+            );
+        }
+        None => {}
+    }
+    Ok(Some(edits.apply(src)))
+}
+
+/// The chrono types the columns use
+fn chrono_types(entity: &Entity) -> Vec<&'static str> {
+    let mut types = vec![];
+    for c in entity.columns.iter() {
+        let ty = if c.rust.contains("NaiveDateTime") {
+            "NaiveDateTime"
+        } else if c.rust.contains("NaiveDate") {
+            "NaiveDate"
+        } else {
+            continue;
+        };
+        if !types.contains(&ty) {
+            types.push(ty);
+        }
+    }
+    types.sort();
+    types
+}
+
+fn write_chrono_use(buf: &mut String, types: &[&str]) {
+    match types {
+        [] => {}
+        [ty] => writeln!(buf, "use chrono::{ty};").unwrap(),
+        _ => writeln!(buf, "use chrono::{{{}}};", types.join(", ")).unwrap(),
+    }
+}
+
+/// Writes an async fn signature on one line if it fits, otherwise with a parameter per line like
+/// rustfmt would
+fn write_fn(buf: &mut String, name: &str, params: &[String], ret: &str, end: &str) {
+    let line = format!(
+        "{INDENT}async fn {name}(&self, {}) -> {ret}{end}",
+        params.join(", ")
+    );
+    if line.len() <= 100 {
+        writeln!(buf, "{line}").unwrap();
+        return;
+    }
+    writeln!(buf, "{INDENT}async fn {name}(\n{INDENT}{INDENT}&self,").unwrap();
+    for p in params {
+        writeln!(buf, "{INDENT}{INDENT}{p},").unwrap();
+    }
+    writeln!(buf, "{INDENT}) -> {ret}{end}").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity() -> Entity {
+        Entity {
+            name: "post".to_string(),
+            model: "Post".to_string(),
+            table: plural("post"),
+            columns: vec![
+                Column::new("title", "String").unwrap(),
+                Column::new("order", "Option<i32>").unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn maps_columns() {
+        let entity = entity();
+        assert_eq!(entity.table, "posts");
+        assert_eq!(plural("category"), "categories");
+        assert!(Column::new("count", "u32").is_none());
+
+        let mut up = String::new();
+        migration_up(&mut up, &entity);
+        assert!(up.contains("  title VARCHAR(255) NOT NULL,\n  \"order\" INTEGER,\n"));
+        assert!(up.contains("SELECT diesel_manage_updated_at('posts');"));
+
+        let mut adapter_rs = String::new();
+        adapter(&mut adapter_rs, &entity);
+        assert!(adapter_rs
+            .contains("struct NewPost<'a> {\n    title: &'a str,\n    order: Option<i32>,\n}"));
+        assert!(adapter_rs.contains(".set((title.eq(post_title), order.eq(post_order)))"));
+    }
+
+    #[test]
+    fn adds_table_to_schema() {
+        let src = "// @generated automatically by Diesel CLI.\n\ndiesel::table! {\n    users (id) {\n        id -> Varchar,\n    }\n}\n";
+        let out = update_schema(src, &entity()).unwrap().unwrap();
+        assert!(out.contains("}\n\ndiesel::table! {\n    posts (id) {\n        id -> Varchar,\n        title -> Varchar,\n        order -> Nullable<Int4>,\n"));
+        assert!(out.ends_with(
+            "diesel::allow_tables_to_appear_in_same_query!(\n    posts,\n    users,\n);\n"
+        ));
+        assert!(update_schema(&out, &entity()).unwrap().is_none());
+
+        let src = "diesel::table! {\n    users (id) {}\n}\n\ndiesel::allow_tables_to_appear_in_same_query!(\n    sessions,\n    users,\n);\n";
+        let out = update_schema(src, &entity()).unwrap().unwrap();
+        assert!(out.contains("diesel::table! {\n    posts (id) {"));
+        assert!(out.ends_with("    users,\n    posts,\n);\n"));
+    }
+}
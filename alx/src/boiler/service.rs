@@ -0,0 +1,139 @@
+//! Adds methods to the service of an existing route. The `ServiceContract` in contract.rs gets the
+//! signature and the service in domain.rs an implementation to fill in.
+use super::{
+    contract::{is_impl_of, service_name},
+    edit::{range, Edits},
+};
+use crate::{error::AlxError, INDENT};
+
+/// A `ServiceContract` method taking the handler's request data
+pub struct ServiceMethod {
+    /// The method name, same as the handler's
+    pub name: String,
+    /// The data struct passed from the handler, if any
+    pub input: Option<String>,
+}
+
+impl ServiceMethod {
+    fn signature(&self) -> String {
+        match self.input {
+            Some(ref data) => format!(
+                "async fn {}(&self, data: {data}) -> Result<HttpResponse, Error>",
+                self.name
+            ),
+            None => format!(
+                "async fn {}(&self) -> Result<HttpResponse, Error>",
+                self.name
+            ),
+        }
+    }
+
+    /// Imports both the signature and the implementation need
+    fn add_uses(&self, edits: &mut Edits, src: &str, items: &[syn::Item]) {
+        if let Some(ref data) = self.input {
+            edits.add_use(src, items, 0, &["super", "data"], data);
+        }
+        edits.add_use(src, items, 0, &["crate", "error"], "Error");
+        edits.add_use(src, items, 0, &["actix_web"], "HttpResponse");
+    }
+}
+
+/// Adds the method signature to the `ServiceContract` trait in contract.rs
+pub fn contract_method(src: &str, method: &ServiceMethod) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let Some(service) = file.items.iter().find_map(|item| match item {
+        syn::Item::Trait(t) if t.ident == "ServiceContract" => Some(t),
+        _ => None,
+    }) else {
+        return Err(AlxError::Edit(
+            "no ServiceContract found in contract.rs".to_string(),
+        ));
+    };
+    let exists = service
+        .items
+        .iter()
+        .any(|item| matches!(item, syn::TraitItem::Method(m) if m.sig.ident == method.name));
+    if exists {
+        return Ok(None);
+    }
+
+    let mut edits = Edits::default();
+    method.add_uses(&mut edits, src, &file.items);
+    let (open, end) = range(src, service.brace_token.span);
+    edits.push_to_block(src, open, end - 1, &format!("{};", method.signature()));
+    Ok(Some(edits.apply(src)))
+}
+
+/// Adds an implementation of the method to the service's `ServiceContract` impl in domain.rs
+pub fn domain_method(src: &str, method: &ServiceMethod) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let Some(imp) = file.items.iter().find_map(|item| match item {
+        syn::Item::Impl(i) if is_impl_of(i, "ServiceContract") => Some(i),
+        _ => None,
+    }) else {
+        return Err(AlxError::Edit(
+            "no ServiceContract implementation found in domain.rs".to_string(),
+        ));
+    };
+    let exists = imp
+        .items
+        .iter()
+        .any(|item| matches!(item, syn::ImplItem::Method(m) if m.sig.ident == method.name));
+    if exists {
+        return Ok(None);
+    }
+
+    let mut edits = Edits::default();
+    method.add_uses(&mut edits, src, &file.items);
+    let (open, end) = range(src, imp.brace_token.span);
+    edits.push_to_block(
+        src,
+        open,
+        end - 1,
+        &format!("{} {{\n{INDENT}todo!()\n}}", method.signature()),
+    );
+    Ok(Some(edits.apply(src)))
+}
+
+/// The names of the service's fields, i.e. the contracts it's constructed with
+pub fn service_fields(file: &syn::File) -> Vec<String> {
+    let Some(service) = service_name(file) else {
+        return vec![];
+    };
+    file.items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Struct(s) if s.ident == service => Some(
+                s.fields
+                    .iter()
+                    .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_service_method() {
+        let method = ServiceMethod {
+            name: "create".to_string(),
+            input: Some("CreateThing".to_string()),
+        };
+
+        let contract = "use async_trait::async_trait;\n\n#[cfg_attr(test, mockall::automock)]\n#[async_trait]\npub(super) trait ServiceContract {}\n";
+        let out = contract_method(contract, &method).unwrap().unwrap();
+        assert!(out.contains("use super::data::CreateThing;\nuse crate::error::Error;\nuse actix_web::HttpResponse;\n"));
+        assert!(out.contains("pub(super) trait ServiceContract {\n    async fn create(&self, data: CreateThing) -> Result<HttpResponse, Error>;\n}"));
+        assert!(contract_method(&out, &method).unwrap().is_none());
+
+        let domain = "use super::contract::ServiceContract;\n\n#[async_trait]\nimpl ServiceContract for Things {\n    async fn get(&self) -> Result<HttpResponse, Error> {\n        todo!()\n    }\n}\n";
+        let out = domain_method(domain, &method).unwrap().unwrap();
+        assert!(out.contains("        todo!()\n    }\n\n    async fn create(&self, data: CreateThing) -> Result<HttpResponse, Error> {\n        todo!()\n    }\n}\n"));
+        assert!(domain_method(&out, &method).unwrap().is_none());
+    }
+}
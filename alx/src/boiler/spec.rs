@@ -0,0 +1,728 @@
+//! Scaffolding of route files from a spec, i.e. a hand-written `alx.yaml` in the lock file format.
+//! Every function here only adds what is missing from the existing source so running it repeatedly
+//! leaves the files as they are.
+use super::edit::{closing_brace, line_indent, offset, range, Edits};
+use crate::{
+    analyzer::{
+        diagnostic::Diagnostics,
+        scanners::{config_param, scan_setup},
+        util::{call_name, method_chain, str_arg},
+    },
+    config::{Data, Extractor, RouteHandler, ANY_METHOD},
+    error::AlxError,
+    print, uppercase, INDENT,
+};
+use colored::Colorize;
+use std::{collections::HashSet, fmt::Write, path::Path};
+use syn::{spanned::Spanned, visit::Visit};
+
+/// The data the routes take, each struct once even if several routes share it
+pub fn route_inputs<'a>(routes: &[&'a RouteHandler]) -> Vec<&'a Data> {
+    let mut seen = HashSet::new();
+    routes
+        .iter()
+        .filter_map(|r| r.input.as_ref())
+        .filter(|d| seen.insert(d.id.as_str()))
+        .collect()
+}
+
+/// Adds the missing data structs to data.rs. Returns `None` if nothing changed.
+pub fn update_data(src: &str, data: &[&Data]) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let existing = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Struct(s) => Some(s.ident.to_string()),
+            syn::Item::Enum(e) => Some(e.ident.to_string()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    let mut structs = String::new();
+    for d in data.iter().filter(|d| !existing.contains(&d.id)) {
+        print(&format!("{} Adding data {}", "\u{270E}".blue(), d.id));
+        data_struct(&mut structs, d);
+    }
+    if structs.is_empty() {
+        return Ok(None);
+    }
+
+    let needed = [("serde", "Deserialize"), ("validator", "Validate")];
+    Ok(Some(splice(src, &structs, &needed)?))
+}
+
+/// Adds the missing handler functions to handler.rs. `known` is all the data the spec declares,
+/// payloads of those types get validated. If `delegate` is set handlers bound by the `ServiceContract`
+/// call its method of the same name with the validated data instead of being left to `todo!()`.
+/// Returns `None` if nothing changed.
+pub fn update_handlers(
+    src: &str,
+    routes: &[&RouteHandler],
+    known: &[&Data],
+    delegate: bool,
+) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let mut existing = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Fn(f) => Some(f.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    let mut fns = String::new();
+    let mut needed = vec![("actix_web", "web"), ("crate::error", "Error")];
+    let mut data_uses = vec![];
+    for route in routes.iter() {
+        let Some(ref handler) = route.handler else {
+            continue;
+        };
+        // Multiple routes can point to the same handler
+        if !existing.insert(handler.name.clone()) {
+            continue;
+        }
+        print(&format!(
+            "{} Adding handler {}",
+            "\u{270E}".blue(),
+            handler.name
+        ));
+
+        let mut params = vec![];
+        let mut validate = vec![];
+        let payloads = handler
+            .inputs
+            .iter()
+            .filter(|i| is_payload(&i.ext_type))
+            .count();
+        for input in handler.inputs.iter() {
+            let ext = input.ext_type.as_str();
+            let ty = input.data_type.as_str();
+            let param = match Extractor::from(input.ext_type.clone()) {
+                Extractor::Data => format!("service: web::Data<{ty}>"),
+                Extractor::Request => {
+                    needed.push(("actix_web", "HttpRequest"));
+                    "req: HttpRequest".to_string()
+                }
+                _ if is_payload(ext) => {
+                    let name = if payloads == 1 {
+                        "data".to_string()
+                    } else {
+                        ext.to_lowercase()
+                    };
+                    if known.iter().any(|d| d.id == ty) {
+                        data_uses.push(ty.to_string());
+                        validate.push(name.clone());
+                    }
+                    format!("{name}: web::{ext}<{ty}>")
+                }
+                _ if ty.is_empty() => format!("{}: {ext}", ext.to_lowercase()),
+                _ => format!("{}: web::{ext}<{ty}>", ext.to_lowercase()),
+            };
+            params.push(param);
+        }
+        if !validate.is_empty() {
+            needed.push(("validator", "Validate"));
+        }
+        if let Some(ref bound) = handler.bound {
+            if bound.contains("ServiceContract") {
+                needed.push(("super::contract", "ServiceContract"));
+            }
+        }
+
+        write!(fns, "\npub(super) async fn {}", handler.name).unwrap();
+        if let Some(ref bound) = handler.bound {
+            write!(fns, "<{bound}>").unwrap();
+        }
+        writeln!(fns, "(").unwrap();
+        for param in params {
+            writeln!(fns, "{INDENT}{param},").unwrap();
+        }
+        // `todo!()` doesn't tell what `impl Responder` is, so stubs name the type
+        let delegates = delegate && handler.bound.is_some();
+        match delegates {
+            true => {
+                needed.push(("actix_web", "Responder"));
+                writeln!(fns, ") -> Result<impl Responder, Error> {{").unwrap();
+            }
+            false => {
+                needed.push(("actix_web", "HttpResponse"));
+                writeln!(fns, ") -> Result<HttpResponse, Error> {{").unwrap();
+            }
+        }
+        // The inner value of `web::Path` is private
+        for name in validate.iter() {
+            writeln!(fns, "{INDENT}let {name} = {name}.into_inner();").unwrap();
+            writeln!(fns, "{INDENT}{name}.validate().map_err(Error::new)?;").unwrap();
+        }
+        if delegates {
+            writeln!(
+                fns,
+                "{INDENT}service.{}({}).await\n}}",
+                handler.name,
+                validate.join(", ")
+            )
+            .unwrap();
+        } else {
+            writeln!(fns, "{INDENT}todo!()\n}}").unwrap();
+        }
+    }
+    if fns.is_empty() {
+        return Ok(None);
+    }
+
+    data_uses.sort();
+    data_uses.dedup();
+    needed.extend(data_uses.iter().map(|d| ("super::data", d.as_str())));
+    Ok(Some(splice(src, &fns, &needed)?))
+}
+
+/// Adds registrations for the routes setup.rs doesn't register yet. Routes on the same path share a
+/// resource, so they have to be wrapped in the same middleware, which has to be bound in the routes
+/// function. If any of the new routes need the service and it isn't added to the app data yet, it is
+/// constructed from the contracts. Returns `None` if nothing changed.
+pub fn update_setup(
+    src: &str,
+    service_name: &str,
+    contracts: &[String],
+    routes: &[&RouteHandler],
+) -> Result<Option<String>, AlxError> {
+    let file = syn::parse_file(src)?;
+    let Some(routes_fn) = file.items.iter().find_map(|item| match item {
+        syn::Item::Fn(f) => Some(f),
+        _ => None,
+    }) else {
+        println!(
+            "{}",
+            "No routes function found in setup.rs, skipping".yellow()
+        );
+        return Ok(None);
+    };
+    let cfg = config_param(routes_fn);
+
+    // Only the registered methods and paths matter here, unresolved handlers don't
+    let mut diagnostics = Diagnostics::new(Path::new("setup.rs"), src);
+    let registered = scan_setup(vec![routes_fn.clone()], &mut diagnostics);
+    let missing = routes
+        .iter()
+        .filter(|r| r.handler.is_some())
+        .filter(|r| {
+            !registered
+                .iter()
+                .any(|e| e.method == r.method && e.path == r.path)
+        })
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(None);
+    }
+
+    // Group the routes in to resources, actix only matches the first resource of a path
+    let mut resources = Vec::<(&str, &[String], Vec<&RouteHandler>)>::new();
+    for route in missing.iter() {
+        let middleware = route.middleware.as_deref().unwrap_or_default();
+        match resources.iter_mut().find(|(path, _, _)| *path == route.path) {
+            Some((path, mw, _)) if *mw != middleware => {
+                return Err(AlxError::Edit(format!(
+                    "the routes on {path} are wrapped in different middleware, put them in separate scopes"
+                )))
+            }
+            Some((_, _, rs)) => rs.push(route),
+            None => resources.push((&route.path, middleware, vec![route])),
+        }
+    }
+
+    let body = &routes_fn.block;
+    let locals = body
+        .stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            syn::Stmt::Local(syn::Local {
+                pat: syn::Pat::Ident(id),
+                ..
+            }) => Some(id.ident.to_string()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    for (_, middleware, _) in resources.iter() {
+        if let Some(mw) = middleware.iter().find(|mw| !locals.contains(*mw)) {
+            return Err(AlxError::Edit(format!(
+                "{mw} is not bound in the routes function of setup.rs, bind it before adding routes wrapped in it"
+            )));
+        }
+    }
+
+    let service_ty = existing_service_type(src).unwrap_or_else(|| {
+        if contracts.is_empty() {
+            service_name.to_string()
+        } else {
+            let generics = contracts.iter().map(|c| uppercase(c)).collect::<Vec<_>>();
+            format!("{service_name}<{}>", generics.join(", "))
+        }
+    });
+
+    let mut stmts = String::new();
+    let needs_service = missing
+        .iter()
+        .any(|r| r.handler.as_ref().is_some_and(|h| h.bound.is_some()));
+    if needs_service && !src.contains("app_data") {
+        print(&format!(
+            "{} Adding {service_name} to app data",
+            "\u{270E}".blue()
+        ));
+        if contracts.is_empty() {
+            writeln!(stmts, "{INDENT}let service = {service_name};").unwrap();
+        } else {
+            writeln!(stmts, "{INDENT}let service = {service_name} {{").unwrap();
+            for c in contracts {
+                writeln!(stmts, "{INDENT}{INDENT}{c}: {} {{}},", uppercase(c)).unwrap();
+            }
+            writeln!(stmts, "{INDENT}}};").unwrap();
+        }
+        writeln!(stmts, "{INDENT}{cfg}.app_data(web::Data::new(service));").unwrap();
+    }
+
+    let route_call = |route: &RouteHandler| {
+        let handler = route.handler.as_ref().unwrap();
+        let to = match handler.bound {
+            Some(_) => format!("handler::{}::<{service_ty}>", handler.name),
+            None => format!("handler::{}", handler.name),
+        };
+        if route.method == ANY_METHOD {
+            format!(".to({to})")
+        } else {
+            format!(".route(web::{}().to({to}))", route.method.to_lowercase())
+        }
+    };
+
+    let mut edits = Edits::default();
+    let mut wrapped = HashSet::new();
+    for (path, middleware, rs) in resources.iter() {
+        let existing = registered
+            .iter()
+            .filter(|e| e.path == *path)
+            .collect::<Vec<_>>();
+        if !existing.is_empty() {
+            // Routes can only go on the existing resource if it's wrapped in the same middleware
+            if existing
+                .iter()
+                .any(|e| e.middleware.as_deref().unwrap_or_default() != *middleware)
+            {
+                return Err(AlxError::Edit(format!(
+                    "{path} is already registered with different middleware, put the new routes in a separate scope"
+                )));
+            }
+            match resource_end(src, body, &cfg, path) {
+                Some((at, sep)) => {
+                    print(&format!(
+                        "{} Adding routes to the {path} resource",
+                        "\u{270E}".blue()
+                    ));
+                    for route in rs.iter() {
+                        edits.insert(at, format!("{sep}{}", route_call(route)));
+                    }
+                    continue;
+                }
+                _ => println!(
+                    "{} {path} {}",
+                    "Warning:".yellow(),
+                    "is already registered, the new resource will be shadowed by it. Move the routes to the existing resource.".yellow()
+                ),
+            }
+        }
+        print(&format!("{} Registering {path}", "\u{270E}".blue()));
+        writeln!(stmts, "{INDENT}{cfg}.service(").unwrap();
+        write!(stmts, "{INDENT}{INDENT}web::resource(\"{path}\")").unwrap();
+        for route in rs.iter() {
+            write!(stmts, "\n{INDENT}{INDENT}{INDENT}{}", route_call(route)).unwrap();
+        }
+        for mw in middleware.iter() {
+            // Middleware bindings are shared between resources, so none of them can move it
+            write!(stmts, "\n{INDENT}{INDENT}{INDENT}.wrap({mw}.clone())").unwrap();
+            wrapped.insert(mw.as_str());
+        }
+        writeln!(stmts, ",\n{INDENT});").unwrap();
+    }
+
+    for (mw, (start, end)) in moved_middleware(src, body) {
+        if wrapped.contains(mw.as_str()) {
+            edits.replace(start, end, format!("{mw}.clone()"));
+        }
+    }
+
+    if !stmts.is_empty() {
+        let close = closing_brace(src, body.brace_token.span);
+        edits.insert(close, format!("\n{stmts}"));
+    }
+    Ok(Some(edits.apply(src)))
+}
+
+/// The bindings moved in to `.wrap(binding)` calls, with the range of the argument
+fn moved_middleware(src: &str, body: &syn::Block) -> Vec<(String, (usize, usize))> {
+    struct Wraps<'a> {
+        src: &'a str,
+        found: Vec<(String, (usize, usize))>,
+    }
+
+    impl<'ast> Visit<'ast> for Wraps<'_> {
+        fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+            if call.method == "wrap" && call.args.len() == 1 {
+                if let Some(syn::Expr::Path(p)) = call.args.first() {
+                    if let Some(ident) = p.path.get_ident() {
+                        self.found
+                            .push((ident.to_string(), range(self.src, p.span())));
+                    }
+                }
+            }
+            syn::visit::visit_expr_method_call(self, call);
+        }
+    }
+
+    let mut wraps = Wraps { src, found: vec![] };
+    wraps.visit_block(body);
+    wraps.found
+}
+
+/// Offset right after the last route of the `cfg.service(web::resource(path)..)` registering the path
+/// outside of any scope, along with what separates the calls in the chain
+fn resource_end(src: &str, body: &syn::Block, cfg: &str, path: &str) -> Option<(usize, String)> {
+    body.stmts.iter().find_map(|stmt| {
+        let syn::Stmt::Semi(syn::Expr::MethodCall(call), _) = stmt else {
+            return None;
+        };
+        let is_cfg = matches!(*call.receiver, syn::Expr::Path(ref p) if p.path.is_ident(cfg));
+        if !is_cfg || call.method != "service" {
+            return None;
+        }
+        let (base, calls) = method_chain(call.args.first()?);
+        let syn::Expr::Call(ref resource) = base else {
+            return None;
+        };
+        if call_name(base).as_deref() != Some("resource") || str_arg(&resource.args)? != path {
+            return None;
+        }
+        let base_start = offset(src, base.span().start());
+        let (indent, at) = match calls
+            .iter()
+            .rev()
+            .find(|c| c.method == "route" || c.method == "to")
+        {
+            Some(c) => (
+                line_indent(src, offset(src, c.dot_token.span.start())).to_string(),
+                offset(src, c.span().end()),
+            ),
+            None => (
+                format!("{}{INDENT}", line_indent(src, base_start)),
+                offset(src, base.span().end()),
+            ),
+        };
+        let chain_end = offset(src, call.args.first()?.span().end());
+        let sep = match src[base_start..chain_end].contains('\n') {
+            true => format!("\n{indent}"),
+            false => String::new(),
+        };
+        Some((at, sep))
+    })
+}
+
+fn data_struct(buf: &mut String, data: &Data) {
+    writeln!(
+        buf,
+        "\n#[derive(Debug, Deserialize, Validate)]\npub(super) struct {} {{",
+        data.id
+    )
+    .unwrap();
+    for field in data.fields.iter() {
+        let mut serde = vec![];
+        if let Some(ref rename) = field.rename {
+            serde.push(format!("rename = {rename:?}"));
+        }
+        if field.default {
+            serde.push("default".to_string());
+        }
+        if !serde.is_empty() {
+            writeln!(buf, "{INDENT}#[serde({})]", serde.join(", ")).unwrap();
+        }
+        for v in field.validation.iter() {
+            writeln!(buf, "{INDENT}#[validate({})]", tidy_tokens(v)).unwrap();
+        }
+        let ty = if field.required || field.default || field.ty.starts_with("Option") {
+            field.ty.clone()
+        } else {
+            format!("Option<{}>", field.ty)
+        };
+        writeln!(buf, "{INDENT}pub {}: {ty},", field.name).unwrap();
+    }
+    writeln!(buf, "}}").unwrap();
+}
+
+/// Extractors carrying request data
+fn is_payload(ext: &str) -> bool {
+    matches!(ext, "Json" | "Query" | "Path" | "Form")
+}
+
+/// Appends the items to the end of the file and imports the names from their modules if they aren't
+/// imported yet.
+/// The file is re-parsed after every import so names from the same module end up in one `use`.
+fn splice(src: &str, items: &str, imports: &[(&str, &str)]) -> Result<String, AlxError> {
+    let mut out = src.to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(items);
+    for (module, name) in imports.iter() {
+        let file = syn::parse_file(&out)?;
+        let module = module.split("::").collect::<Vec<_>>();
+        let mut edits = Edits::default();
+        edits.add_use(&out, &file.items, 0, &module, name);
+        out = edits.apply(&out);
+    }
+    if src.trim().is_empty() {
+        out = out.trim_start().to_string();
+    }
+    Ok(out)
+}
+
+/// The service type used by existing registrations, i.e. `UserService<Repository<PgUserAdapter>>` in
+/// `.to(handler::get::<UserService<Repository<PgUserAdapter>>>)`
+fn existing_service_type(src: &str) -> Option<String> {
+    let start = src.find(".to(handler::")?;
+    let rest = &src[start..];
+    let open = rest.find("::<")? + 3;
+    let mut depth = 1;
+    for (i, c) in rest[open..].char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    let ty = tidy_tokens(&rest[open..open + i]);
+                    return Some(ty.trim_end_matches(',').to_string());
+                }
+            }
+            ')' | ';' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Collapses the whitespace left over from stringified token streams and line breaks,
+/// e.g. `length (min = 1)` to `length(min = 1)`
+fn tidy_tokens(s: &str) -> String {
+    s.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" (", "(")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(" ,", ",")
+        .replace("< ", "<")
+        .replace(" <", "<")
+        .replace(" >", ">")
+        .replace(",>", ">")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Field, Handler, HandlerInput};
+
+    fn route() -> RouteHandler {
+        RouteHandler {
+            method: "POST".to_string(),
+            path: "/things".to_string(),
+            handler: Some(Handler {
+                name: "create".to_string(),
+                inputs: vec![
+                    HandlerInput {
+                        ext_type: "Json".to_string(),
+                        data_type: "CreateThing".to_string(),
+                    },
+                    HandlerInput {
+                        ext_type: "Data".to_string(),
+                        data_type: "T".to_string(),
+                    },
+                ],
+                bound: Some("T: ServiceContract".to_string()),
+                ..Default::default()
+            }),
+            middleware: Some(vec!["auth_guard".to_string()]),
+            guards: None,
+            service: None,
+            input: Some(Data {
+                id: "CreateThing".to_string(),
+                fields: vec![Field {
+                    name: "name".to_string(),
+                    ty: "String".to_string(),
+                    required: true,
+                    validation: vec!["length (min = 1)".to_string()],
+                    ..Default::default()
+                }],
+            }),
+        }
+    }
+
+    #[test]
+    fn scaffolds_idempotently() {
+        let route = route();
+        let data = route.input.as_ref().unwrap();
+
+        let data_rs = update_data("", &[data]).unwrap().unwrap();
+        assert!(data_rs.contains("#[validate(length(min = 1))]\n    pub name: String,"));
+        assert!(update_data(&data_rs, &[data]).unwrap().is_none());
+
+        let handler_rs = update_handlers("", &[&route], &[data], false)
+            .unwrap()
+            .unwrap();
+        assert!(handler_rs.starts_with(
+            "use super::contract::ServiceContract;\n\
+             use super::data::CreateThing;\n\
+             use crate::error::Error;\n\
+             use actix_web::{web, HttpResponse};\n\
+             use validator::Validate;\n\n"
+        ));
+        assert!(handler_rs.contains("pub(super) async fn create<T: ServiceContract>("));
+        assert!(handler_rs.contains(") -> Result<HttpResponse, Error> {"));
+        assert!(handler_rs.contains(
+            "    let data = data.into_inner();\n    data.validate().map_err(Error::new)?;\n"
+        ));
+        assert!(update_handlers(&handler_rs, &[&route], &[data], false)
+            .unwrap()
+            .is_none());
+
+        let setup = "use actix_web::web;\n\npub(crate) fn routes(cfg: &mut web::ServiceConfig) {\n    let auth_guard = AuthGuard::new(Role::User);\n}";
+        let contracts = ["repository".to_string()];
+        let setup_rs = update_setup(setup, "Things", &contracts, &[&route])
+            .unwrap()
+            .unwrap();
+        assert!(
+            setup_rs.contains("let service = Things {\n        repository: Repository {},\n    };")
+        );
+        assert!(setup_rs.contains(".route(web::post().to(handler::create::<Things<Repository>>))"));
+        assert!(setup_rs.contains(".wrap(auth_guard.clone()),"));
+        assert!(update_setup(&setup_rs, "Things", &contracts, &[&route])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn writes_shared_inputs_once() {
+        let shared = route();
+        let mut other = route();
+        other.input.as_mut().unwrap().id = "UpdateThing".to_string();
+        let data = route_inputs(&[&shared, &other, &shared]);
+        assert_eq!(
+            data.iter().map(|d| d.id.as_str()).collect::<Vec<_>>(),
+            ["CreateThing", "UpdateThing"]
+        );
+        let data_rs = update_data("", &data).unwrap().unwrap();
+        assert_eq!(data_rs.matches("struct CreateThing").count(), 1);
+    }
+
+    #[test]
+    fn writes_serde_names_and_defaults() {
+        let data = Data {
+            id: "GetThings".to_string(),
+            fields: vec![
+                Field {
+                    name: "per_page".to_string(),
+                    rename: Some("perPage".to_string()),
+                    ty: "Option<u16>".to_string(),
+                    ..Default::default()
+                },
+                Field {
+                    name: "query".to_string(),
+                    rename: Some("q".to_string()),
+                    ty: "String".to_string(),
+                    default: true,
+                    ..Default::default()
+                },
+            ],
+        };
+        let data_rs = update_data("", &[&data]).unwrap().unwrap();
+        assert!(data_rs.contains(
+            "    #[serde(rename = \"perPage\")]\n    pub per_page: Option<u16>,\n    \
+             #[serde(rename = \"q\", default)]\n    pub query: String,\n"
+        ));
+    }
+
+    #[test]
+    fn extends_existing_resource() {
+        let route = route();
+        let setup = r#"pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
+    let auth_guard = AuthGuard::new(Role::User);
+    cfg.app_data(web::Data::new(Things));
+    cfg.service(
+        web::resource("/things")
+            .route(web::get().to(handler::get::<Things<Repository>>))
+            .wrap(auth_guard),
+    );
+}
+"#;
+        let out = update_setup(setup, "Things", &[], &[&route])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            out,
+            setup.replace(
+                ">))\n",
+                ">))\n            .route(web::post().to(handler::create::<Things<Repository>>))\n"
+            )
+        );
+    }
+
+    #[test]
+    fn registers_a_resource_per_path_and_clones_guards() {
+        let create = route();
+        let mut list = route();
+        list.method = "GET".to_string();
+        list.handler.as_mut().unwrap().name = "list".to_string();
+        let mut delete = route();
+        delete.method = "DELETE".to_string();
+        delete.path = "/things/{id}".to_string();
+        delete.handler.as_mut().unwrap().name = "delete".to_string();
+        let setup = r#"pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
+    let auth_guard = AuthGuard::new(Role::User);
+    cfg.app_data(web::Data::new(Things));
+    cfg.service(web::resource("/others").to(handler::other).wrap(auth_guard));
+}
+"#;
+        let out = update_setup(setup, "Things", &[], &[&create, &list, &delete])
+            .unwrap()
+            .unwrap();
+        assert_eq!(out.matches("web::resource(\"/things\")").count(), 1);
+        assert!(out.contains(".to(handler::other).wrap(auth_guard.clone())"));
+        assert_eq!(out.matches(".wrap(auth_guard.clone())").count(), 3);
+
+        // One resource can't be wrapped in both
+        list.middleware = None;
+        let err = update_setup(setup, "Things", &[], &[&create, &list]).unwrap_err();
+        assert!(err.to_string().contains("different middleware"));
+
+        let unbound = setup.replace("let auth_guard", "let session");
+        let err = update_setup(&unbound, "Things", &[], &[&create]).unwrap_err();
+        assert!(err.to_string().contains("auth_guard is not bound"));
+    }
+
+    #[test]
+    fn delegates_to_the_service() {
+        let route = route();
+        let data = route.input.as_ref().unwrap();
+        let handler_rs = update_handlers("", &[&route], &[data], true)
+            .unwrap()
+            .unwrap();
+        assert!(handler_rs.contains("use actix_web::{web, Responder};"));
+        assert!(handler_rs.contains(") -> Result<impl Responder, Error> {"));
+        assert!(handler_rs.contains("    service.create(data).await\n}"));
+    }
+
+    #[test]
+    fn finds_existing_service_type() {
+        let src = ".to(handler::get::<\n    UserService<Repository<PgUserAdapter>>,\n>)";
+        assert_eq!(
+            existing_service_type(src).as_deref(),
+            Some("UserService<Repository<PgUserAdapter>>")
+        );
+    }
+}
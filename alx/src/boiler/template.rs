@@ -0,0 +1,502 @@
+//! User templates overriding the boilerplate in `plate`. Templates live in
+//! `.alx/templates/{route,middleware}/<file>.rs.tmpl` and are rendered with a small syntax:
+//!
+//! - `{{ service }}` and `{{ contract.pascal }}` insert variables,
+//! - `{% for contract in contracts %}..{% endfor %}` loops over lists, with `loop.first`,
+//!   `loop.last` and `loop.index` available in the body,
+//! - `{% if contracts %}..{% else %}..{% endif %}` checks a variable is true, a non-empty string
+//!   or a non-empty list. Conditions can be negated with `not` and compared to a string literal
+//!   with `==` or `!=`,
+//! - `{# .. #}` is a comment.
+//!
+//! Lines containing only a `{% %}` or `{# #}` tag are removed from the output entirely.
+use super::{configure::contract_clients, plate::BoilerType};
+use crate::{error::AlxError, settings::settings, uppercase};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Where the templates are looked up, relative to the project root
+pub const TEMPLATES_DIR: &str = ".alx/templates";
+const EXTENSION: &str = ".rs.tmpl";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Bool(bool),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+            Value::List(l) => !l.is_empty(),
+            Value::Map(_) => true,
+        }
+    }
+
+    fn map<const N: usize>(entries: [(&str, Value); N]) -> Self {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+pub type Context = BTreeMap<String, Value>;
+
+/// The variables available to the route and middleware templates:
+///
+/// - `name`, the name given to the generator, i.e. `blog_post`,
+/// - `service`, the name of the domain service, i.e. `Blog_post`,
+/// - `vis`, the visibility of the contracts, `pub(super)` for routes and `pub(crate)` for
+///   middleware,
+/// - `contracts`, a list with the `name`, `pascal` case name and generic `letter` of each contract,
+/// - `generics`, the letters of the contracts separated by commas, i.e. `R, C`,
+/// - `clients`, a list with the `ty`, import `path`, `local` in configure.rs and `param` name in
+///   `setup::routes` of each client the contracts need.
+pub fn context(name: &str, service_name: &str, contracts: &[&str], ty: BoilerType) -> Context {
+    let vis = settings().generator(ty).contract_visibility.as_str();
+    let letters = contracts
+        .iter()
+        .map(|c| uppercase(c)[..1].to_string())
+        .collect::<Vec<_>>();
+    let contract_values = contracts
+        .iter()
+        .zip(letters.iter())
+        .map(|(c, letter)| {
+            Value::map([
+                ("name", (*c).into()),
+                ("pascal", uppercase(c).as_str().into()),
+                ("letter", letter.as_str().into()),
+            ])
+        })
+        .collect();
+    let clients = contract_clients(contracts)
+        .into_iter()
+        .map(|c| {
+            Value::map([
+                ("ty", c.ty.into()),
+                ("path", c.path.into()),
+                ("local", c.local.into()),
+                ("param", c.param.into()),
+            ])
+        })
+        .collect();
+
+    Context::from([
+        ("name".to_string(), name.into()),
+        ("service".to_string(), service_name.into()),
+        ("vis".to_string(), vis.into()),
+        ("contracts".to_string(), Value::List(contract_values)),
+        ("generics".to_string(), letters.join(", ").as_str().into()),
+        ("clients".to_string(), Value::List(clients)),
+    ])
+}
+
+/// The parsed templates of a kind of boilerplate, keyed by the file they generate
+#[derive(Debug, Default)]
+pub struct Templates {
+    templates: BTreeMap<String, Template>,
+}
+
+impl Templates {
+    /// Loads the templates in `<root>/.alx/templates/<kind>`. A missing directory means no
+    /// templates. Every template is parsed so syntax errors surface before anything is written.
+    pub fn load(root: &Path, kind: &str, files: &[&str]) -> Result<Self, AlxError> {
+        let dir = root.join(TEMPLATES_DIR).join(kind);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Ok(Self::default());
+        };
+        let mut templates = BTreeMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let Some(file) = name.strip_suffix(EXTENSION) else {
+                continue;
+            };
+            if !files.contains(&file) {
+                return Err(AlxError::Template(format!(
+                    "{}: {kind} has no file {file}.rs, expected one of {}",
+                    path.display(),
+                    files.join(", ")
+                )));
+            }
+            let src = fs::read_to_string(&path)?;
+            templates.insert(
+                file.to_string(),
+                Template::parse(&path.display().to_string(), &src)?,
+            );
+        }
+        Ok(Self { templates })
+    }
+
+    /// Renders the template for the file, `None` if there isn't one
+    pub fn render(&self, file: &str, ctx: &Context) -> Option<Result<String, AlxError>> {
+        self.templates.get(file).map(|t| t.render(ctx))
+    }
+}
+
+#[derive(Debug)]
+pub struct Template {
+    name: String,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var(Vec<String>, usize),
+    If(Cond, Vec<Node>, Vec<Node>),
+    For(String, Vec<String>, Vec<Node>, usize),
+}
+
+#[derive(Debug)]
+struct Cond {
+    negated: bool,
+    path: Vec<String>,
+    /// `true` for `==`, the literal compared to
+    compare: Option<(bool, String)>,
+    line: usize,
+}
+
+/// A block being parsed
+#[derive(Debug, Default)]
+struct Frame {
+    /// The tag opening the block and its line, `None` for the template itself
+    open: Option<(String, usize)>,
+    nodes: Vec<Node>,
+    /// The nodes before the `else`, if the block has one
+    els: Option<Vec<Node>>,
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Var(String, usize),
+    Tag(String, usize),
+}
+
+impl Template {
+    /// `name` is only used in error messages
+    pub fn parse(name: &str, src: &str) -> Result<Self, AlxError> {
+        let err = |line: usize, msg: String| AlxError::Template(format!("{name}:{line}: {msg}"));
+        let tokens = tokenize(src).map_err(|(line, msg)| err(line, msg))?;
+
+        let mut stack = vec![Frame::default()];
+        for token in tokens {
+            let frame = stack.last_mut().unwrap();
+            match token {
+                Token::Text(t) => frame.nodes.push(Node::Text(t)),
+                Token::Var(v, line) => frame.nodes.push(Node::Var(path(&v), line)),
+                Token::Tag(tag, line) => {
+                    let (keyword, rest) = tag.split_once(' ').unwrap_or((&tag, ""));
+                    match keyword {
+                        "if" | "for" => stack.push(Frame {
+                            open: Some((tag.clone(), line)),
+                            ..Default::default()
+                        }),
+                        "else" => match frame.open {
+                            Some((ref open, _))
+                                if open.starts_with("if ") && frame.els.is_none() =>
+                            {
+                                frame.els = Some(std::mem::take(&mut frame.nodes));
+                            }
+                            _ => return Err(err(line, "unexpected else".to_string())),
+                        },
+                        "endif" | "endfor" => {
+                            let frame = stack.pop().unwrap();
+                            let open = match frame.open {
+                                Some(open) if open.0.starts_with(&keyword[3..]) => open,
+                                _ => return Err(err(line, format!("unexpected {keyword}"))),
+                            };
+                            let node =
+                                block(open, frame.nodes, frame.els).map_err(|(l, m)| err(l, m))?;
+                            stack.last_mut().unwrap().nodes.push(node);
+                        }
+                        _ => return Err(err(line, format!("unknown tag `{keyword} {rest}`"))),
+                    }
+                }
+            }
+        }
+        let frame = stack.pop().unwrap();
+        match frame.open {
+            None => Ok(Self {
+                name: name.to_string(),
+                nodes: frame.nodes,
+            }),
+            Some((open, line)) => Err(err(line, format!("`{open}` is never closed"))),
+        }
+    }
+
+    pub fn render(&self, ctx: &Context) -> Result<String, AlxError> {
+        let mut out = String::new();
+        let mut scopes = vec![];
+        render(&self.nodes, ctx, &mut scopes, &mut out)
+            .map_err(|(line, msg)| AlxError::Template(format!("{}:{line}: {msg}", self.name)))?;
+        Ok(out)
+    }
+}
+
+/// Builds the node of an `if` or `for` block from its opening tag
+fn block(
+    (open, line): (String, usize),
+    body: Vec<Node>,
+    els: Option<Vec<Node>>,
+) -> Result<Node, (usize, String)> {
+    let words = open.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["for", var, "in", list] => match els {
+            None => Ok(Node::For(var.to_string(), path(list), body, line)),
+            Some(_) => Err((line, "for loops can't have an else".to_string())),
+        },
+        ["if", cond @ ..] => {
+            let (negated, cond) = match cond {
+                ["not", rest @ ..] => (true, rest),
+                _ => (false, cond),
+            };
+            let compare = match cond {
+                [_] => None,
+                [_, op @ ("==" | "!="), lit]
+                    if lit.len() >= 2 && lit.starts_with('"') && lit.ends_with('"') =>
+                {
+                    Some((*op == "==", lit[1..lit.len() - 1].to_string()))
+                }
+                _ => return Err((line, format!("invalid condition `{open}`"))),
+            };
+            let cond = Cond {
+                negated,
+                path: path(cond[0]),
+                compare,
+                line,
+            };
+            match els {
+                Some(then) => Ok(Node::If(cond, then, body)),
+                None => Ok(Node::If(cond, body, vec![])),
+            }
+        }
+        _ => Err((line, format!("invalid tag `{open}`"))),
+    }
+}
+
+fn path(s: &str) -> Vec<String> {
+    s.trim().split('.').map(str::to_string).collect()
+}
+
+/// Splits the source into text, variables and tags. Tags alone on their line take the line with
+/// them.
+fn tokenize(src: &str) -> Result<Vec<Token>, (usize, String)> {
+    let mut tokens = vec![];
+    let mut rest = src;
+    let mut line = 1;
+    while let Some(start) = rest.find('{') {
+        let kind = rest[start + 1..].chars().next();
+        let close = match kind {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => {
+                push_text(&mut tokens, &rest[..start + 1]);
+                line += rest[..start + 1].matches('\n').count();
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        let mut text = &rest[..start];
+        let Some(len) = rest[start + 2..].find(close) else {
+            let line = line + text.matches('\n').count();
+            return Err((
+                line,
+                format!("`{}` is never closed", &rest[start..start + 2]),
+            ));
+        };
+        let inner = rest[start + 2..start + 2 + len].trim().to_string();
+        let mut after = &rest[start + 2 + len + 2..];
+        line += text.matches('\n').count();
+        let tag_line = line;
+        line += inner.matches('\n').count();
+
+        // A block tag or comment alone on its line removes the whole line
+        if kind != Some('{') {
+            let line_start = text.rfind('\n').map_or(0, |i| i + 1);
+            let on_own_line = text[line_start..].trim().is_empty()
+                && (line_start > 0 || tokens.is_empty() || ends_line(&tokens));
+            let line_end = after.find('\n');
+            let rest_empty = match line_end {
+                Some(end) => after[..end].trim().is_empty(),
+                None => after.trim().is_empty(),
+            };
+            if on_own_line && rest_empty {
+                text = &text[..line_start];
+                after = match line_end {
+                    Some(end) => {
+                        line += 1;
+                        &after[end + 1..]
+                    }
+                    None => "",
+                };
+            }
+        }
+
+        push_text(&mut tokens, text);
+        match kind {
+            Some('{') => tokens.push(Token::Var(inner, tag_line)),
+            Some('%') => tokens.push(Token::Tag(inner, tag_line)),
+            _ => {}
+        }
+        rest = after;
+    }
+    push_text(&mut tokens, rest);
+    Ok(tokens)
+}
+
+/// Whether the output so far ends at the start of a line, so the next tag can own its line
+fn ends_line(tokens: &[Token]) -> bool {
+    match tokens.iter().rev().find(|t| !matches!(t, Token::Tag(..))) {
+        Some(Token::Text(t)) => t.ends_with('\n'),
+        Some(Token::Var(..)) => false,
+        _ => true,
+    }
+}
+
+fn push_text(tokens: &mut Vec<Token>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match tokens.last_mut() {
+        Some(Token::Text(t)) => t.push_str(text),
+        _ => tokens.push(Token::Text(text.to_string())),
+    }
+}
+
+fn render(
+    nodes: &[Node],
+    ctx: &Context,
+    scopes: &mut Vec<(String, Value)>,
+    out: &mut String,
+) -> Result<(), (usize, String)> {
+    for node in nodes {
+        match node {
+            Node::Text(t) => out.push_str(t),
+            Node::Var(path, line) => match lookup(path, ctx, scopes, *line)? {
+                Value::Str(s) => out.push_str(&s),
+                Value::Bool(b) => out.push_str(&b.to_string()),
+                _ => return Err((*line, format!("`{}` is a list or a map", path.join(".")))),
+            },
+            Node::If(cond, then, els) => {
+                let value = lookup(&cond.path, ctx, scopes, cond.line)?;
+                let mut result = match cond.compare {
+                    Some((eq, ref lit)) => (value == Value::Str(lit.clone())) == eq,
+                    None => value.truthy(),
+                };
+                if cond.negated {
+                    result = !result;
+                }
+                match result {
+                    true => render(then, ctx, scopes, out)?,
+                    false => render(els, ctx, scopes, out)?,
+                }
+            }
+            Node::For(var, path, body, line) => {
+                let Value::List(items) = lookup(path, ctx, scopes, *line)? else {
+                    return Err((*line, format!("`{}` is not a list", path.join("."))));
+                };
+                for (i, item) in items.iter().enumerate() {
+                    let meta = Value::map([
+                        ("first", Value::Bool(i == 0)),
+                        ("last", Value::Bool(i == items.len() - 1)),
+                        ("index", (i + 1).to_string().as_str().into()),
+                    ]);
+                    scopes.push(("loop".to_string(), meta));
+                    scopes.push((var.clone(), item.clone()));
+                    let result = render(body, ctx, scopes, out);
+                    scopes.truncate(scopes.len() - 2);
+                    result?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the variable, innermost loop variables first
+fn lookup(
+    path: &[String],
+    ctx: &Context,
+    scopes: &[(String, Value)],
+    line: usize,
+) -> Result<Value, (usize, String)> {
+    let unknown = || (line, format!("unknown variable `{}`", path.join(".")));
+    let (first, fields) = path.split_first().ok_or_else(unknown)?;
+    let mut value = scopes
+        .iter()
+        .rev()
+        .find(|(name, _)| name == first)
+        .map(|(_, v)| v)
+        .or_else(|| ctx.get(first))
+        .ok_or_else(unknown)?;
+    for field in fields {
+        value = match value {
+            Value::Map(m) => m.get(field).ok_or_else(unknown)?,
+            _ => return Err(unknown()),
+        };
+    }
+    Ok(value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_templates() {
+        let src = r#"{# The service #}
+pub(super) struct {{ service }}{% if contracts %}<{{ generics }}>{% endif %}
+{% if contracts %}
+where
+    {% for c in contracts %}
+    {{ c.letter }}: {{ c.pascal }}Contract{% if not loop.last %},{% endif %}
+    {% endfor %}
+{% else %}
+;
+{% endif %}
+{% if vis == "pub(super)" %}
+// route
+{% endif %}
+"#;
+        let template = Template::parse("domain.rs.tmpl", src).unwrap();
+        let ctx = context("posts", "Posts", &["repo", "cache"], BoilerType::Route);
+        assert_eq!(
+            template.render(&ctx).unwrap(),
+            "pub(super) struct Posts<R, C>\nwhere\n    R: RepoContract,\n    C: CacheContract\n// route\n"
+        );
+        let ctx = context("posts", "Posts", &[], BoilerType::MW);
+        assert_eq!(
+            template.render(&ctx).unwrap(),
+            "pub(super) struct Posts\n;\n"
+        );
+
+        let err = Template::parse("t", "a\n{% if x %}\nb")
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("t:2: `if x` is never closed"), "{err}");
+        let err = Template::parse("t", "{{ nope }}")
+            .unwrap()
+            .render(&ctx)
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("t:1: unknown variable `nope`"), "{err}");
+    }
+}
@@ -1,4 +1,9 @@
-use super::{envex::EnvExOptions, generate::GenerateSubject, migration::Migration};
+use super::{
+    audit::AuditOptions, diff::DiffOptions, doctor::DoctorOptions, env::EnvCommand,
+    envex::EnvExOptions, export::ExportCommand, generate::GenerateSubject, graph::GraphOptions,
+    lint::LintOptions, migration::Migration, new::NewOptions, openapi::OpenApiOptions,
+    remove::RemoveCommand, route::RouteCommand, schema::SchemaOptions, secrets::SecretsCommand,
+};
 use crate::analyzer::analyze::AnalyzeOptions;
 use clap::{Parser, Subcommand};
 use std::fmt::Display;
@@ -12,18 +17,57 @@ pub struct Alx {
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
+    // project bootstrapping
+    New(NewOptions),
+
     // alx component generation
     Generate(GenerateSubject),
     Gen(GenerateSubject),
     G(GenerateSubject),
 
+    // route modification
+    Route(RouteCommand),
+
+    // removal of generated components
+    Remove(RemoveCommand),
+
     // analyzer
     Analyze(AnalyzeOptions),
     Anal(AnalyzeOptions),
 
+    // breaking change detection
+    Diff(DiffOptions),
+
+    // project structure checks
+    Doctor(DoctorOptions),
+
+    // layering rules
+    Lint(LintOptions),
+
+    // route guard audit
+    Audit(AuditOptions),
+
+    // dependency graph
+    Graph(GraphOptions),
+
+    // OpenAPI document
+    Openapi(OpenApiOptions),
+
+    // JSON Schemas of request data
+    Schema(SchemaOptions),
+
+    // Postman/Insomnia collections
+    Export(ExportCommand),
+
     // .env.example
     Envex(EnvExOptions),
 
+    // env variable checks
+    Env(EnvCommand),
+
+    // secrets and key pairs
+    Secrets(SecretsCommand),
+
     // postgres migrations
     Migration(Migration),
     Mig(Migration),
@@ -42,14 +86,67 @@ impl Display for Command {
                 | super::generate::GenerateSubcommand::MW(_) => {
                     write!(f, "Generating middleware")
                 }
+                super::generate::GenerateSubcommand::Client(_) => write!(f, "Generating client"),
+                super::generate::GenerateSubcommand::FromSpec(_) => {
+                    write!(f, "Generating routes from spec")
+                }
+                super::generate::GenerateSubcommand::Handler(_) => write!(f, "Generating handler"),
+                super::generate::GenerateSubcommand::Repository(_) => {
+                    write!(f, "Generating repository")
+                }
+                super::generate::GenerateSubcommand::Tests(_) => write!(f, "Generating tests"),
+                super::generate::GenerateSubcommand::Integration(_) => {
+                    write!(f, "Generating integration tests")
+                }
+            },
+            Command::New(_) => write!(f, "Creating project"),
+            Command::Route(r) => match r.command {
+                super::route::RouteSubcommand::Gen(_) | super::route::RouteSubcommand::G(_) => {
+                    write!(f, "Generating route")
+                }
+                super::route::RouteSubcommand::AddContract(_)
+                | super::route::RouteSubcommand::AC(_) => write!(f, "Adding contract"),
+            },
+            Command::Remove(r) => match r.subject {
+                super::remove::RemoveSubcommand::Route(_) => write!(f, "Removing route"),
             },
             Command::Analyze(_) | Command::Anal(_) => write!(f, "Analyzing"),
+            Command::Diff(_) => write!(f, "Comparing API to lock file"),
+            Command::Doctor(_) => write!(f, "Checking project structure"),
+            Command::Lint(_) => write!(f, "Linting layers"),
+            Command::Audit(_) => write!(f, "Auditing route guards"),
+            Command::Graph(_) => write!(f, "Generating dependency graph"),
+            Command::Openapi(_) => write!(f, "Generating OpenAPI document"),
+            Command::Schema(_) => write!(f, "Generating JSON Schemas"),
+            Command::Export(_) => write!(f, "Exporting collection"),
             Command::Envex(_) => write!(f, "Generating .env.example"),
+            Command::Env(c) => match c.action {
+                super::env::EnvSubcommand::Check(_) => write!(f, "Checking env variables"),
+            },
+            Command::Secrets(c) => match c.action {
+                super::secrets::SecretsSubcommand::Gen(ref g) => match g.kind {
+                    super::secrets::SecretsGenSubcommand::Secret(_) => {
+                        write!(f, "Generating secret")
+                    }
+                    super::secrets::SecretsGenSubcommand::Password(_) => {
+                        write!(f, "Generating password")
+                    }
+                    super::secrets::SecretsGenSubcommand::KeyPair(_) => {
+                        write!(f, "Generating key pair")
+                    }
+                },
+                super::secrets::SecretsSubcommand::List(_) => write!(f, "Listing secrets"),
+                super::secrets::SecretsSubcommand::Rotate(_) => write!(f, "Rotating secret"),
+                super::secrets::SecretsSubcommand::Check(_) => write!(f, "Checking secrets"),
+            },
             Command::Migration(c) | Command::Mig(c) | Command::M(c) => match c.action {
                 super::migration::MigrationSubcommand::Gen(_) => write!(f, "Generating migration"),
-                super::migration::MigrationSubcommand::Run => write!(f, "Running migrations"),
-                super::migration::MigrationSubcommand::Rev => write!(f, "Reversing migration"),
+                super::migration::MigrationSubcommand::Run(_) => write!(f, "Running migrations"),
+                super::migration::MigrationSubcommand::Rev(_) => write!(f, "Reversing migration"),
                 super::migration::MigrationSubcommand::Redo(_) => write!(f, "Restarting migration"),
+                super::migration::MigrationSubcommand::Status(_) => {
+                    write!(f, "Checking migration status")
+                }
             },
         }
     }
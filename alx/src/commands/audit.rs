@@ -0,0 +1,107 @@
+//! List every route with the guard protecting it and flag state changing routes without one
+use crate::{
+    analyzer::audit::{audit, Allowlist},
+    error::AlxError,
+    print,
+    settings::settings,
+};
+use clap::Args;
+use colored::Colorize;
+use std::{fmt::Write, fs, path::Path};
+
+/// The allowlist of intentionally public routes
+pub const DEFAULT_ALLOWLIST: &str = ".alx/public_routes";
+
+/// List the guard of every route. Exits with a non-zero code if a POST, PUT, PATCH or DELETE route
+/// has no guard and isn't in the allowlist.
+#[derive(Debug, Args)]
+pub struct AuditOptions {
+    /// File listing the routes that are public on purpose, one `METHOD /path` or `/path` per line,
+    /// .alx/public_routes next to alx.toml by default
+    #[arg(short, long)]
+    pub allow: Option<String>,
+    /// Add the currently flagged routes to the allowlist
+    #[arg(short, long, action)]
+    pub write: bool,
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Returns true if exposed routes remain
+pub fn handle(opts: AuditOptions, api_path: &str) -> Result<bool, AlxError> {
+    let allow = match opts.allow {
+        Some(ref a) => a.to_string(),
+        None => settings().project_path(DEFAULT_ALLOWLIST),
+    };
+    let routes = audit(api_path)?;
+    let allowlist_src = match fs::read_to_string(&allow) {
+        Ok(src) => src,
+        Err(_) => {
+            print(&format!("No allowlist at {allow}"));
+            String::new()
+        }
+    };
+    let allowlist = Allowlist::parse(&allowlist_src);
+
+    let mut exposed = vec![];
+    let mut endpoint = "";
+    for route in routes.iter() {
+        if route.endpoint != endpoint {
+            endpoint = &route.endpoint;
+            println!("{}", endpoint.bold());
+        }
+        let (mark, guard) = match route.guard {
+            Some(ref guard) => ("\u{2713}".green(), guard.to_string().green()),
+            None if !route.exposed() => ("-".normal(), "public".normal()),
+            None if allowlist.allows(route) => ("\u{2713}".yellow(), "public, allowed".yellow()),
+            None => {
+                exposed.push(route);
+                ("\u{2717}".red(), "public".red())
+            }
+        };
+        println!("  {} {} {} {}", mark, route.method, route.path, guard);
+    }
+
+    let stale = allowlist.stale(&routes);
+    if !stale.is_empty() {
+        println!(
+            "{} {} ({})",
+            "Not matching a public route in".yellow().bold(),
+            allow,
+            stale.len()
+        );
+        for entry in stale.iter() {
+            println!("  {} {}", "-".yellow(), entry);
+        }
+    }
+
+    if exposed.is_empty() {
+        println!("{}", "No unguarded state changing routes".green());
+        return Ok(false);
+    }
+    if opts.write {
+        let mut src = allowlist_src;
+        if !src.is_empty() && !src.ends_with('\n') {
+            src.push('\n');
+        }
+        for route in exposed.iter() {
+            writeln!(src, "{} {}", route.method, route.path).unwrap();
+        }
+        if let Some(parent) = Path::new(&allow).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&allow, src)?;
+        println!("{}{}", "Successfully wrote ".green(), allow);
+        return Ok(false);
+    }
+    println!(
+        "{} ({})",
+        "Unguarded state changing routes".red().bold(),
+        exposed.len()
+    );
+    Ok(true)
+}
@@ -0,0 +1,63 @@
+//! Compare the current state of the API to a lock file and report breaking changes
+use crate::{
+    analyzer::{analyze::project_config, diff::diff},
+    config::ProjectConfig,
+    error::AlxError,
+    print,
+    settings::settings,
+};
+use clap::Args;
+use colored::Colorize;
+
+/// Re-run the analyzer and compare the result to an existing lock file. Exits with a non-zero
+/// code if breaking changes are found.
+#[derive(Debug, Args)]
+pub struct DiffOptions {
+    /// The lock file to compare against, alx_lock.json in the lock directory of alx.toml by default
+    #[arg(short, long)]
+    pub against: Option<String>,
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Returns true if breaking changes were found
+pub fn handle(opts: DiffOptions, api_path: &str) -> Result<bool, AlxError> {
+    let against = match opts.against {
+        Some(ref a) => a.to_string(),
+        None => settings().lock.file("json"),
+    };
+    print(&format!("Reading {}", against));
+    let old = ProjectConfig::read_lock(&against)?;
+    let new = project_config(api_path)?;
+
+    let changes = diff(&old, &new);
+    if changes.is_empty() {
+        println!("{}", "No changes found".green());
+        return Ok(false);
+    }
+
+    let (breaking, non_breaking): (Vec<_>, Vec<_>) =
+        changes.into_iter().partition(|c| c.is_breaking());
+
+    if !breaking.is_empty() {
+        println!("{} ({})", "Breaking changes".red().bold(), breaking.len());
+        for c in breaking.iter() {
+            println!("  {} {}", "\u{2717}".red(), c);
+        }
+    }
+    if !non_breaking.is_empty() {
+        println!(
+            "{} ({})",
+            "Non-breaking changes".green().bold(),
+            non_breaking.len()
+        );
+        for c in non_breaking.iter() {
+            println!("  {} {}", "\u{2713}".green(), c);
+        }
+    }
+    Ok(!breaking.is_empty())
+}
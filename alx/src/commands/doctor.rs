@@ -0,0 +1,143 @@
+//! Check the project follows the structure the generators assume and fix what can be fixed
+use crate::{
+    analyzer::doctor::{check, Fix},
+    boiler::{configure, edit},
+    error::AlxError,
+    print,
+};
+use clap::Args;
+use colored::Colorize;
+use std::{fs, path::Path};
+
+/// Check routes and middleware are declared, complete and registered. Exits with a non-zero code
+/// if problems remain.
+#[derive(Debug, Args)]
+pub struct DoctorOptions {
+    /// Apply the fixes for mechanical problems, such as undeclared modules, missing files and
+    /// routes not called from configure.rs
+    #[arg(long, action)]
+    pub fix: bool,
+    /// The path to configure.rs. Defaults to configure.rs next to the API directory.
+    #[arg(short, long)]
+    pub configure: Option<String>,
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Returns true if problems remain
+pub fn handle(opts: DoctorOptions, api_path: &str) -> Result<bool, AlxError> {
+    let configure = match opts.configure {
+        Some(ref c) => c.clone(),
+        None => Path::new(api_path)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("configure.rs")
+            .display()
+            .to_string(),
+    };
+    print(&format!("Checking {api_path} and {configure}"));
+
+    let diagnostics = check(api_path, &configure)?;
+    if diagnostics.is_empty() {
+        println!("{}", "No problems found".green());
+        return Ok(false);
+    }
+
+    let mut remaining = 0;
+    for d in diagnostics.iter() {
+        match (&d.fix, opts.fix) {
+            (Some(fix), true) => match apply(fix) {
+                Ok(_) => println!(
+                    "  {} {}: {} ({})",
+                    "\u{2713}".green(),
+                    d.file,
+                    d.message,
+                    format!("fixed, {fix}").green()
+                ),
+                Err(e) => {
+                    remaining += 1;
+                    println!(
+                        "  {} {}: {} ({})",
+                        "\u{2717}".red(),
+                        d.file,
+                        d.message,
+                        format!("unable to fix: {e}").red()
+                    );
+                }
+            },
+            (Some(fix), false) => {
+                remaining += 1;
+                println!(
+                    "  {} {}: {} ({})",
+                    "\u{2717}".red(),
+                    d.file,
+                    d.message,
+                    format!("fixable, {fix}").yellow()
+                );
+            }
+            (None, _) => {
+                remaining += 1;
+                println!("  {} {}: {}", "\u{2717}".red(), d.file, d.message);
+            }
+        }
+    }
+
+    let fixable = diagnostics.iter().filter(|d| d.fix.is_some()).count();
+    if remaining == 0 {
+        println!("{}", "All problems fixed".green());
+    } else {
+        println!("{} ({})", "Problems found".red().bold(), remaining);
+        if !opts.fix && fixable > 0 {
+            println!("Run with --fix to fix {fixable} of them");
+        }
+    }
+    Ok(remaining > 0)
+}
+
+fn apply(fix: &Fix) -> Result<(), AlxError> {
+    match fix {
+        Fix::Declare {
+            mod_file,
+            name,
+            vis,
+        } => declare(mod_file, name, vis),
+        Fix::Create {
+            path,
+            mod_file,
+            name,
+            vis,
+        } => {
+            print(&format!("{} Writing {}", "\u{270E}".blue(), path));
+            fs::write(path, "")?;
+            match name.as_str() {
+                "mod" => Ok(()),
+                _ => declare(mod_file, name, vis),
+            }
+        }
+        Fix::Wire {
+            configure,
+            route,
+            setup,
+        } => {
+            let src = fs::read_to_string(configure)?;
+            if let Some(src) = configure::wire(&src, route, &fs::read_to_string(setup)?)? {
+                print(&format!("{} Writing {}", "\u{270E}".blue(), configure));
+                fs::write(configure, src)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn declare(mod_file: &str, name: &str, vis: &str) -> Result<(), AlxError> {
+    let src = fs::read_to_string(mod_file).unwrap_or_default();
+    if let Some(src) = edit::add_mod(&src, vis, name)? {
+        print(&format!("{} Writing {}", "\u{270E}".blue(), mod_file));
+        fs::write(mod_file, src)?;
+    }
+    Ok(())
+}
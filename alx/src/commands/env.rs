@@ -0,0 +1,229 @@
+//! Check the env variables the workspace reads against the ones declared in the .env files
+use crate::{
+    analyzer::env::{declared, interpolated, scan, EnvVar},
+    error::AlxError,
+    print,
+};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::{fmt::Write, fs, path::Path};
+
+/// Check and document the env variables read by the workspace
+#[derive(Debug, Args)]
+pub struct EnvCommand {
+    #[clap(subcommand)]
+    pub action: EnvSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EnvSubcommand {
+    /// Report variables read by the code but missing from the env files, variables declared but
+    /// never read and variables with defaults. Exits with a non-zero code if a variable without a
+    /// default is missing.
+    Check(EnvCheckArgs),
+}
+
+/// Env check arguments
+#[derive(Debug, Args)]
+pub struct EnvCheckArgs {
+    /// The workspace root to scan
+    #[arg(short, long, default_value = ".")]
+    pub root: String,
+    /// The .env file to check
+    #[arg(long, default_value = "./.env")]
+    pub env: String,
+    /// The .env.example file to check
+    #[arg(long, default_value = "./.env.example")]
+    pub example: String,
+    /// Regenerate the .env.example with a comment per variable saying where it's used
+    #[arg(short, long, action)]
+    pub write: bool,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Prefix of the comments written above each variable of the generated .env.example
+const USED_IN: &str = "# Used in ";
+const UNUSED: &str = "# Unused";
+
+/// Returns true if a variable without a default is missing from an env file
+pub fn handle_check(args: EnvCheckArgs) -> Result<bool, AlxError> {
+    print(&format!("Scanning {}", args.root));
+    let vars = scan(Path::new(&args.root));
+
+    let mut missing_required = false;
+    let mut files = vec![];
+    for path in [&args.env, &args.example] {
+        match fs::read_to_string(path) {
+            Ok(src) => files.push((path, src)),
+            Err(_) => println!("{}{}", "Skipping missing file ".yellow(), path),
+        }
+    }
+
+    for (path, src) in files.iter() {
+        let declared = declared(src);
+        let missing = vars
+            .iter()
+            .filter(|v| !declared.iter().any(|(k, _)| *k == v.key))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            println!(
+                "{} {} ({})",
+                "Missing from".red().bold(),
+                path,
+                missing.len()
+            );
+            for v in missing.iter() {
+                let mark = match v.default {
+                    Some(_) => "\u{2713}".yellow(),
+                    None => {
+                        missing_required = true;
+                        "\u{2717}".red()
+                    }
+                };
+                println!("  {} {} ({})", mark, v.key, v.uses.join(", "));
+            }
+        }
+
+        let unused = unused(&vars, &declared);
+        if !unused.is_empty() {
+            println!(
+                "{} {} ({})",
+                "Declared but unused in".yellow().bold(),
+                path,
+                unused.len()
+            );
+            for key in unused.iter() {
+                println!("  {} {}", "-".yellow(), key);
+            }
+        }
+    }
+
+    let defaults = vars
+        .iter()
+        .filter(|v| v.default.is_some())
+        .collect::<Vec<_>>();
+    if !defaults.is_empty() {
+        println!("{} ({})", "With defaults".green().bold(), defaults.len());
+        for v in defaults {
+            println!(
+                "  {} {} = {:?} ({})",
+                "\u{2713}".green(),
+                v.key,
+                v.default.as_deref().unwrap_or_default(),
+                v.uses.join(", ")
+            );
+        }
+    }
+
+    if args.write {
+        // Prefer the .env as the template as it is the most up to date
+        let template = files
+            .first()
+            .map(|(_, src)| src.as_str())
+            .unwrap_or_default();
+        let example = example(template, &vars);
+        fs::write(&args.example, example)?;
+        println!("{}{}", "Successfully wrote ".green(), args.example);
+    }
+
+    Ok(missing_required)
+}
+
+/// Declared keys neither read by the code nor interpolated in a used variable
+pub(crate) fn unused(vars: &[EnvVar], declared: &[(String, String)]) -> Vec<String> {
+    let used = |key: &str| vars.iter().any(|v| v.key == key);
+    let interpolated = declared
+        .iter()
+        .filter(|(k, _)| used(k))
+        .flat_map(|(_, v)| interpolated(v))
+        .collect::<Vec<_>>();
+    declared
+        .iter()
+        .filter(|(k, _)| !used(k) && !interpolated.contains(k))
+        .map(|(k, _)| k.clone())
+        .collect()
+}
+
+/// Writes the .env.example from the given env file, keeping its comments and layout. Values are
+/// blanked out unless the key contains `_URL` and every variable gets a comment saying where it's
+/// read. Variables read by the code but not in the template are appended at the end.
+pub(crate) fn example(template: &str, vars: &[EnvVar]) -> String {
+    let declared = declared(template);
+    let interpolated = declared
+        .iter()
+        .filter(|(k, _)| vars.iter().any(|v| v.key == *k))
+        .flat_map(|(_, v)| interpolated(v))
+        .collect::<Vec<_>>();
+
+    let mut example = String::new();
+    for line in template.lines() {
+        let trimmed = line.trim();
+        // Comments from a previous run are regenerated
+        if trimmed.starts_with(USED_IN) || trimmed == UNUSED {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| !trimmed.starts_with('#')) else {
+            writeln!(example, "{line}").unwrap();
+            continue;
+        };
+        let key_name = key.trim();
+        match vars.iter().find(|v| v.key == key_name) {
+            Some(var) => writeln!(example, "{}", used_in(var)).unwrap(),
+            None if interpolated.iter().any(|k| k == key_name) => {}
+            None => writeln!(example, "{UNUSED}").unwrap(),
+        }
+        match key.contains("_URL") {
+            true => writeln!(example, "{key}={value}").unwrap(),
+            false => writeln!(example, "{key}=").unwrap(),
+        }
+    }
+
+    let undeclared = vars
+        .iter()
+        .filter(|v| !declared.iter().any(|(k, _)| *k == v.key))
+        .collect::<Vec<_>>();
+    if !undeclared.is_empty() {
+        writeln!(example, "\n### UNDECLARED ###\n").unwrap();
+        for var in undeclared {
+            writeln!(example, "{}\n{}=", used_in(var), var.key).unwrap();
+        }
+    }
+    example
+}
+
+/// The comment listing the files reading the variable
+fn used_in(var: &EnvVar) -> String {
+    let mut files = var
+        .uses
+        .iter()
+        .map(|u| u.rsplit_once(':').map_or(u.as_str(), |(file, _)| file))
+        .collect::<Vec<_>>();
+    files.dedup();
+    let mut comment = format!("{USED_IN}{}", files.join(", "));
+    if let Some(ref default) = var.default {
+        write!(comment, ", defaults to {default:?}").unwrap();
+    }
+    comment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regenerates_the_example() {
+        let var = |key: &str, file: &str| EnvVar {
+            key: key.to_string(),
+            default: None,
+            uses: vec![format!("{file}:1")],
+        };
+        let vars = [var("PG_URL", "a.rs"), var("SECRET", "b.rs")];
+        assert_eq!(
+            example("PG_URL=postgres://x\nOLD=1\n", &vars),
+            "# Used in a.rs\nPG_URL=postgres://x\n# Unused\nOLD=\n\n\
+             ### UNDECLARED ###\n\n# Used in b.rs\nSECRET=\n"
+        );
+    }
+}
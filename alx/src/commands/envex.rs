@@ -1,4 +1,5 @@
 //! Generate an env example file from the .env file in the root
+use crate::boiler::files::{self, WriteArgs};
 use clap::Args;
 use std::fmt::Write;
 
@@ -8,6 +9,8 @@ pub struct EnvExOptions {
     /// If provided alx will search for a .env file in the given directory and generate a .env.example there
     #[arg(short, long)]
     pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
 }
 
 pub fn envex(path: Option<String>) {
@@ -35,5 +38,5 @@ pub fn envex(path: Option<String>) {
 
     write!(path, ".example").unwrap();
 
-    std::fs::write(&path, example).unwrap();
+    files::create(&path, example).unwrap();
 }
@@ -0,0 +1,94 @@
+//! Export the analyzed router directory as a Postman or Insomnia collection
+use crate::{
+    analyzer::{analyze::project_config, audit::Guards},
+    config::ProjectConfig,
+    error::AlxError,
+    export::collection::{insomnia, postman, CollectionInfo},
+    print,
+    validation::resolve_regexes,
+};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::{fs, path::Path};
+
+/// Export the API as a collection for an API client
+#[derive(Debug, Args)]
+pub struct ExportCommand {
+    #[clap(subcommand)]
+    pub format: ExportSubcommand,
+}
+
+/// Supported collection formats
+#[derive(Debug, Subcommand)]
+pub enum ExportSubcommand {
+    /// Export a Postman v2.1 collection.
+    Postman(ExportArgs),
+    /// Export an Insomnia v4 collection.
+    Insomnia(ExportArgs),
+}
+
+/// Export arguments
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Where to write the collection. Defaults to ./<NAME>.postman_collection.json for Postman and
+    /// ./<NAME>.insomnia.json for Insomnia.
+    #[arg(short, long)]
+    pub out: Option<String>,
+    /// The name of the collection
+    #[arg(short, long, default_value = "API")]
+    pub name: String,
+    /// The default value of the host variable
+    #[arg(long, default_value = "http://localhost:8080")]
+    pub host: String,
+    /// The lock file to export. If not given the API is analyzed.
+    #[arg(long)]
+    pub lock: Option<String>,
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+impl ExportSubcommand {
+    pub fn args(&self) -> &ExportArgs {
+        match self {
+            Self::Postman(args) | Self::Insomnia(args) => args,
+        }
+    }
+}
+
+pub fn handle(cmd: ExportSubcommand, api_path: &str) -> Result<(), AlxError> {
+    let args = cmd.args();
+    let pc = match args.lock {
+        Some(ref lock) => ProjectConfig::read_lock(lock)?,
+        None => project_config(api_path)?,
+    };
+    let guards = Guards::of(&pc)?;
+
+    // Regexes are declared somewhere in the crate the API is in
+    let crate_src = Path::new(api_path).parent().unwrap_or(Path::new(api_path));
+    let regexes = resolve_regexes(crate_src);
+    print(&format!("Resolved {} regex statics", regexes.len()));
+
+    let info = CollectionInfo {
+        name: args.name.clone(),
+        host: args.host.clone(),
+    };
+
+    let (collection, default_out) = match cmd {
+        ExportSubcommand::Postman(_) => (
+            postman(&pc, &info, &regexes, &guards),
+            format!("./{}.postman_collection.json", args.name),
+        ),
+        ExportSubcommand::Insomnia(_) => (
+            insomnia(&pc, &info, &regexes, &guards),
+            format!("./{}.insomnia.json", args.name),
+        ),
+    };
+    let out = args.out.clone().unwrap_or(default_out);
+    fs::write(&out, serde_json::to_string_pretty(&collection)?)?;
+    println!("{}{}", "Successfully wrote ".green(), out);
+    Ok(())
+}
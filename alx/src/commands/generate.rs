@@ -1,14 +1,26 @@
 use crate::{
+    analyzer::{analyze::project_config, audit::Guards},
     boiler::{
         self,
-        files::{handle_create_dir, write_to_mod_file},
+        client::ClientLang,
+        configure, edit,
+        files::{self, handle_create_dir, write_to_mod_file, WriteArgs},
+        integration, mock,
         plate::BoilerType,
+        repository::{self, Column, Entity},
+        service::{self, ServiceMethod},
+        spec,
+        template::{self, Templates},
     },
-    print, uppercase, MW_FILES, ROUTE_FILES,
+    config::{Data, Field, Handler, HandlerInput, ProjectConfig, RouteHandler, HTTP_METHODS},
+    error::AlxError,
+    migration, pascal_case, print,
+    settings::settings,
+    uppercase,
 };
 use clap::{Args, Subcommand};
 use colored::Colorize;
-use std::fs;
+use std::path::{Path, PathBuf};
 
 /// Generate a new endpoint or middleware
 #[derive(Debug, Args)]
@@ -28,6 +40,18 @@ pub enum GenerateSubcommand {
     Middleware(GenerateArgs),
     /// Shorthand for add contract.
     MW(GenerateArgs),
+    /// Generate a typed API client.
+    Client(ClientArgs),
+    /// Create or update routes from a spec written in the alx.yaml format.
+    FromSpec(SpecArgs),
+    /// Add a handler to an existing route.
+    Handler(HandlerArgs),
+    /// Generate a repository with a Postgres adapter and migration.
+    Repository(RepositoryArgs),
+    /// Generate mockall tests for the service methods of an existing route.
+    Tests(TestsArgs),
+    /// Generate integration tests for the validation and auth guards of the analyzed routes.
+    Integration(IntegrationArgs),
 }
 
 /// Generate arguments
@@ -36,18 +60,144 @@ pub struct GenerateArgs {
     /// The name of the route of middleware.
     pub name: String,
     /// The various services or repositories the endpoint will use. Comma seperated. e.g. `-c repo,cache`
+    /// Defaults to the contracts in alx.toml.
     #[arg(short, long)]
     pub contracts: Option<String>,
     /// The path to the API you wish to generate this endpoint. Defaults to ./server/api/router
     #[arg(short, long)]
     pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Client generation arguments
+#[derive(Debug, Args)]
+pub struct ClientArgs {
+    /// The language of the client
+    #[arg(short, long, value_enum, default_value = "ts")]
+    pub lang: ClientLang,
+    /// The directory to write the client to.
+    #[arg(short, long, default_value = "./client")]
+    pub out: String,
+    /// The lock file to generate the client from. If not given the API is analyzed.
+    #[arg(long)]
+    pub lock: Option<String>,
+    /// The path to the API directory to analyze. Defaults to ./server/src/api
+    #[arg(short, long)]
+    pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Spec scaffolding arguments
+#[derive(Debug, Args)]
+pub struct SpecArgs {
+    /// The spec to generate routes from. Json if the extension is `.json`, Yaml otherwise.
+    #[arg(default_value = "alx.yaml")]
+    pub spec: String,
+    /// The path to the router directory. Defaults to ./server/src/api/router
+    #[arg(short, long)]
+    pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Handler generation arguments
+#[derive(Debug, Args)]
+pub struct HandlerArgs {
+    /// The name of the route to add the handler to.
+    pub route: String,
+    /// The name of the handler, also used for the service method. e.g. `create_thing`
+    pub name: String,
+    /// The HTTP method of the handler.
+    #[arg(short, long, default_value = "get")]
+    pub method: String,
+    /// The resource path. Defaults to /<ROUTE>
+    #[arg(short, long)]
+    pub url: Option<String>,
+    /// The extractor for the request data, one of `json`, `query`, `path`, `form` or `none`. Defaults to
+    /// `json` for methods with a body and `query` otherwise.
+    #[arg(short, long)]
+    pub extractor: Option<String>,
+    /// The fields of the request data. Comma seperated `name:Type` pairs, e.g. `-f name:String,age:u8`
+    #[arg(short, long)]
+    pub fields: Option<String>,
+    /// Middleware bindings in setup.rs to wrap the resource with. Comma seperated.
+    #[arg(short, long)]
+    pub wrap: Option<String>,
+    /// The path to the router directory. Defaults to ./server/src/api/router
+    #[arg(short, long)]
+    pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Test generation arguments
+#[derive(Debug, Args)]
+pub struct TestsArgs {
+    /// The name of the route to generate the tests for.
+    pub route: String,
+    /// The path to the router directory. Defaults to ./server/src/api/router
+    #[arg(short, long)]
+    pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Integration test generation arguments
+#[derive(Debug, Args)]
+pub struct IntegrationArgs {
+    /// The name of the route to generate the tests for. Generates them for every route if not given.
+    pub route: Option<String>,
+    /// The path to the API directory to analyze. Defaults to ./server/src/api
+    #[arg(short, long)]
+    pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Repository generation arguments
+#[derive(Debug, Args)]
+pub struct RepositoryArgs {
+    /// The name of the entity in snake case, e.g. `blog_post`
+    pub name: String,
+    /// The columns of the table besides the `id`, `created_at` and `updated_at` every table gets.
+    /// Comma seperated `name:Type` pairs, e.g. `-f title:String,body:Option<String>`
+    #[arg(short, long)]
+    pub fields: String,
+    /// The name of the table. Defaults to the plural of the name.
+    #[arg(short, long)]
+    pub table: Option<String>,
+    /// The path to the store directory. Defaults to ./infrastructure/src/store
+    #[arg(short, long)]
+    pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
     /// Print what's going on to stdout
     #[arg(short, long, action)]
     pub verbose: bool,
 }
 
 /// Generate route boilerplate
-pub fn handle_gen_route(args: GenerateArgs, router_path: &str) {
+pub fn handle_gen_route(args: GenerateArgs, router_path: &str) -> Result<(), AlxError> {
     let mut ep_path = format!("{}/{}", router_path, args.name);
     // If a path is given switch to it
     if let Some(ref p) = args.path {
@@ -55,49 +205,548 @@ pub fn handle_gen_route(args: GenerateArgs, router_path: &str) {
     }
     let ep_path = &ep_path;
 
-    let service_name = uppercase(&args.name);
-
     // Gather up contracts if any
     let contracts = match args.contracts {
         Some(ref c) => c.split(',').collect::<Vec<&str>>(),
-        None => vec![],
+        None => default_contracts(BoilerType::Route),
     };
 
+    // Render everything up front so a broken template doesn't leave a half written route behind
+    let files = boiler_files(&args.name, &contracts, BoilerType::Route)?;
+
     // Try to create the directory and prompt for overwrite if it exists
     if !handle_create_dir(ep_path) {
-        return;
+        return Ok(());
     }
 
     // Append the mod clause to the existing router.mod file
     let router_mod = format!("{}/mod.rs", router_path);
-    write_to_mod_file(&router_mod, &args.name);
+    write_to_mod_file(
+        &router_mod,
+        module_visibility(BoilerType::Route),
+        &args.name,
+    );
 
-    for file in ROUTE_FILES {
-        print(&format!("{} Writing {}.rs", "\u{270E}".blue(), file));
-        let mut contents = String::new();
+    write_boiler_files(ep_path, files)?;
+    if let Err(e) = wire_route(router_path, &args.name) {
+        println!(
+            "{}{}",
+            "Unable to register the route in configure.rs: ".yellow(),
+            e
+        );
+    }
+    print(&format!(
+        "{}{}",
+        "Successfully wrote route ".green(),
+        ep_path
+    ));
+    Ok(())
+}
+
+/// Calls the route's `setup::routes` from configure.rs, found next to the API directory
+fn wire_route(router_path: &str, name: &str) -> Result<(), AlxError> {
+    let configure_path = configure_path(router_path);
+    let src = files::read_to_string(&configure_path)?;
+    let setup = files::read_to_string(format!("{router_path}/{name}/setup.rs"))?;
+    if let Some(src) = configure::wire(&src, name, &setup)? {
+        print(&format!(
+            "{} Writing {}",
+            "\u{270E}".blue(),
+            configure_path.display()
+        ));
+        files::write(&configure_path, src)?;
+    }
+    Ok(())
+}
+
+/// `server/src/configure.rs` for `server/src/api/router`
+pub fn configure_path(router_path: &str) -> PathBuf {
+    Path::new(router_path)
+        .ancestors()
+        .nth(2)
+        .unwrap_or(Path::new("."))
+        .join("configure.rs")
+}
+
+/// The contracts from alx.toml, for when none are given
+fn default_contracts(ty: BoilerType) -> Vec<&'static str> {
+    let contracts = &settings().generator(ty).contracts;
+    contracts.iter().map(String::as_str).collect()
+}
+
+fn module_visibility(ty: BoilerType) -> &'static str {
+    &settings().generator(ty).module_visibility
+}
+
+/// The contents of the route or middleware files, the ones set in alx.toml if any. Files with a template in `.alx/templates` are
+/// rendered from it, the rest get the built-in boilerplate.
+fn boiler_files(
+    name: &str,
+    contracts: &[&str],
+    ty: BoilerType,
+) -> Result<Vec<(&'static str, String)>, AlxError> {
+    let service_name = uppercase(name);
+    let kind = match ty {
+        BoilerType::Route => "route",
+        BoilerType::MW => "middleware",
+    };
+    let files = settings()
+        .generator(ty)
+        .files
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let templates = Templates::load(settings().root(), kind, &files)?;
+    let ctx = template::context(name, &service_name, contracts, ty);
+
+    let mut contents = vec![];
+    for file in files {
+        if let Some(rendered) = templates.render(file, &ctx) {
+            print(&format!("Rendering {file}.rs from its template"));
+            contents.push((file, rendered?));
+            continue;
+        }
+        let mut buf = String::new();
         match file {
-            "contract" => boiler::plate::contracts(&mut contents, &contracts, BoilerType::Route),
-            "domain" => boiler::plate::domain(&mut contents, &service_name, &contracts),
+            "contract" => boiler::plate::contracts(&mut buf, contracts, ty),
+            "domain" => boiler::plate::domain(&mut buf, &service_name, contracts),
             "infrastructure" if !contracts.is_empty() => {
-                boiler::plate::infrastructure(&mut contents, &contracts)
+                boiler::plate::infrastructure(&mut buf, contracts)
             }
-            "setup" => boiler::plate::setup(&mut contents, &service_name, &contracts),
-            "mod" => boiler::plate::r#mod(&mut contents, BoilerType::Route),
+            "setup" => boiler::plate::setup(&mut buf, &service_name, contracts),
+            "interceptor" => boiler::plate::mw_interceptor(&mut buf, &service_name, contracts),
+            "mod" => boiler::plate::r#mod(&mut buf, ty),
             _ => {}
         }
-        fs::write(&format!("{}/{}.rs", ep_path, file), contents.clone())
-            .expect("Could't write to file");
-        contents.clear();
+        contents.push((file, buf));
     }
-    print(&format!(
-        "{}{}",
-        "Successfully wrote route ".green(),
+    Ok(contents)
+}
+
+fn write_boiler_files(ep_path: &str, files: Vec<(&str, String)>) -> Result<(), AlxError> {
+    for (file, contents) in files {
+        print(&format!("{} Writing {}.rs", "\u{270E}".blue(), file));
+        files::write(format!("{ep_path}/{file}.rs"), contents)?;
+    }
+    Ok(())
+}
+
+/// Create or update the routes declared in the spec. New endpoints get the usual route boilerplate,
+/// after which the data structs, handlers and registrations missing from the files are added.
+/// Existing code, hand-written or not, is left alone.
+pub fn handle_gen_from_spec(args: SpecArgs, router_path: &str) -> Result<(), AlxError> {
+    print(&format!("Reading {}", args.spec));
+    let spec = ProjectConfig::read_lock(&args.spec)?;
+
+    for ep in spec.endpoints.iter() {
+        let ep_path = format!("{}/{}", router_path, ep.name);
+        let service_name = uppercase(&ep.name);
+        let contracts = ep.contracts.clone().unwrap_or_default();
+
+        if !Path::new(&ep_path).exists() {
+            let contracts = contracts.iter().map(String::as_str).collect::<Vec<_>>();
+            let files = boiler_files(&ep.name, &contracts, BoilerType::Route)?;
+            print(&format!("{} Creating {}", "\u{270E}".blue(), ep_path));
+            files::create_dir_all(&ep_path)?;
+            write_to_mod_file(
+                &format!("{}/mod.rs", router_path),
+                module_visibility(BoilerType::Route),
+                &ep.name,
+            );
+            write_boiler_files(&ep_path, files)?;
+            if let Err(e) = wire_route(router_path, &ep.name) {
+                println!(
+                    "{}{}",
+                    "Unable to register the route in configure.rs: ".yellow(),
+                    e
+                );
+            }
+            println!("{}{}", "Created ".green(), ep_path);
+        }
+
+        let routes = ep.routes.iter().collect::<Vec<_>>();
+        let data = spec::route_inputs(&routes);
+
+        // Nothing is written unless every file could be edited
+        let read =
+            |file: &str| files::read_to_string(format!("{ep_path}/{file}.rs")).unwrap_or_default();
+        let edited = [
+            ("data", spec::update_data(&read("data"), &data)?),
+            (
+                "handler",
+                spec::update_handlers(&read("handler"), &routes, &data, false)?,
+            ),
+            (
+                "setup",
+                spec::update_setup(&read("setup"), &service_name, &contracts, &routes)?,
+            ),
+        ];
+        let mut updated = vec![];
+        for (file, contents) in edited {
+            if let Some(contents) = contents {
+                let file_path = format!("{ep_path}/{file}.rs");
+                print(&format!("{} Writing {}", "\u{270E}".blue(), file_path));
+                files::write(&file_path, contents)?;
+                updated.push(file);
+            }
+        }
+
+        if updated.is_empty() {
+            println!("{} is up to date", ep.name);
+        } else {
+            println!("{}{} ({})", "Updated ".green(), ep.name, updated.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Add a handler to an existing route. The handler validates its request data and passes it to a new
+/// `ServiceContract` method, which is left to `todo!()` in the domain. The resource is registered in
+/// setup.rs. Nothing is written unless every file could be edited.
+///
+/// The route files already exist and may have been edited by hand, so the `plate` writers, which write
+/// whole files, don't apply. The files are edited with the same functions `gen from-spec` uses, which
+/// only add what's missing.
+pub fn handle_gen_handler(args: HandlerArgs, router_path: &str) -> Result<(), AlxError> {
+    let ep_path = format!("{}/{}", router_path, args.route);
+    if !Path::new(&ep_path).exists() {
+        return Err(AlxError::Edit(format!(
+            "route {ep_path} doesn't exist, generate it first"
+        )));
+    }
+
+    let method = args.method.to_uppercase();
+    if !HTTP_METHODS.contains(&method.as_str()) {
+        return Err(AlxError::Edit(format!(
+            "unsupported HTTP method {}",
+            args.method
+        )));
+    }
+    let ext = match args.extractor.as_deref() {
+        Some(ext) => ext.to_lowercase(),
+        None if matches!(method.as_str(), "POST" | "PUT" | "PATCH") => "json".to_string(),
+        None => "query".to_string(),
+    };
+    let ext = match ext.as_str() {
+        "json" | "query" | "path" | "form" => Some(uppercase(&ext)),
+        "none" => None,
+        _ => return Err(AlxError::Edit(format!("unsupported extractor {ext}"))),
+    };
+
+    let mut fields = vec![];
+    for field in args.fields.iter().flat_map(|f| f.split(',')) {
+        let Some((name, ty)) = field.split_once(':') else {
+            return Err(AlxError::Edit(format!(
+                "fields are written as name:Type, got {field}"
+            )));
+        };
+        fields.push(Field {
+            name: name.trim().to_string(),
+            ty: ty.trim().to_string(),
+            required: true,
+            validation: vec![],
+            ..Default::default()
+        });
+    }
+    let data = Data {
+        id: pascal_case(&args.name),
+        fields,
+    };
+
+    let mut inputs = vec![];
+    if let Some(ref ext) = ext {
+        inputs.push(HandlerInput {
+            ext_type: ext.clone(),
+            data_type: data.id.clone(),
+        });
+    }
+    inputs.push(HandlerInput {
+        ext_type: "Data".to_string(),
+        data_type: "T".to_string(),
+    });
+    let route = RouteHandler {
+        method,
+        path: args
+            .url
+            .clone()
+            .unwrap_or_else(|| format!("/{}", args.route)),
+        handler: Some(Handler {
+            name: args.name.clone(),
+            inputs,
+            bound: Some("T: ServiceContract".to_string()),
+            ..Default::default()
+        }),
+        middleware: args
+            .wrap
+            .as_ref()
+            .map(|w| w.split(',').map(|m| m.trim().to_string()).collect()),
+        guards: None,
+        service: None,
+        input: ext.as_ref().map(|_| data.clone()),
+    };
+    let method = ServiceMethod {
+        name: args.name.clone(),
+        input: ext.as_ref().map(|_| data.id.clone()),
+    };
+
+    let read = |file: &str| files::read_to_string(format!("{}/{}.rs", ep_path, file));
+    let domain = read("domain")?;
+    let domain_file = syn::parse_file(&domain)?;
+    let Some(service_name) = boiler::contract::service_name(&domain_file) else {
+        return Err(AlxError::Edit(
+            "no ServiceContract implementation found in domain.rs".to_string(),
+        ));
+    };
+    let contracts = service::service_fields(&domain_file);
+
+    let edited = [
+        (
+            "data",
+            match ext {
+                Some(_) => spec::update_data(&read("data")?, &[&data])?,
+                None => None,
+            },
+        ),
+        (
+            "handler",
+            spec::update_handlers(&read("handler")?, &[&route], &[&data], true)?,
+        ),
+        (
+            "contract",
+            service::contract_method(&read("contract")?, &method)?,
+        ),
+        ("domain", service::domain_method(&domain, &method)?),
+        (
+            "setup",
+            spec::update_setup(&read("setup")?, &service_name, &contracts, &[&route])?,
+        ),
+    ];
+
+    let mut written = 0;
+    for (file, contents) in edited {
+        if let Some(contents) = contents {
+            print(&format!("{} Writing {}.rs", "\u{270E}".blue(), file));
+            files::write(format!("{}/{}.rs", ep_path, file), contents)?;
+            written += 1;
+        }
+    }
+
+    if written == 0 {
+        println!("{} already has {}", args.route, args.name);
+    } else {
+        println!(
+            "{}{} to {}",
+            "Successfully added ".green(),
+            args.name,
+            ep_path
+        );
+    }
+    Ok(())
+}
+
+/// Generate a test per `ServiceContract` method in the route's mod.rs. The contracts get mocked with
+/// mockall, which gets enabled on the ones in contract.rs that aren't mocked yet.
+pub fn handle_gen_tests(args: TestsArgs, router_path: &str) -> Result<(), AlxError> {
+    let ep_path = format!("{}/{}", router_path, args.route);
+    if !Path::new(&ep_path).exists() {
+        return Err(AlxError::Edit(format!(
+            "route {ep_path} doesn't exist, generate it first"
+        )));
+    }
+
+    let read = |file: &str| files::read_to_string(format!("{}/{}.rs", ep_path, file));
+    let contract = read("contract")?;
+    let Some((tests, names)) = mock::tests_module(
+        &read("mod")?,
+        &contract,
+        &read("domain")?,
+        &read("data").unwrap_or_default(),
+    )?
+    else {
+        println!("{} has no service methods without a test", args.route);
+        return Ok(());
+    };
+
+    if let Some(contract) = mock::automock(&contract)? {
+        print(&format!("{} Writing contract.rs", "\u{270E}".blue()));
+        files::write(format!("{}/contract.rs", ep_path), contract)?;
+    }
+    print(&format!("{} Writing mod.rs", "\u{270E}".blue()));
+    files::write(format!("{}/mod.rs", ep_path), tests)?;
+    println!(
+        "{}{} to {}/mod.rs",
+        "Successfully added tests for ".green(),
+        names.join(", "),
         ep_path
-    ))
+    );
+    Ok(())
+}
+
+/// Generate an `integration.rs` test module per endpoint in the router from the analyzed routes and
+/// declare it in the endpoint's mod.rs. The module is regenerated as a whole every time.
+pub fn handle_gen_integration(args: IntegrationArgs, api_path: &str) -> Result<(), AlxError> {
+    let pc = project_config(api_path)?;
+    let endpoints = pc
+        .endpoints
+        .iter()
+        .filter(|ep| args.route.as_ref().is_none_or(|r| *r == ep.name))
+        .collect::<Vec<_>>();
+    if endpoints.is_empty() {
+        println!(
+            "{}{}",
+            "No routes found in ".red(),
+            args.route.as_deref().unwrap_or(api_path)
+        );
+        return Ok(());
+    }
+
+    let guards = Guards::of(&pc)?;
+    for ep in endpoints {
+        let ep_path = format!("{}/{}", settings().paths.router_of(api_path), ep.name);
+        if !Path::new(&ep_path).is_dir() {
+            print(&format!("Skipping {}, it isn't a directory", ep.name));
+            continue;
+        }
+        let tests = integration::tests(ep, &guards);
+
+        for skipped in tests.skipped.iter() {
+            println!("{}{}", "Skipping ".yellow(), skipped);
+        }
+        if tests.tests.is_empty() {
+            println!("{} has no validated or guarded routes to test", ep.name);
+            continue;
+        }
+
+        let file = format!("{ep_path}/integration.rs");
+        print(&format!("{} Writing {}", "\u{270E}".blue(), file));
+        if !files::create(&file, &tests.contents)? {
+            continue;
+        }
+        let mod_file = format!("{ep_path}/mod.rs");
+        if let Some(src) = edit::add_test_mod(&files::read_to_string(&mod_file)?, "integration")? {
+            print(&format!("{} Writing {}", "\u{270E}".blue(), mod_file));
+            files::write(&mod_file, src)?;
+        }
+        println!(
+            "{}{} to {}",
+            "Successfully wrote ".green(),
+            tests.tests.join(", "),
+            file
+        );
+    }
+    Ok(())
+}
+
+/// Generate a repository trait and model, a Postgres adapter implementing it, the table in schema.rs and
+/// a migration creating it
+pub fn handle_gen_repository(args: RepositoryArgs, store_path: &str) -> Result<(), AlxError> {
+    let valid_name = |n: &str| {
+        n.starts_with(|c: char| c.is_ascii_lowercase())
+            && n.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    };
+    if !valid_name(&args.name) {
+        return Err(AlxError::Edit(format!(
+            "names are written in snake case, got {}",
+            args.name
+        )));
+    }
+
+    let mut columns = vec![];
+    for field in args.fields.split(',') {
+        let Some((name, ty)) = field.split_once(':') else {
+            return Err(AlxError::Edit(format!(
+                "fields are written as name:Type, got {field}"
+            )));
+        };
+        let name = name.trim();
+        if !valid_name(name) || Column::is_automatic(name) {
+            return Err(AlxError::Edit(format!("invalid column name {name}")));
+        }
+        let Some(column) = Column::new(name, ty) else {
+            return Err(AlxError::Edit(format!("unsupported column type {ty}")));
+        };
+        columns.push(column);
+    }
+    let entity = Entity {
+        model: pascal_case(&args.name),
+        table: args
+            .table
+            .clone()
+            .unwrap_or_else(|| repository::plural(&args.name)),
+        name: args.name,
+        columns,
+    };
+
+    let repo_path = format!("{}/repository/{}.rs", store_path, entity.name);
+    let pg_path = format!("{}/adapters/postgres", store_path);
+    let adapter_path = format!("{}/{}.rs", pg_path, entity.name);
+    for file in [&repo_path, &adapter_path] {
+        if Path::new(file).exists() {
+            return Err(AlxError::Edit(format!("{file} already exists")));
+        }
+    }
+
+    // Edit the existing files before writing anything so nothing is written if they can't be edited
+    let read = |file: &str| files::read_to_string(file);
+    let repo_mod = format!("{}/repository/mod.rs", store_path);
+    let pg_mod = format!("{}/mod.rs", pg_path);
+    let schema = format!("{}/schema.rs", pg_path);
+    let edited = [
+        (
+            &repo_mod,
+            edit::add_mod(&read(&repo_mod)?, "pub", &entity.name)?,
+        ),
+        (
+            &pg_mod,
+            edit::add_mod(&read(&pg_mod)?, "pub", &entity.name)?,
+        ),
+        (
+            &schema,
+            repository::update_schema(&read(&schema)?, &entity)?,
+        ),
+    ];
+
+    let mut contents = String::new();
+    repository::repository(&mut contents, &entity);
+    print(&format!("{} Writing {}", "\u{270E}".blue(), repo_path));
+    files::write(&repo_path, &contents)?;
+
+    contents.clear();
+    repository::adapter(&mut contents, &entity);
+    print(&format!("{} Writing {}", "\u{270E}".blue(), adapter_path));
+    files::write(&adapter_path, &contents)?;
+
+    for (file, contents) in edited {
+        if let Some(contents) = contents {
+            print(&format!("{} Writing {}", "\u{270E}".blue(), file));
+            files::write(file, contents)?;
+        }
+    }
+
+    contents.clear();
+    repository::migration_up(&mut contents, &entity);
+    let mut down = String::new();
+    repository::migration_down(&mut down, &entity);
+    let migration = migration::create(
+        &format!("{pg_path}/migrations"),
+        &format!("create_{}", entity.table),
+        &contents,
+        &down,
+    )?;
+    print(&format!("{} Writing {}", "\u{270E}".blue(), migration));
+
+    println!(
+        "{}{}Repository{}alx migration run",
+        "Successfully wrote ".green(),
+        entity.model,
+        ", run the migration with ".green(),
+    );
+    Ok(())
 }
 
 /// Generate middleware boilerplate
-pub fn handle_gen_mw(args: GenerateArgs, mw_path: &str) {
+pub fn handle_gen_mw(args: GenerateArgs, mw_path: &str) -> Result<(), AlxError> {
     let mut ep_path = format!("{}/{}", mw_path, args.name);
     // If a path is given switch to it
     if let Some(ref p) = args.path {
@@ -105,45 +754,54 @@ pub fn handle_gen_mw(args: GenerateArgs, mw_path: &str) {
     }
     let ep_path = &ep_path;
 
-    let service_name = uppercase(&args.name);
-
     // Gather up contracts if any
     let contracts = match args.contracts {
         Some(ref c) => c.split(',').collect::<Vec<&str>>(),
-        None => vec![],
+        None => default_contracts(BoilerType::MW),
     };
 
+    let files = boiler_files(&args.name, &contracts, BoilerType::MW)?;
+
     // Try to create the directory and prompt for overwrite if it exists
     if !handle_create_dir(ep_path) {
-        return;
+        return Ok(());
     }
 
     // Append the mod clause to the existing router.mod file
     let mw_mod = format!("{}/mod.rs", mw_path);
-    write_to_mod_file(&mw_mod, &args.name);
+    write_to_mod_file(&mw_mod, module_visibility(BoilerType::MW), &args.name);
 
-    for file in MW_FILES {
-        print(&format!("{} Writing {}.rs", "\u{270E}".blue(), file));
-        let mut contents = String::new();
-        match file {
-            "contract" => boiler::plate::contracts(&mut contents, &contracts, BoilerType::MW),
-            "domain" => boiler::plate::domain(&mut contents, &service_name, &contracts),
-            "infrastructure" if !contracts.is_empty() => {
-                boiler::plate::infrastructure(&mut contents, &contracts)
-            }
-            "interceptor" => {
-                boiler::plate::mw_interceptor(&mut contents, &service_name, &contracts)
-            }
-            "mod" => boiler::plate::r#mod(&mut contents, BoilerType::MW),
-            _ => {}
-        }
-        fs::write(&format!("{}/{}.rs", ep_path, file), contents.clone())
-            .expect("Could't write to file");
-        contents.clear();
-    }
+    write_boiler_files(ep_path, files)?;
     print(&format!(
         "{}{}",
         "Successfully wrote middleware ".green(),
         ep_path
-    ))
+    ));
+    Ok(())
+}
+
+/// Generate a typed API client from the lock file or the analyzed API
+pub fn handle_gen_client(args: ClientArgs, api_path: &str) -> Result<(), AlxError> {
+    let lang = args.lang;
+    let pc = match args.lock {
+        Some(ref lock) => {
+            print(&format!("Reading {lock}"));
+            ProjectConfig::read_lock(lock)?
+        }
+        None => project_config(api_path)?,
+    };
+
+    let mut contents = String::new();
+    match lang {
+        ClientLang::TypeScript => boiler::client::typescript(&mut contents, &pc, &Guards::of(&pc)?),
+    }
+
+    files::create_dir_all(&args.out)?;
+    let file = format!("{}/{}", args.out, lang.file_name());
+    print(&format!("{} Writing {}", "\u{270E}".blue(), file));
+    if !files::create(&file, contents)? {
+        return Ok(());
+    }
+    println!("{}{}", "Successfully wrote client ".green(), file);
+    Ok(())
 }
@@ -0,0 +1,45 @@
+//! Visualise how the routes of the API end up at the clients
+use crate::{
+    analyzer::graph::{graph, GraphFormat},
+    error::AlxError,
+    print,
+};
+use clap::Args;
+use colored::Colorize;
+use std::fs;
+
+/// Generate a dependency graph of the routes, their handlers and middleware, the services and
+/// contracts behind them and the infrastructure, adapters and clients implementing those
+#[derive(Debug, Args)]
+pub struct GraphOptions {
+    /// The format of the graph
+    #[arg(short, long, value_enum, default_value = "dot")]
+    pub format: GraphFormat,
+    /// Where to write the graph. Printed to stdout if not given.
+    #[arg(short, long)]
+    pub out: Option<String>,
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+pub fn handle(opts: GraphOptions, api_path: &str) -> Result<(), AlxError> {
+    let graph = graph(api_path)?;
+    print(&format!(
+        "Found {} nodes and {} edges",
+        graph.nodes.len(),
+        graph.edges.len()
+    ));
+    let contents = graph.render(opts.format);
+    match opts.out {
+        Some(out) => {
+            fs::write(&out, contents)?;
+            println!("{}{}", "Successfully wrote ".green(), out);
+        }
+        None => print!("{contents}"),
+    }
+    Ok(())
+}
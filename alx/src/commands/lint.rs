@@ -0,0 +1,58 @@
+//! Check the route files keep to the layers of the ports and adapters architecture
+use crate::{
+    analyzer::lint::{config, lint, Rule},
+    error::AlxError,
+    print,
+    settings::settings,
+};
+use clap::Args;
+use colored::Colorize;
+
+/// The lint config enabling rules and overriding their forbidden paths
+pub const DEFAULT_LINT_CONFIG: &str = ".alx/lint.yaml";
+
+/// Check domain.rs doesn't depend on clients or adapters, handler.rs only calls ServiceContract
+/// methods, contract.rs doesn't name concrete adapters and infrastructure.rs doesn't return
+/// responses. Exits with a non-zero code if a rule is violated.
+#[derive(Debug, Args)]
+pub struct LintOptions {
+    /// The lint config, .alx/lint.yaml next to alx.toml by default
+    #[arg(short, long)]
+    pub config: Option<String>,
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Returns true if a rule is violated
+pub fn handle(opts: LintOptions, api_path: &str) -> Result<bool, AlxError> {
+    let config_path = match opts.config {
+        Some(ref c) => c.to_string(),
+        None => settings().project_path(DEFAULT_LINT_CONFIG),
+    };
+    let config = config(&config_path)?;
+    print(&format!("Linting {api_path} with {config_path}"));
+    let findings = lint(api_path, &config)?;
+    if findings.is_empty() {
+        println!("{}", "No layering violations found".green());
+        return Ok(false);
+    }
+    for finding in findings.iter() {
+        println!("  {} {}", "\u{2717}".red(), finding);
+    }
+    let counts = Rule::ALL
+        .iter()
+        .map(|r| (r, findings.iter().filter(|f| f.rule == *r).count()))
+        .filter(|(_, n)| *n > 0)
+        .map(|(r, n)| format!("{} {}", r.id(), n))
+        .collect::<Vec<_>>();
+    println!(
+        "{} ({})",
+        "Layering violations".red().bold(),
+        counts.join(", ")
+    );
+    Ok(true)
+}
@@ -1,4 +1,17 @@
 pub mod alx;
+pub mod audit;
+pub mod diff;
+pub mod doctor;
+pub mod env;
 pub mod envex;
+pub mod export;
 pub mod generate;
+pub mod graph;
+pub mod lint;
 pub mod migration;
+pub mod new;
+pub mod openapi;
+pub mod remove;
+pub mod route;
+pub mod schema;
+pub mod secrets;
@@ -0,0 +1,174 @@
+//! Bootstrap a project from the starter workspace with the components it needs
+use super::{
+    env::{example, unused},
+    secrets::{PRIVATE_KEY, PUBLIC_KEY},
+};
+use crate::{
+    analyzer::env::{declared, scan},
+    boiler::{
+        files::{dry_run, handle_create_dir, WriteArgs},
+        project::{copy, drop_empty_sections, select, strip, Component, COPIED, MEMBERS},
+    },
+    error::AlxError,
+    print,
+    secrets::{certificates, key_pair, secret, set_var, KeyAlgorithm, SecretEncoding},
+};
+use clap::Args;
+use colored::Colorize;
+use std::{fs, path::Path};
+
+const GITIGNORE: &str = "target/\nCargo.lock\n.env\nencryption/\nopenssl/*.pem\n";
+
+/// Bytes of the generated `_SECRET` variables, enough for the cookie key
+const SECRET_LENGTH: usize = 64;
+const KEY_PAIR_BITS: u32 = 2048;
+/// As in openssl/openssl.sh
+const CERT_BITS: u32 = 4096;
+
+/// Lay out a workspace with the infrastructure and server members and only the clients, adapters,
+/// routes and env variables of the selected components. Auth is included unless left out, along with
+/// the redis and email components it requires. Generates the secrets of the .env, the RSA key pair
+/// in encryption/key_pair and the certificates in openssl.
+#[derive(Debug, Args)]
+pub struct NewOptions {
+    /// The directory to create the project in
+    pub name: String,
+    /// Components to include
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub with: Vec<Component>,
+    /// Components to leave out
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub without: Vec<Component>,
+    /// The starter workspace to copy the project from, i.e. a checkout of the alx repository
+    #[arg(long)]
+    pub from: String,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Keys and certificates are never diffed, so a dry run only prints what would be included
+pub fn handle(opts: NewOptions) -> Result<(), AlxError> {
+    let selected = select(&opts.with, &opts.without)?;
+    let removed = Component::ALL
+        .into_iter()
+        .filter(|c| !selected.contains(c))
+        .collect::<Vec<_>>();
+    let list = |cs: &[Component]| match cs.is_empty() {
+        true => "no optional components".to_string(),
+        false => cs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    let from = Path::new(&opts.from);
+    if let Some(missing) = COPIED.iter().find(|d| !from.join(d).is_dir()) {
+        return Err(AlxError::Project(format!(
+            "{} has no {missing} directory, point --from to the starter workspace",
+            opts.from
+        )));
+    }
+    if dry_run() {
+        println!("Would create {} with {}", opts.name, list(&selected));
+        if !removed.is_empty() {
+            println!("Would leave out {}", list(&removed));
+        }
+        return Ok(());
+    }
+    if !handle_create_dir(&opts.name) {
+        return Ok(());
+    }
+
+    let root = Path::new(&opts.name);
+    for dir in COPIED {
+        print(&format!("{} Copying {}", "\u{270E}".blue(), dir));
+        copy(&from.join(dir), &root.join(dir))?;
+    }
+    strip(root, &removed)?;
+
+    let members = MEMBERS.map(|m| format!("\"{m}\"")).join(", ");
+    write(
+        root,
+        "Cargo.toml",
+        format!("[workspace]\n\nmembers = [{members}]\n"),
+    )?;
+    write(root, ".gitignore", GITIGNORE)?;
+    env_files(from, root)?;
+
+    let dir = root
+        .join("encryption")
+        .join(KeyAlgorithm::Rsa.default_dir());
+    fs::create_dir_all(&dir)?;
+    let (private, public) = key_pair(KeyAlgorithm::Rsa, KEY_PAIR_BITS)?;
+    write(&dir, PRIVATE_KEY, private)?;
+    write(&dir, PUBLIC_KEY, public)?;
+
+    let certs = certificates(CERT_BITS)?;
+    let dir = root.join("openssl");
+    for (file, pem) in [
+        ("ca-key.pem", certs.ca_key),
+        ("ca.pem", certs.ca),
+        ("key.pem", certs.key),
+        ("cert.pem", certs.cert),
+    ] {
+        write(&dir, file, pem)?;
+    }
+
+    println!(
+        "{}{} with {}",
+        "Successfully created ".green(),
+        opts.name,
+        list(&selected)
+    );
+    if !removed.is_empty() {
+        println!(
+            "Run `cargo fmt` in {} to tidy up the pruned imports",
+            opts.name
+        );
+    }
+    Ok(())
+}
+
+/// Writes the .env.example with the variables the project reads, taking the layout of the starter's,
+/// and a .env with the same variables, defaults filled in and fresh secrets
+fn env_files(from: &Path, root: &Path) -> Result<(), AlxError> {
+    let vars = scan(root);
+    let template = fs::read_to_string(from.join(".env.example")).unwrap_or_default();
+    let unused = unused(&vars, &declared(&template));
+    let template = template
+        .lines()
+        .filter(|l| {
+            let key = l.split_once('=').map(|(k, _)| k.trim().to_string());
+            l.trim_start().starts_with('#') || key.is_none_or(|k| !unused.contains(&k))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let template = drop_empty_sections(&template);
+    write(root, ".env.example", example(&template, &vars))?;
+
+    let mut env = template;
+    for var in vars.iter() {
+        let value = match var.key.ends_with("_SECRET") {
+            true => Some(secret(SECRET_LENGTH, SecretEncoding::Hex)?),
+            false => var.default.clone(),
+        };
+        let is_declared = declared(&env).iter().any(|(k, _)| *k == var.key);
+        match value {
+            Some(value) => env = set_var(&env, &var.key, &value),
+            None if !is_declared => env = set_var(&env, &var.key, ""),
+            None => {}
+        }
+    }
+    write(root, ".env", env)
+}
+
+fn write(dir: &Path, file: &str, contents: impl AsRef<[u8]>) -> Result<(), AlxError> {
+    let path = dir.join(file);
+    print(&format!("{} Writing {}", "\u{270E}".blue(), path.display()));
+    fs::write(path, contents)?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Generate an OpenAPI document from the analyzed router directory
+use crate::{
+    analyzer::{analyze::project_config, audit::Guards},
+    error::AlxError,
+    export::openapi::{document, OpenApiInfo},
+    print,
+    validation::resolve_regexes,
+};
+use clap::Args;
+use colored::Colorize;
+use std::{fs, path::Path};
+
+/// Generate an OpenAPI 3.1 document describing all the routes found by the analyzer
+#[derive(Debug, Args)]
+pub struct OpenApiOptions {
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Where to write the document. Written as Yaml if the extension is `.yaml` or `.yml`.
+    #[arg(short, long, default_value = "./openapi.json")]
+    pub out: String,
+    /// The title of the API
+    #[arg(short, long, default_value = "API")]
+    pub title: String,
+    /// The version of the API
+    #[arg(long, default_value = "0.1.0")]
+    pub api_version: String,
+    /// Server URLs, comma seperated. e.g. `-s https://localhost:8080`
+    #[arg(short, long)]
+    pub servers: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+pub fn handle(opts: OpenApiOptions, api_path: &str) -> Result<(), AlxError> {
+    let pc = project_config(api_path)?;
+
+    // Regexes are declared somewhere in the crate the API is in
+    let crate_src = Path::new(api_path).parent().unwrap_or(Path::new(api_path));
+    let regexes = resolve_regexes(crate_src);
+    print(&format!("Resolved {} regex statics", regexes.len()));
+
+    let info = OpenApiInfo {
+        title: opts.title,
+        version: opts.api_version,
+        servers: match opts.servers {
+            Some(s) => s.split(',').map(|s| s.trim().to_string()).collect(),
+            None => vec![],
+        },
+    };
+    let guards = Guards::of(&pc)?;
+    let doc = document(&pc, &info, &regexes, &guards);
+
+    let contents = if opts.out.ends_with(".yaml") || opts.out.ends_with(".yml") {
+        serde_yaml::to_string(&doc)?
+    } else {
+        serde_json::to_string_pretty(&doc)?
+    };
+    fs::write(&opts.out, contents)?;
+    println!("{}{}", "Successfully wrote ".green(), opts.out);
+    Ok(())
+}
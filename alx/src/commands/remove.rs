@@ -0,0 +1,108 @@
+//! Remove generated components and undo the edits made when generating them
+use crate::{
+    boiler::{configure, edit, files},
+    commands::generate::configure_path,
+    error::AlxError,
+    print,
+};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::{fs, path::Path};
+
+/// Remove a generated component
+#[derive(Debug, Args)]
+pub struct RemoveCommand {
+    #[clap(subcommand)]
+    pub subject: RemoveSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RemoveSubcommand {
+    /// Remove the call to the route's setup from configure.rs, its declaration from the router
+    /// and delete its directory
+    Route(RemoveRouteArgs),
+}
+
+/// Remove route arguments
+#[derive(Debug, Args)]
+pub struct RemoveRouteArgs {
+    /// The name of the route to remove
+    pub name: String,
+    /// Delete the route without asking for confirmation
+    #[arg(short, long, action)]
+    pub yes: bool,
+    /// Specify the path to the router directory.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+pub fn handle_remove_route(args: RemoveRouteArgs, router_path: &str) -> Result<(), AlxError> {
+    let dir = Path::new(router_path).join(&args.name);
+    let file = Path::new(router_path).join(format!("{}.rs", args.name));
+    let target = match (dir.is_dir(), file.is_file()) {
+        (true, _) => Some(dir),
+        (_, true) => Some(file),
+        _ => None,
+    };
+
+    if let Some(ref target) = target {
+        if !args.yes && !confirm(&target.display().to_string()) {
+            println!("Aborting");
+            return Ok(());
+        }
+    }
+
+    let configure_path = configure_path(router_path);
+    match fs::read_to_string(&configure_path) {
+        Ok(src) => {
+            if let Some(src) = configure::unwire(&src, &args.name)? {
+                print(&format!(
+                    "{} Writing {}",
+                    "\u{270E}".blue(),
+                    configure_path.display()
+                ));
+                fs::write(&configure_path, src)?;
+            }
+        }
+        Err(_) => println!(
+            "{}{}",
+            "Skipping missing file ".yellow(),
+            configure_path.display()
+        ),
+    }
+
+    let router_mod = format!("{router_path}/mod.rs");
+    if let Some(src) = edit::remove_mod(&fs::read_to_string(&router_mod)?, &args.name)? {
+        print(&format!("{} Writing {}", "\u{270E}".blue(), router_mod));
+        fs::write(&router_mod, src)?;
+    }
+
+    match target {
+        Some(target) => {
+            print(&format!(
+                "{} Deleting {}",
+                "\u{2717}".red(),
+                target.display()
+            ));
+            match target.is_dir() {
+                true => fs::remove_dir_all(&target)?,
+                false => fs::remove_file(&target)?,
+            }
+            println!("{}{}", "Successfully removed ".green(), target.display());
+        }
+        None => println!("{}{}", "No files found for route ".yellow(), args.name),
+    }
+    Ok(())
+}
+
+fn confirm(path: &str) -> bool {
+    println!(
+        "\u{26A0} {} \u{26A0}\nThis will delete {}",
+        "WARNING".red(),
+        path
+    );
+    files::confirm()
+}
@@ -1,15 +1,14 @@
-use std::fs;
-
-use clap::{Args, Subcommand};
-use colored::Colorize;
-
 use crate::{
     boiler::{
-        self,
-        files::{handle_create_dir, write_to_mod_file},
+        contract::{self, Contract},
+        files::{self, WriteArgs},
     },
-    uppercase, DEFAULT_PATH, FILES,
+    commands::generate::GenerateArgs,
+    error::AlxError,
+    print,
 };
+use clap::{Args, Subcommand};
+use colored::Colorize;
 
 /// Router commands
 #[derive(Debug, Args)]
@@ -21,75 +20,103 @@ pub(crate) struct RouteCommand {
 #[derive(Debug, Subcommand)]
 pub(crate) enum RouteSubcommand {
     /// Generate a route.
-    Gen(RouteArgs),
+    Gen(GenerateArgs),
     /// Shorthand for generate.
-    G(RouteArgs),
+    G(GenerateArgs),
     /// Add a contract to an existing route endpoint.
-    AddContract(RouteName),
+    AddContract(ContractArgs),
     /// Shorthand for add contract.
-    AC(RouteName),
+    AC(ContractArgs),
 }
 
-/// Contains endpoint information
+/// Contract arguments
 #[derive(Debug, Args)]
-pub(crate) struct RouteArgs {
-    /// The name of the router endpoint.
+pub(crate) struct ContractArgs {
+    /// The name of a router endpoint
     pub name: String,
+    /// The name of the contract in snake case, e.g. `email_client` for an `EmailClientContract`
+    #[arg(value_parser = contract_name)]
+    pub contract: String,
+    /// The path to the router directory. Defaults to ./server/src/api/router
     #[arg(short, long)]
-    /// The various services or repositories the endpoint will use. Comma seperated.
-    pub contracts: Option<String>,
-    #[arg(short, long)]
-    /// The path to the API you wish to generate this endpoint. Defaults to ./server/api/router
     pub path: Option<String>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
 }
 
-#[derive(Debug, Args)]
-pub(crate) struct RouteName {
-    /// The name of a router endpoint
-    pub name: String,
-}
-
-pub(crate) fn handle_gen_route(args: RouteArgs) {
-    let mut path = format!("{}/{}", DEFAULT_PATH, args.name);
-    // If a path is given switch to it
-    if let Some(ref p) = args.path {
-        path = format!("{}/{}", p, args.name)
+/// Contract names become the service field and, in pascal case, the adapter and trait names
+fn contract_name(name: &str) -> Result<String, String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    match valid {
+        true => Ok(name.to_string()),
+        false => Err("names are written in snake case, i.e. email_client".to_string()),
     }
-    let path = &path;
-
-    let service_name = uppercase(&args.name);
+}
 
-    let router_mod_path = format!("{}/mod.rs", DEFAULT_PATH);
+/// Adds a contract trait to contract.rs, an adapter implementing it to infrastructure.rs, a field bound
+/// by it to the service in domain.rs and constructs the adapter in setup.rs. If the contracts are mocked
+/// the tests in mod.rs get the mock. All the files are edited in place, nothing is written unless every
+/// file could be edited.
+pub(crate) fn handle_add_contract(args: ContractArgs, router_path: &str) -> Result<(), AlxError> {
+    let ep_path = format!("{}/{}", router_path, args.name);
+    let contract = Contract::new(&args.contract);
+    let read = |file: &str| files::read_to_string(format!("{}/{}.rs", ep_path, file));
 
-    // Gather up contracts if any
-    let contracts = match args.contracts {
-        Some(ref c) => c.split(',').collect::<Vec<&str>>(),
-        None => vec![],
-    };
+    let domain = read("domain")?;
+    let service = contract::service_name(&syn::parse_file(&domain)?).ok_or_else(|| {
+        AlxError::Edit("no ServiceContract implementation found in domain.rs".to_string())
+    })?;
 
-    // Try to create the directory and prompt for overwrite if it exists
-    if !handle_create_dir(path) {
-        return;
-    }
+    let contracts = read("contract")?;
+    let mocked = contracts.contains("automock");
 
-    // Append the mod clause to the existing router.mod file
-    write_to_mod_file(&router_mod_path, &args.name);
+    let edited = [
+        ("contract", contract::contract_file(&contracts, &contract)?),
+        (
+            "infrastructure",
+            contract::infrastructure_file(&read("infrastructure")?, &contract)?,
+        ),
+        ("domain", contract::domain_file(&domain, &contract)?),
+        (
+            "setup",
+            contract::setup_file(&read("setup")?, &contract, &service)?,
+        ),
+        (
+            "mod",
+            match mocked {
+                true => contract::tests_file(&read("mod")?, &contract, &service)?,
+                false => None,
+            },
+        ),
+    ];
 
-    for file in FILES {
-        let mut contents = String::new();
-        match file {
-            "contract" => boiler::plate::contracts(&mut contents, &contracts),
-            "domain" => boiler::plate::domain(&mut contents, &service_name, &contracts),
-            "infrastructure" if !contracts.is_empty() => {
-                boiler::plate::infrastructure(&mut contents, &contracts)
+    let mut written = 0;
+    for (file, contents) in edited {
+        match contents {
+            Some(contents) => {
+                print(&format!("{} Writing {}.rs", "\u{270E}".blue(), file));
+                files::write(format!("{}/{}.rs", ep_path, file), contents)?;
+                written += 1;
             }
-            "setup" => boiler::plate::setup(&mut contents, &service_name, &contracts),
-            "mod" => boiler::plate::r#mod(&mut contents),
-            _ => {}
+            None => print(&format!("{}.rs already has {}", file, contract.name)),
         }
-        fs::write(&format!("{}/{}.rs", path, file), contents.clone())
-            .expect("Could't write to file");
-        contents.clear();
     }
-    println!("{}{}", "Successfully wrote endpoint ".green(), path)
+
+    if written == 0 {
+        println!("{} already has {}", args.name, contract.name);
+    } else {
+        println!(
+            "{}{} to {}",
+            "Successfully added ".green(),
+            contract.name,
+            ep_path
+        );
+    }
+    Ok(())
 }
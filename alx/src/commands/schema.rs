@@ -0,0 +1,59 @@
+//! Generate JSON Schemas of the request data structs
+use crate::{
+    analyzer::{analyze::scan, diagnostic::report},
+    error::AlxError,
+    export::schema::json_schema,
+    print,
+    validation::resolve_regexes,
+};
+use clap::Args;
+use colored::Colorize;
+use std::{fs, path::Path};
+
+/// Generate a JSON Schema for every request data struct found by the analyzer, with the constraints
+/// of their `#[validate]` attributes, so payloads can be validated against the same rules outside of
+/// the server. Written to `<OUT>/<ROUTE>/<STRUCT>.json`.
+#[derive(Debug, Args)]
+pub struct SchemaOptions {
+    /// Specify the path to the API directory to read from.
+    #[arg(short, long)]
+    pub path: Option<String>,
+    /// The directory to write the schemas to
+    #[arg(short, long, default_value = "./schemas")]
+    pub out: String,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+pub fn handle(opts: SchemaOptions, api_path: &str) -> Result<(), AlxError> {
+    let scan = scan(api_path)?;
+    report(&scan.diagnostics);
+
+    // Regexes are declared somewhere in the crate the API is in
+    let crate_src = Path::new(api_path).parent().unwrap_or(Path::new(api_path));
+    let regexes = resolve_regexes(crate_src);
+    print(&format!("Resolved {} regex statics", regexes.len()));
+
+    let mut routes = scan.data.iter().collect::<Vec<_>>();
+    routes.sort_by_key(|(route, _)| route.as_str());
+    let mut count = 0;
+    for (route, data) in routes {
+        let dir = Path::new(&opts.out).join(route);
+        fs::create_dir_all(&dir)?;
+        for d in data.iter() {
+            let path = dir.join(format!("{}.json", d.id));
+            print(&format!("{} Writing {}", "\u{270E}".blue(), path.display()));
+            let schema = json_schema(d, data, &regexes);
+            fs::write(&path, serde_json::to_string_pretty(&schema)?)?;
+            count += 1;
+        }
+    }
+    println!(
+        "{}{} schemas to {}",
+        "Successfully wrote ".green(),
+        count,
+        opts.out
+    );
+    Ok(())
+}
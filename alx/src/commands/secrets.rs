@@ -0,0 +1,389 @@
+//! Generate, list, rotate and check the secrets in the .env file and the key pairs in the
+//! encryption directory
+use crate::{
+    analyzer::env::{declared, scan},
+    error::AlxError,
+    print,
+    secrets::{
+        is_secret, key_pair, password, secret, set_var, unquote, weakness, KeyAlgorithm,
+        SecretEncoding, PREVIOUS,
+    },
+};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::{fs, path::Path};
+
+/// Bytes of randomness in generated and rotated secrets
+const DEFAULT_LENGTH: usize = 64;
+pub(crate) const PRIVATE_KEY: &str = "priv_key.pem";
+pub(crate) const PUBLIC_KEY: &str = "pub_key.pem";
+
+/// Manage the secrets in the .env file and the key pairs used by the server
+#[derive(Debug, Args)]
+pub struct SecretsCommand {
+    #[clap(subcommand)]
+    pub action: SecretsSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretsSubcommand {
+    /// Generate a secret, password or key pair
+    Gen(SecretsGen),
+    /// List the secrets in the .env file and the key pairs in the encryption directory with their
+    /// values masked
+    List(SecretsListArgs),
+    /// Replace a secret with a new one of the same encoding and keep the old value as
+    /// `<KEY>_PREVIOUS`
+    Rotate(SecretsRotateArgs),
+    /// Report secrets read by the workspace that are missing, empty, too short or of low entropy.
+    /// Exits with a non-zero code if any are found.
+    Check(SecretsCheckArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SecretsGen {
+    #[clap(subcommand)]
+    pub kind: SecretsGenSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretsGenSubcommand {
+    /// Append a new secret to the .env file
+    Secret(GenSecretArgs),
+    /// Write a random password to a file
+    Password(GenPasswordArgs),
+    /// Write a key pair in PEM format. Existing keys are renamed instead of deleted.
+    KeyPair(GenKeyPairArgs),
+}
+
+/// Secret generation arguments
+#[derive(Debug, Args)]
+pub struct GenSecretArgs {
+    /// The key to write the secret to
+    pub key: String,
+    /// Number of random bytes
+    #[arg(short, long, default_value_t = DEFAULT_LENGTH)]
+    pub length: usize,
+    #[arg(short, long, value_enum, default_value_t = SecretEncoding::Hex)]
+    pub encoding: SecretEncoding,
+    /// The .env file to write to
+    #[arg(long, default_value = "./.env")]
+    pub env: String,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Password generation arguments
+#[derive(Debug, Args)]
+pub struct GenPasswordArgs {
+    /// Number of characters
+    #[arg(short, long, default_value_t = 32)]
+    pub length: usize,
+    /// The file to write the password to
+    #[arg(short, long, default_value = "./encryption/password")]
+    pub out: String,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Key pair generation arguments
+#[derive(Debug, Args)]
+pub struct GenKeyPairArgs {
+    #[arg(short, long, value_enum, default_value_t = KeyAlgorithm::Rsa)]
+    pub alg: KeyAlgorithm,
+    /// Size of RSA keys in bits
+    #[arg(short, long, default_value_t = 2048)]
+    pub bits: u32,
+    /// The encryption directory
+    #[arg(short, long, default_value = "./encryption")]
+    pub dir: String,
+    /// Name of the directory in the encryption directory to write the keys to. Defaults to
+    /// `key_pair` for RSA, `ec_key_pair` and `ed25519_key_pair`.
+    #[arg(short, long)]
+    pub name: Option<String>,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Secrets list arguments
+#[derive(Debug, Args)]
+pub struct SecretsListArgs {
+    /// The .env file to list
+    #[arg(long, default_value = "./.env")]
+    pub env: String,
+    /// The encryption directory
+    #[arg(short, long, default_value = "./encryption")]
+    pub dir: String,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Secret rotation arguments
+#[derive(Debug, Args)]
+pub struct SecretsRotateArgs {
+    /// The key of the secret
+    pub key: String,
+    /// Number of random bytes. Defaults to what the old secret had, but at least 64.
+    #[arg(short, long)]
+    pub length: Option<usize>,
+    /// Defaults to the encoding of the old secret
+    #[arg(short, long, value_enum)]
+    pub encoding: Option<SecretEncoding>,
+    /// The .env file the secret is in
+    #[arg(long, default_value = "./.env")]
+    pub env: String,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+/// Secrets check arguments
+#[derive(Debug, Args)]
+pub struct SecretsCheckArgs {
+    /// The workspace root to scan
+    #[arg(short, long, default_value = ".")]
+    pub root: String,
+    /// The .env file to check
+    #[arg(long, default_value = "./.env")]
+    pub env: String,
+    /// Print what's going on to stdout
+    #[arg(short, long, action)]
+    pub verbose: bool,
+}
+
+impl SecretsSubcommand {
+    pub fn verbose(&self) -> bool {
+        match self {
+            Self::Gen(g) => match g.kind {
+                SecretsGenSubcommand::Secret(ref a) => a.verbose,
+                SecretsGenSubcommand::Password(ref a) => a.verbose,
+                SecretsGenSubcommand::KeyPair(ref a) => a.verbose,
+            },
+            Self::List(a) => a.verbose,
+            Self::Rotate(a) => a.verbose,
+            Self::Check(a) => a.verbose,
+        }
+    }
+}
+
+/// Secrets are never printed in diffs so these write straight to disk regardless of `--dry-run`.
+/// Returns true if the check found problems.
+pub fn handle(action: SecretsSubcommand) -> Result<bool, AlxError> {
+    match action {
+        SecretsSubcommand::Gen(g) => match g.kind {
+            SecretsGenSubcommand::Secret(args) => gen_secret(args)?,
+            SecretsGenSubcommand::Password(args) => gen_password(args)?,
+            SecretsGenSubcommand::KeyPair(args) => gen_key_pair(args)?,
+        },
+        SecretsSubcommand::List(args) => list(args)?,
+        SecretsSubcommand::Rotate(args) => rotate(args)?,
+        SecretsSubcommand::Check(args) => return check(args),
+    }
+    Ok(false)
+}
+
+fn gen_secret(args: GenSecretArgs) -> Result<(), AlxError> {
+    let src = fs::read_to_string(&args.env).unwrap_or_default();
+    if declared(&src).iter().any(|(k, _)| *k == args.key) {
+        return Err(AlxError::Edit(format!(
+            "{} already exists in {}, use `alx secrets rotate {}` to replace it",
+            args.key, args.env, args.key
+        )));
+    }
+    print(&format!("{} Writing {}", "\u{270E}".blue(), args.env));
+    let value = secret(args.length, args.encoding)?;
+    fs::write(&args.env, set_var(&src, &args.key, &value))?;
+    println!("{}{}", "Successfully generated ".green(), args.key);
+    Ok(())
+}
+
+fn gen_password(args: GenPasswordArgs) -> Result<(), AlxError> {
+    if let Some(parent) = Path::new(&args.out).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    print(&format!("{} Writing {}", "\u{270E}".blue(), args.out));
+    fs::write(&args.out, password(args.length)?)?;
+    println!(
+        "{}{}",
+        "Successfully generated password in ".green(),
+        args.out
+    );
+    Ok(())
+}
+
+fn gen_key_pair(args: GenKeyPairArgs) -> Result<(), AlxError> {
+    let name = args
+        .name
+        .as_deref()
+        .unwrap_or_else(|| args.alg.default_dir());
+    let dir = Path::new(&args.dir).join(name);
+    fs::create_dir_all(&dir)?;
+
+    let suffix = chrono::Local::now().format("%Y%m%d%H%M%S");
+    for file in [PRIVATE_KEY, PUBLIC_KEY] {
+        let path = dir.join(file);
+        if path.exists() {
+            let stem = file.trim_end_matches(".pem");
+            let backup = dir.join(format!("{stem}.{suffix}.pem"));
+            println!(
+                "{}{} to {}",
+                "Moving existing key ".yellow(),
+                path.display(),
+                backup.display()
+            );
+            fs::rename(&path, backup)?;
+        }
+    }
+
+    let (private, public) = key_pair(args.alg, args.bits)?;
+    for (file, pem) in [(PRIVATE_KEY, private), (PUBLIC_KEY, public)] {
+        let path = dir.join(file);
+        print(&format!("{} Writing {}", "\u{270E}".blue(), path.display()));
+        fs::write(path, pem)?;
+    }
+    println!(
+        "{}{}",
+        "Successfully generated key pair in ".green(),
+        dir.display()
+    );
+    Ok(())
+}
+
+fn list(args: SecretsListArgs) -> Result<(), AlxError> {
+    match fs::read_to_string(&args.env) {
+        Ok(src) => {
+            let secrets = declared(&src)
+                .into_iter()
+                .filter(|(k, _)| is_secret(k))
+                .collect::<Vec<_>>();
+            println!("{} ({})", args.env.bold(), secrets.len());
+            for (key, value) in secrets.iter() {
+                let value = unquote(value);
+                let mark = match weakness(key.trim_end_matches(PREVIOUS), value) {
+                    Some(w) => format!("{} {w}", "\u{2717}".red()),
+                    None => "\u{2713}".green().to_string(),
+                };
+                let previous = match key.ends_with(PREVIOUS) {
+                    true => " (previous)".dimmed().to_string(),
+                    false => String::new(),
+                };
+                println!(
+                    "  {key}{previous} = {} {} chars, {:?} {mark}",
+                    mask(value),
+                    value.len(),
+                    SecretEncoding::detect(value),
+                );
+            }
+        }
+        Err(_) => println!("{}{}", "Skipping missing file ".yellow(), args.env),
+    }
+
+    let Ok(entries) = fs::read_dir(&args.dir) else {
+        println!("{}{}", "Skipping missing directory ".yellow(), args.dir);
+        return Ok(());
+    };
+    println!("{}", args.dir.bold());
+    let mut entries = entries.flatten().map(|e| e.path()).collect::<Vec<_>>();
+    entries.sort();
+    for path in entries {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !path.is_dir() {
+            println!("  {name}");
+            continue;
+        }
+        let keys = [PRIVATE_KEY, PUBLIC_KEY].map(|f| path.join(f).exists());
+        let mark = match keys {
+            [true, true] => "\u{2713}".green(),
+            _ => "\u{2717}".red(),
+        };
+        let backups = fs::read_dir(&path)?
+            .flatten()
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.starts_with("priv_key.") && name != PRIVATE_KEY
+            })
+            .count();
+        println!("  {mark} {name} ({backups} previous)");
+    }
+    Ok(())
+}
+
+/// The first few characters of the secret
+fn mask(value: &str) -> String {
+    let shown = value.chars().take(4).collect::<String>();
+    format!("{shown}{}", "*".repeat(8))
+}
+
+fn rotate(args: SecretsRotateArgs) -> Result<(), AlxError> {
+    let src = fs::read_to_string(&args.env)?;
+    let Some((_, old)) = declared(&src).into_iter().find(|(k, _)| *k == args.key) else {
+        return Err(AlxError::Edit(format!(
+            "{} is not declared in {}, use `alx secrets gen secret {}` to create it",
+            args.key, args.env, args.key
+        )));
+    };
+    let old = unquote(&old);
+    let encoding = args.encoding.unwrap_or_else(|| SecretEncoding::detect(old));
+    let length = args
+        .length
+        .unwrap_or_else(|| encoding.decoded_len(old.len()).max(DEFAULT_LENGTH));
+
+    print(&format!("{} Writing {}", "\u{270E}".blue(), args.env));
+    let previous = format!("{}{PREVIOUS}", args.key);
+    let src = set_var(&src, &previous, old);
+    let src = set_var(&src, &args.key, &secret(length, encoding)?);
+    fs::write(&args.env, src)?;
+    println!(
+        "{}{}, the old value is kept in {}",
+        "Successfully rotated ".green(),
+        args.key,
+        previous
+    );
+    Ok(())
+}
+
+/// Returns true if a secret is missing or weak
+fn check(args: SecretsCheckArgs) -> Result<bool, AlxError> {
+    print(&format!("Scanning {}", args.root));
+    let vars = scan(Path::new(&args.root));
+    let declared = match fs::read_to_string(&args.env) {
+        Ok(src) => declared(&src),
+        Err(_) => {
+            println!("{}{}", "Missing file ".yellow(), args.env);
+            vec![]
+        }
+    };
+
+    let missing = vars
+        .iter()
+        .filter(|v| is_secret(&v.key) && v.default.is_none())
+        .filter(|v| !declared.iter().any(|(k, _)| *k == v.key))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        println!("{} ({})", "Missing".red().bold(), missing.len());
+        for v in missing.iter() {
+            println!("  {} {} ({})", "\u{2717}".red(), v.key, v.uses.join(", "));
+        }
+    }
+
+    let weak = declared
+        .iter()
+        .filter(|(k, _)| is_secret(k) && !k.ends_with(PREVIOUS))
+        .filter_map(|(k, v)| Some((k, weakness(k, v)?)))
+        .collect::<Vec<_>>();
+    if !weak.is_empty() {
+        println!("{} ({})", "Weak".red().bold(), weak.len());
+        for (key, weakness) in weak.iter() {
+            println!("  {} {} ({})", "\u{2717}".red(), key, weakness);
+        }
+    }
+
+    if missing.is_empty() && weak.is_empty() {
+        println!("{}", "All secrets are present and strong".green());
+    }
+    Ok(!missing.is_empty() || !weak.is_empty())
+}
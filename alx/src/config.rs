@@ -4,39 +4,52 @@ use std::{fmt::Display, fs, path::Path};
 
 const INDENT: &str = "    ";
 
+/// HTTP methods actix has route builders and guards for
+pub const HTTP_METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+];
+
+/// Method of routes registered without a method guard, e.g. `web::resource("/x").to(handler)`.
+/// These respond to any method.
+pub const ANY_METHOD: &str = "ANY";
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ProjectConfig {
     pub endpoints: Vec<Endpoint>,
 }
 
 impl ProjectConfig {
-    pub fn _parse(yaml: String) -> Result<Self, AlxError> {
+    pub fn parse(yaml: String) -> Result<Self, AlxError> {
         let config = serde_yaml::from_str::<Self>(&yaml)?;
         Ok(config)
     }
 
-    pub fn write_config_lock(&self, format: ConfigFormat) -> Result<(), AlxError> {
-        match format {
-            ConfigFormat::Json => {
-                let config = serde_json::to_string_pretty(self)?;
-                fs::write(Path::new("./alx_lock.json"), config)?;
-            }
-            ConfigFormat::Yaml => {
-                let config = serde_yaml::to_string(self)?;
-                fs::write(Path::new("./alx_lock.yaml"), config)?;
-            }
-            ConfigFormat::Both => {
-                let config = serde_json::to_string_pretty(self)?;
-                fs::write(Path::new("./alx_lock.json"), config)?;
-                let config = serde_yaml::to_string(self)?;
-                fs::write(Path::new("./alx_lock.yaml"), config)?;
-            }
-        };
+    /// Reads a previously written lock file. Json if the extension is `.json`, Yaml otherwise.
+    pub fn read_lock(path: &str) -> Result<Self, AlxError> {
+        let contents = fs::read_to_string(path)?;
+        if path.ends_with(".json") {
+            Ok(serde_json::from_str(&contents)?)
+        } else {
+            Self::parse(contents)
+        }
+    }
+
+    /// Writes the lock file(s) into the directory
+    pub fn write_config_lock(&self, format: ConfigFormat, dir: &Path) -> Result<(), AlxError> {
+        if matches!(format, ConfigFormat::Json | ConfigFormat::Both) {
+            let config = serde_json::to_string_pretty(self)?;
+            fs::write(dir.join("alx_lock.json"), config)?;
+        }
+        if matches!(format, ConfigFormat::Yaml | ConfigFormat::Both) {
+            let config = serde_yaml::to_string(self)?;
+            fs::write(dir.join("alx_lock.yaml"), config)?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ConfigFormat {
     Json,
     Yaml,
@@ -82,8 +95,12 @@ impl Display for ProjectConfig {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Endpoint {
     pub name: String,
+    #[serde(default)]
     pub full_path: String,
     pub routes: Vec<RouteHandler>,
+    /// Contracts the endpoint's service depends on. Only used when scaffolding from a spec.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contracts: Option<Vec<String>>,
 }
 
 /// Love child of [Route] and [Handler]
@@ -93,10 +110,25 @@ pub struct RouteHandler {
     pub path: String,
     pub handler: Option<Handler>,
     pub middleware: Option<Vec<String>>,
+    /// Non method guards on the route, if any
+    #[serde(default)]
+    pub guards: Option<Vec<String>>,
     pub service: Option<String>,
     pub input: Option<Data>,
 }
 
+impl RouteHandler {
+    /// Returns the data type of the handler input using the given extractor, if any
+    pub fn extractor_data(&self, extractor: Extractor) -> Option<&str> {
+        self.handler.as_ref().and_then(|h| {
+            h.inputs
+                .iter()
+                .find(|i| Extractor::from(i.ext_type.clone()) == extractor)
+                .map(|i| i.data_type.as_str())
+        })
+    }
+}
+
 impl From<(&mut Route, Option<&Handler>, Option<&Data>)> for RouteHandler {
     fn from((r, h, d): (&mut Route, Option<&Handler>, Option<&Data>)) -> Self {
         Self {
@@ -104,6 +136,7 @@ impl From<(&mut Route, Option<&Handler>, Option<&Data>)> for RouteHandler {
             path: r.path.to_string(),
             handler: h.cloned(),
             middleware: r.middleware.clone(),
+            guards: r.guards.clone(),
             service: r.service.clone(),
             input: d.cloned(),
         }
@@ -121,6 +154,8 @@ pub struct Route {
     pub path: String,
     /// The middleware wrapped around the route, if any
     pub middleware: Option<Vec<String>>,
+    /// Non method guards on the route, if any
+    pub guards: Option<Vec<String>>,
     /// The service this route uses
     pub service: Option<String>,
 }
@@ -131,9 +166,14 @@ pub struct Handler {
     /// Handler name
     pub name: String,
     /// The inputs (args) for this handler function
+    #[serde(default)]
     pub inputs: Vec<HandlerInput>,
     /// Trait bounds for the handler, if any
     pub bound: Option<String>,
+    /// Routes declared on the handler with actix attribute macros, i.e. `#[get("/path")]`.
+    /// Resolved against the setup file when the project config is assembled.
+    #[serde(skip)]
+    pub macro_routes: Vec<Route>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
@@ -152,13 +192,35 @@ pub struct Data {
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 pub struct Field {
+    /// The Rust identifier
     pub name: String,
+    /// What serde (de)serializes the field as, when it's not the identifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename: Option<String>,
     pub ty: String,
+    /// Whether requests have to carry the field
+    #[serde(default = "required_default")]
     pub required: bool,
+    /// Whether serde fills in the default when the field is missing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default: bool,
+    #[serde(default)]
     pub validation: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+impl Field {
+    /// The name of the field in requests
+    pub fn wire_name(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Fields in hand-written specs are required unless stated otherwise
+fn required_default() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Extractor {
     #[serde(alias = "path", alias = "Path")]
     Path,
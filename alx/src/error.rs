@@ -8,4 +8,22 @@ pub enum AlxError {
     SerdeYaml(#[from] serde_yaml::Error),
     #[error("Json: {0}")]
     SerdeJson(#[from] serde_json::Error),
+    #[error("Syntax: {0}")]
+    Syn(#[from] syn::Error),
+    #[error("Unable to edit: {0}")]
+    Edit(String),
+    #[error("Database connection: {0}")]
+    Connection(#[from] diesel::ConnectionError),
+    #[error("Database: {0}")]
+    Database(#[from] diesel::result::Error),
+    #[error("Template: {0}")]
+    Template(String),
+    #[error("Migration: {0}")]
+    Migration(String),
+    #[error("Settings: {0}")]
+    Settings(String),
+    #[error("Project: {0}")]
+    Project(String),
+    #[error("Crypto: {0}")]
+    Crypto(#[from] openssl::error::ErrorStack),
 }
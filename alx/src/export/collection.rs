@@ -0,0 +1,475 @@
+//! Builds Postman and Insomnia collections from the analyzed project. Every endpoint gets a folder
+//! with a request per route, pre-filled with example data. The host, session cookie and CSRF token
+//! are variables so the collection works against any environment.
+use super::example::{data_example, field_example, ExampleContext};
+use crate::{
+    analyzer::audit::Guards,
+    config::{Data, Endpoint, Extractor, ProjectConfig, RouteHandler, ANY_METHOD},
+    print,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+pub const POSTMAN_SCHEMA: &str =
+    "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+pub const HOST_VAR: &str = "API_URI";
+pub const SESSION_VAR: &str = "S_ID";
+pub const CSRF_VAR: &str = "CSRF";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Collection metadata not obtainable from the project
+pub struct CollectionInfo {
+    pub name: String,
+    /// The default value of the host variable, e.g. `http://localhost:8080`
+    pub host: String,
+}
+
+/// A route as it's sent, shared by both formats
+struct Request<'a> {
+    name: String,
+    route: &'a RouteHandler,
+    /// Whether the route requires a session
+    guarded: bool,
+    /// Path segments, parameters hold an example value
+    segments: Vec<Segment>,
+    /// Query parameters with an example value and whether they're required
+    query: Vec<(String, Value, bool)>,
+    /// Content type and example
+    body: Option<(&'static str, Value)>,
+}
+
+enum Segment {
+    Static(String),
+    Param(String, Value),
+}
+
+impl<'a> Request<'a> {
+    fn new(route: &'a RouteHandler, guarded: bool, ctx: &ExampleContext) -> Self {
+        let find = |ext| {
+            route
+                .extractor_data(ext)
+                .and_then(|id| ctx.data.iter().find(|d| d.id == id))
+        };
+        let path_data = find(Extractor::Path);
+        let segments = route
+            .path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(
+                |s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(param) => {
+                        // Actix params can carry a regex, i.e. `{id:\d+}`
+                        let name = param.split(':').next().unwrap_or(param).to_string();
+                        let example = path_data
+                            .and_then(|d| d.fields.iter().find(|f| f.wire_name() == name))
+                            .map_or(Value::String(String::new()), |f| field_example(f, ctx));
+                        Segment::Param(name, example)
+                    }
+                    None => Segment::Static(s.to_string()),
+                },
+            )
+            .collect();
+
+        let query = match find(Extractor::Query) {
+            Some(data) => data
+                .fields
+                .iter()
+                .map(|f| (f.wire_name().to_string(), field_example(f, ctx), f.required))
+                .collect(),
+            None => vec![],
+        };
+
+        let body = match (find(Extractor::Json), find(Extractor::Form)) {
+            (Some(data), _) => Some(("application/json", data_example(data, ctx))),
+            (_, Some(data)) => Some(("application/x-www-form-urlencoded", data_example(data, ctx))),
+            _ => None,
+        };
+
+        Self {
+            name: request_name(route),
+            route,
+            guarded,
+            segments,
+            query,
+            body,
+        }
+    }
+}
+
+/// Assemble a Postman v2.1 collection. The regexes are used to pick examples that pass
+/// `regex = "NAME"` validations, the guards to tell which routes require a session.
+pub fn postman(
+    pc: &ProjectConfig,
+    info: &CollectionInfo,
+    regexes: &HashMap<String, String>,
+    guards: &Guards,
+) -> Value {
+    let known = known_data(pc);
+    let ctx = ExampleContext {
+        data: &known,
+        regexes,
+    };
+    let folders = endpoints(pc)
+        .map(|(ep, routes)| {
+            let items = routes
+                .iter()
+                .map(|r| {
+                    let guarded = guards.is_guarded(&ep.name, r);
+                    postman_request(&Request::new(r, guarded, &ctx))
+                })
+                .collect::<Vec<_>>();
+            json!({ "name": ep.name, "item": items })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "info": {
+            "name": info.name,
+            "schema": POSTMAN_SCHEMA,
+        },
+        "item": folders,
+        "event": [{
+            "listen": "test",
+            "script": {
+                "type": "text/javascript",
+                "exec": [
+                    format!("if (pm.response.headers.has('{CSRF_HEADER}')) {{"),
+                    format!("    pm.collectionVariables.set('{CSRF_VAR}', pm.response.headers.get('{CSRF_HEADER}'));"),
+                    "}".to_string(),
+                    format!("if (pm.cookies.has('{SESSION_VAR}')) {{"),
+                    format!("    pm.collectionVariables.set('{SESSION_VAR}', pm.cookies.get('{SESSION_VAR}'));"),
+                    "}".to_string(),
+                ]
+            }
+        }],
+        "variable": [
+            { "key": HOST_VAR, "value": info.host, "type": "string" },
+            { "key": SESSION_VAR, "value": "", "type": "string" },
+            { "key": CSRF_VAR, "value": "", "type": "string" },
+        ]
+    })
+}
+
+fn postman_request(req: &Request) -> Value {
+    let path = req
+        .segments
+        .iter()
+        .map(|s| match s {
+            Segment::Static(s) => s.clone(),
+            Segment::Param(name, _) => format!(":{name}"),
+        })
+        .collect::<Vec<_>>();
+    let query = req
+        .query
+        .iter()
+        .map(|(key, value, required)| {
+            json!({ "key": key, "value": plain(value), "disabled": !required })
+        })
+        .collect::<Vec<_>>();
+    let variables = req
+        .segments
+        .iter()
+        .filter_map(|s| match s {
+            Segment::Param(name, value) => Some(json!({ "key": name, "value": plain(value) })),
+            Segment::Static(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    let host = postman_var(HOST_VAR);
+    let mut raw = format!("{host}/{}", path.join("/"));
+    let enabled = req
+        .query
+        .iter()
+        .filter(|(_, _, required)| *required)
+        .map(|(key, value, _)| format!("{key}={}", plain(value)))
+        .collect::<Vec<_>>();
+    if !enabled.is_empty() {
+        raw = format!("{raw}?{}", enabled.join("&"));
+    }
+
+    let mut url = json!({
+        "raw": raw,
+        "host": [host],
+        "path": path,
+    });
+    if !query.is_empty() {
+        url["query"] = json!(query);
+    }
+    if !variables.is_empty() {
+        url["variable"] = json!(variables);
+    }
+
+    let mut request = json!({
+        "method": req.route.method,
+        "header": headers(req, postman_var)
+            .into_iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "text" }))
+            .collect::<Vec<_>>(),
+        "url": url,
+    });
+    match req.body {
+        Some(("application/json", ref example)) => {
+            request["body"] = json!({
+                "mode": "raw",
+                "raw": serde_json::to_string_pretty(example).unwrap_or_default(),
+                "options": { "raw": { "language": "json" } }
+            })
+        }
+        Some((_, ref example)) => {
+            request["body"] = json!({
+                "mode": "urlencoded",
+                "urlencoded": form_fields(example)
+                    .into_iter()
+                    .map(|(key, value)| json!({ "key": key, "value": value, "type": "text" }))
+                    .collect::<Vec<_>>(),
+            })
+        }
+        None => {}
+    }
+
+    json!({
+        "name": req.name,
+        "request": request,
+        "response": [],
+    })
+}
+
+/// Assemble an Insomnia v4 export, see [postman] for the regexes and guards
+pub fn insomnia(
+    pc: &ProjectConfig,
+    info: &CollectionInfo,
+    regexes: &HashMap<String, String>,
+    guards: &Guards,
+) -> Value {
+    let known = known_data(pc);
+    let ctx = ExampleContext {
+        data: &known,
+        regexes,
+    };
+    let workspace = "wrk_alx";
+    let mut resources = vec![
+        json!({
+            "_id": workspace,
+            "_type": "workspace",
+            "name": info.name,
+            "scope": "collection",
+        }),
+        json!({
+            "_id": "env_alx",
+            "_type": "environment",
+            "parentId": workspace,
+            "name": "Base Environment",
+            "data": {
+                HOST_VAR: info.host,
+                SESSION_VAR: "",
+                CSRF_VAR: "",
+            }
+        }),
+    ];
+
+    for (ep, routes) in endpoints(pc) {
+        let folder = format!("fld_{}", ep.name);
+        resources.push(json!({
+            "_id": folder,
+            "_type": "request_group",
+            "parentId": workspace,
+            "name": ep.name,
+        }));
+        for (i, route) in routes.iter().enumerate() {
+            let req = Request::new(route, guards.is_guarded(&ep.name, route), &ctx);
+            let mut resource = insomnia_request(&req);
+            resource["_id"] = json!(format!("req_{}_{i}", ep.name));
+            resource["parentId"] = json!(folder);
+            resources.push(resource);
+        }
+    }
+
+    json!({
+        "_type": "export",
+        "__export_format": 4,
+        "__export_source": "alx",
+        "resources": resources,
+    })
+}
+
+fn insomnia_request(req: &Request) -> Value {
+    // Insomnia has no path variables, the examples go straight in to the url
+    let path = req
+        .segments
+        .iter()
+        .map(|s| match s {
+            Segment::Static(s) => s.clone(),
+            Segment::Param(_, value) => plain(value),
+        })
+        .collect::<Vec<_>>();
+    let mut headers = headers(req, insomnia_var)
+        .into_iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect::<Vec<_>>();
+    let parameters = req
+        .query
+        .iter()
+        .map(|(name, value, required)| {
+            json!({ "name": name, "value": plain(value), "disabled": !required })
+        })
+        .collect::<Vec<_>>();
+
+    let body = match req.body {
+        Some(("application/json", ref example)) => json!({
+            "mimeType": "application/json",
+            "text": serde_json::to_string_pretty(example).unwrap_or_default(),
+        }),
+        Some((mime, ref example)) => json!({
+            "mimeType": mime,
+            "params": form_fields(example)
+                .into_iter()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect::<Vec<_>>(),
+        }),
+        None => json!({}),
+    };
+    if let Some((mime, _)) = req.body {
+        headers.push(json!({ "name": "Content-Type", "value": mime }));
+    }
+
+    json!({
+        "_type": "request",
+        "name": req.name,
+        "method": req.route.method,
+        "url": format!("{}/{}", insomnia_var(HOST_VAR), path.join("/")),
+        "headers": headers,
+        "parameters": parameters,
+        "body": body,
+    })
+}
+
+/// The endpoints with the routes that can be sent, routes without a method guard are skipped
+fn endpoints(pc: &ProjectConfig) -> impl Iterator<Item = (&Endpoint, Vec<&RouteHandler>)> {
+    pc.endpoints.iter().filter_map(|ep| {
+        let routes = ep
+            .routes
+            .iter()
+            .filter(|r| {
+                if r.method == ANY_METHOD {
+                    print(&format!("Skipping {}, it has no method guard", r.path));
+                }
+                r.method != ANY_METHOD
+            })
+            .collect::<Vec<_>>();
+        (!routes.is_empty()).then_some((ep, routes))
+    })
+}
+
+fn known_data(pc: &ProjectConfig) -> Vec<Data> {
+    pc.endpoints
+        .iter()
+        .flat_map(|ep| ep.routes.iter().filter_map(|r| r.input.clone()))
+        .collect()
+}
+
+/// Guarded routes need the session cookie and the CSRF token obtained on login. `var` references a
+/// variable in the format's syntax.
+fn headers(req: &Request, var: fn(&str) -> String) -> Vec<(&'static str, String)> {
+    if !req.guarded {
+        return vec![];
+    }
+    vec![
+        (CSRF_HEADER, var(CSRF_VAR)),
+        ("Cookie", format!("{SESSION_VAR}={}", var(SESSION_VAR))),
+    ]
+}
+
+fn postman_var(name: &str) -> String {
+    format!("{{{{{name}}}}}")
+}
+
+fn insomnia_var(name: &str) -> String {
+    format!("{{{{ _.{name} }}}}")
+}
+
+/// Title cased handler name, i.e. `Get Paginated` for `get_paginated`
+fn request_name(route: &RouteHandler) -> String {
+    match route.handler {
+        Some(ref h) if !h.name.is_empty() => h
+            .name
+            .split('_')
+            .filter(|w| !w.is_empty())
+            .map(|w| format!("{}{}", w[..1].to_uppercase(), &w[1..]))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => format!("{} {}", route.method, route.path),
+    }
+}
+
+/// Flattens an example object to form fields
+fn form_fields(example: &Value) -> Vec<(String, String)> {
+    example
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| (k.clone(), plain(v)))
+        .collect()
+}
+
+/// Example values as they appear in urls, strings without quotes
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Field, Handler, HandlerInput};
+
+    #[test]
+    fn fills_in_path_params_and_guard_headers() {
+        let data = Data {
+            id: "GetThing".to_string(),
+            fields: vec![Field {
+                name: "id".to_string(),
+                ty: "u32".to_string(),
+                required: true,
+                validation: vec!["range(min = 1)".to_string()],
+                ..Default::default()
+            }],
+        };
+        let route = RouteHandler {
+            method: "GET".to_string(),
+            path: "/things/{id:\\d+}".to_string(),
+            handler: Some(Handler {
+                name: "get_thing".to_string(),
+                inputs: vec![HandlerInput {
+                    ext_type: "Path".to_string(),
+                    data_type: "GetThing".to_string(),
+                }],
+                ..Default::default()
+            }),
+            middleware: Some(vec!["auth_guard".to_string()]),
+            guards: None,
+            service: None,
+            input: None,
+        };
+
+        let known = [data];
+        let ctx = ExampleContext {
+            data: &known,
+            regexes: &HashMap::new(),
+        };
+        let req = postman_request(&Request::new(&route, true, &ctx));
+        assert_eq!(req["name"], "Get Thing");
+        assert_eq!(req["request"]["url"]["raw"], "{{API_URI}}/things/:id");
+        assert_eq!(
+            req["request"]["url"]["variable"],
+            json!([{ "key": "id", "value": "1" }])
+        );
+        assert_eq!(req["request"]["header"][1]["value"], "S_ID={{S_ID}}");
+
+        let req = insomnia_request(&Request::new(&route, true, &ctx));
+        assert_eq!(req["url"], "{{ _.API_URI }}/things/1");
+        assert_eq!(req["headers"][0]["value"], "{{ _.CSRF }}");
+    }
+}
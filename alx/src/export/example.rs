@@ -0,0 +1,208 @@
+//! Example values for analyzed data, used to pre-fill request bodies and parameters of exported
+//! collections. Examples satisfy the validation of the fields where it's known how to.
+use super::schema::generic_inner;
+use crate::{
+    config::{Data, Field},
+    validation::Validation,
+};
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+pub struct ExampleContext<'a> {
+    /// Known data structs, used to fill in nested data structs
+    pub data: &'a [Data],
+    /// Regex statics by name, see [crate::validation::resolve_regexes]
+    pub regexes: &'a HashMap<String, String>,
+}
+
+/// Strings tried in order for `regex = "NAME"` validations, the first one the pattern matches is used
+const REGEX_SAMPLES: [&str; 6] = [
+    "user@example.com",
+    "https://example.com",
+    "+385911234567",
+    "00000000-0000-0000-0000-000000000000",
+    "string",
+    "0",
+];
+
+/// Builds an example object for the data struct
+pub fn data_example(data: &Data, ctx: &ExampleContext) -> Value {
+    data_example_nested(data, ctx, 0)
+}
+
+/// Builds an example value for a single field
+pub fn field_example(field: &Field, ctx: &ExampleContext) -> Value {
+    field_example_nested(field, ctx, 0)
+}
+
+/// Recursive data structs get cut off at this depth
+const MAX_DEPTH: usize = 4;
+
+fn data_example_nested(data: &Data, ctx: &ExampleContext, depth: usize) -> Value {
+    let mut obj = Map::new();
+    for field in data.fields.iter() {
+        obj.insert(
+            field.wire_name().to_string(),
+            field_example_nested(field, ctx, depth),
+        );
+    }
+    Value::Object(obj)
+}
+
+fn field_example_nested(field: &Field, ctx: &ExampleContext, depth: usize) -> Value {
+    let validations = Validation::parse_all(&field.validation);
+    let mut value = type_example(&field.ty, ctx, depth);
+    let ty = inner_type(&field.ty);
+    for v in validations.iter() {
+        match v {
+            Validation::Email => value = json!("user@example.com"),
+            Validation::Url => value = json!("https://example.com"),
+            Validation::Regex(name) if value.is_string() => {
+                if let Some(sample) = regex_sample(ctx.regexes.get(name)) {
+                    value = json!(sample);
+                }
+            }
+            Validation::Range { min, max } => {
+                let n = min.or(*max).unwrap_or_default();
+                value = match ty.starts_with('f') {
+                    true => json!(n),
+                    false => json!(n as i64),
+                };
+            }
+            Validation::Length { min, max, equal } => {
+                if let Value::String(ref mut s) = value {
+                    let min = equal.or(*min).unwrap_or_default() as usize;
+                    let max = equal.or(*max).map_or(usize::MAX, |m| m as usize);
+                    while s.len() < min {
+                        s.push('x');
+                    }
+                    s.truncate(max);
+                }
+            }
+            _ => {}
+        }
+    }
+    value
+}
+
+/// Maps a rust type as written by the analyzer to an example value
+fn type_example(ty: &str, ctx: &ExampleContext, depth: usize) -> Value {
+    let ty = ty.trim();
+    if let Some(inner) = generic_inner(ty, "Option").or_else(|| generic_inner(ty, "Box")) {
+        return type_example(inner, ctx, depth);
+    }
+    if let Some(inner) = generic_inner(ty, "Vec").or_else(|| generic_inner(ty, "HashSet")) {
+        return json!([type_example(inner, ctx, depth)]);
+    }
+    if generic_inner(ty, "HashMap")
+        .or_else(|| generic_inner(ty, "BTreeMap"))
+        .is_some()
+    {
+        return json!({});
+    }
+    match ty {
+        "String" | "str" | "&str" => json!("string"),
+        "char" => json!("a"),
+        "bool" => json!(false),
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "isize" => json!(0),
+        "f32" | "f64" => json!(0.0),
+        "Uuid" => json!("00000000-0000-0000-0000-000000000000"),
+        "NaiveDateTime" | "DateTime" => json!("1970-01-01T00:00:00Z"),
+        "NaiveDate" => json!("1970-01-01"),
+        other => match ctx.data.iter().find(|d| d.id == other) {
+            Some(d) if depth < MAX_DEPTH => data_example_nested(d, ctx, depth + 1),
+            // Types defined outside of data.rs, we can't know their shape
+            _ => Value::Null,
+        },
+    }
+}
+
+/// The first sample the pattern matches. Unresolved statics and patterns none of the samples match
+/// keep the default example.
+fn regex_sample(pattern: Option<&String>) -> Option<&'static str> {
+    let regex = Regex::new(pattern?).ok()?;
+    REGEX_SAMPLES.into_iter().find(|s| regex.is_match(s))
+}
+
+/// The type wrapped in optionals, i.e. `u16` for `Option<u16>`
+fn inner_type(ty: &str) -> &str {
+    match generic_inner(ty, "Option") {
+        Some(inner) => inner_type(inner),
+        None => ty.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples_satisfy_validation() {
+        let data = Data {
+            id: "Register".to_string(),
+            fields: vec![
+                Field {
+                    name: "email".to_string(),
+                    ty: "String".to_string(),
+                    required: true,
+                    validation: vec!["email".to_string()],
+                    ..Default::default()
+                },
+                Field {
+                    name: "backup_email".to_string(),
+                    ty: "Option<String>".to_string(),
+                    required: false,
+                    validation: vec!["regex = \"EMAIL_REGEX\"".to_string()],
+                    ..Default::default()
+                },
+                Field {
+                    name: "phone".to_string(),
+                    ty: "String".to_string(),
+                    required: true,
+                    validation: vec!["regex = \"PHONE_REGEX\"".to_string()],
+                    ..Default::default()
+                },
+                Field {
+                    name: "password".to_string(),
+                    ty: "String".to_string(),
+                    required: true,
+                    validation: vec!["length(min = 8, max = 64)".to_string()],
+                    ..Default::default()
+                },
+                Field {
+                    name: "page".to_string(),
+                    ty: "Option<u16>".to_string(),
+                    required: false,
+                    validation: vec!["range(min = 1, max = 65_535)".to_string()],
+                    ..Default::default()
+                },
+            ],
+        };
+        let regexes = HashMap::from([
+            (
+                "EMAIL_REGEX".to_string(),
+                "[a-z0-9.]+@[a-z0-9-]+\\.[a-z]+".to_string(),
+            ),
+            (
+                "PHONE_REGEX".to_string(),
+                "^\\+[1-9][0-9]{6,14}$".to_string(),
+            ),
+        ]);
+        let ctx = ExampleContext {
+            data: &[],
+            regexes: &regexes,
+        };
+        assert_eq!(
+            data_example(&data, &ctx),
+            json!({
+                "email": "user@example.com",
+                "backup_email": "user@example.com",
+                "phone": "+385911234567",
+                "password": "stringxx",
+                "page": 1
+            })
+        );
+    }
+}
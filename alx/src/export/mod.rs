@@ -0,0 +1,4 @@
+pub mod collection;
+pub mod example;
+pub mod openapi;
+pub mod schema;
@@ -0,0 +1,306 @@
+//! Builds an OpenAPI 3.1 document from the analyzed project
+use super::schema::{data_schema, field_schema, type_schema, SchemaContext};
+use crate::{
+    analyzer::audit::Guards,
+    config::{Data, Extractor, ProjectConfig, RouteHandler, ANY_METHOD},
+    print,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+pub const OPENAPI_VERSION: &str = "3.1.0";
+const SCHEMA_REF: &str = "#/components/schemas/";
+
+/// Document metadata not obtainable from the project
+pub struct OpenApiInfo {
+    pub title: String,
+    pub version: String,
+    pub servers: Vec<String>,
+}
+
+/// Assemble the OpenAPI document. The regexes are used to resolve `regex = "NAME"` validations
+/// to actual patterns, the guards to tell which routes require a session.
+pub fn document(
+    pc: &ProjectConfig,
+    info: &OpenApiInfo,
+    regexes: &HashMap<String, String>,
+    guards: &Guards,
+) -> Value {
+    // Every data struct in the project can be referenced from any other
+    let data = pc
+        .endpoints
+        .iter()
+        .flat_map(|ep| ep.routes.iter().filter_map(|r| r.input.clone()))
+        .collect::<Vec<Data>>();
+    let ctx = SchemaContext {
+        data: &data,
+        regexes,
+        ref_prefix: SCHEMA_REF,
+    };
+
+    let mut paths = Map::new();
+    let mut schemas = Map::new();
+    let mut operation_ids = HashMap::<String, usize>::new();
+
+    for ep in pc.endpoints.iter() {
+        for route in ep.routes.iter() {
+            // OpenAPI has no notion of a route answering to every method
+            if route.method == ANY_METHOD {
+                print(&format!("Skipping {}, it has no method guard", route.path));
+                continue;
+            }
+            let path = openapi_path(&route.path);
+            let mut operation = operation(route, &ep.name, &ctx, guards);
+
+            // Handler names are only unique per endpoint
+            if let Some(ref h) = route.handler {
+                let count = operation_ids.entry(h.name.clone()).or_insert(0);
+                operation["operationId"] = if *count == 0 {
+                    json!(h.name)
+                } else {
+                    json!(format!("{}_{}", ep.name, h.name))
+                };
+                *count += 1;
+            }
+
+            if let Some(ref input) = route.input {
+                if body_content_type(route).is_some() {
+                    schemas.insert(input.id.clone(), data_schema(input, &ctx));
+                }
+            }
+
+            let item = paths
+                .entry(path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            item[route.method.to_lowercase()] = operation;
+        }
+    }
+
+    schemas.insert("ErrorResponse".to_string(), error_response_schema());
+
+    let mut doc = json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": info.title,
+            "version": info.version,
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "session": {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": "S_ID",
+                    "description": "Session ID cookie obtained on login"
+                },
+                "csrf": {
+                    "type": "apiKey",
+                    "in": "header",
+                    "name": "x-csrf-token",
+                    "description": "CSRF token obtained on login, bound to the session"
+                }
+            }
+        }
+    });
+    if !info.servers.is_empty() {
+        doc["servers"] = info
+            .servers
+            .iter()
+            .map(|url| json!({ "url": url }))
+            .collect();
+    }
+    doc
+}
+
+fn operation(route: &RouteHandler, tag: &str, ctx: &SchemaContext, guards: &Guards) -> Value {
+    let mut op = json!({
+        "tags": [tag],
+        "responses": {
+            "200": { "description": "Successful response" },
+            "default": error_response("Error response"),
+        }
+    });
+
+    let mut parameters = path_parameters(route, ctx);
+    parameters.append(&mut query_parameters(route, ctx));
+    if !parameters.is_empty() {
+        op["parameters"] = Value::Array(parameters);
+    }
+
+    if let (Some(content_type), Some(ref input)) = (body_content_type(route), &route.input) {
+        op["requestBody"] = json!({
+            "required": true,
+            "content": {
+                content_type: {
+                    "schema": { "$ref": format!("{SCHEMA_REF}{}", input.id) }
+                }
+            }
+        });
+    }
+
+    if guards.is_guarded(tag, route) {
+        op["security"] = json!([{ "session": [], "csrf": [] }]);
+        op["responses"]["401"] = error_response("Missing or invalid session");
+    }
+    let other = route
+        .middleware
+        .iter()
+        .flatten()
+        .filter(|mw| guards.guard(tag, mw).is_none())
+        .collect::<Vec<_>>();
+    if !other.is_empty() {
+        op["x-middleware"] = json!(other);
+    }
+    op
+}
+
+/// Actix paths use `{name}` and `{name:regex}` segments, OpenAPI only supports the former
+fn openapi_path(path: &str) -> String {
+    let mut out = String::new();
+    let mut in_param = false;
+    let mut skipping = false;
+    for c in path.chars() {
+        match c {
+            '{' => {
+                in_param = true;
+                out.push(c);
+            }
+            '}' if in_param => {
+                in_param = false;
+                skipping = false;
+                out.push(c);
+            }
+            ':' if in_param => skipping = true,
+            _ if skipping => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Names of all the `{param}` segments in the route path
+fn path_template_params(path: &str) -> Vec<String> {
+    openapi_path(path)
+        .split('{')
+        .skip(1)
+        .filter_map(|s| s.split_once('}').map(|(name, _)| name.to_string()))
+        .collect()
+}
+
+fn path_parameters(route: &RouteHandler, ctx: &SchemaContext) -> Vec<Value> {
+    let names = path_template_params(&route.path);
+    if names.is_empty() {
+        return vec![];
+    }
+    let data = route
+        .extractor_data(Extractor::Path)
+        .and_then(|id| ctx.data.iter().find(|d| d.id == id));
+    names
+        .iter()
+        .map(|name| {
+            let schema = match data.and_then(|d| d.fields.iter().find(|f| f.wire_name() == name)) {
+                Some(field) => non_null(field_schema(field, ctx)),
+                None => json!({ "type": "string" }),
+            };
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": schema,
+            })
+        })
+        .collect()
+}
+
+fn query_parameters(route: &RouteHandler, ctx: &SchemaContext) -> Vec<Value> {
+    let Some(id) = route.extractor_data(Extractor::Query) else {
+        return vec![];
+    };
+    match ctx.data.iter().find(|d| d.id == id) {
+        Some(data) => data
+            .fields
+            .iter()
+            .map(|field| {
+                json!({
+                    "name": field.wire_name(),
+                    "in": "query",
+                    "required": field.required,
+                    "schema": non_null(field_schema(field, ctx)),
+                })
+            })
+            .collect(),
+        // Query extractors over primitives/hashmaps
+        None => vec![json!({
+            "name": "query",
+            "in": "query",
+            "style": "form",
+            "explode": true,
+            "schema": type_schema(id, ctx),
+        })],
+    }
+}
+
+/// Optional parameters are expressed with `required: false`, so the null variant is redundant
+fn non_null(schema: Value) -> Value {
+    match schema.get("anyOf") {
+        Some(Value::Array(variants)) if variants.len() == 2 && variants[1]["type"] == "null" => {
+            variants[0].clone()
+        }
+        _ => schema,
+    }
+}
+
+fn body_content_type(route: &RouteHandler) -> Option<&'static str> {
+    if route.extractor_data(Extractor::Json).is_some() {
+        Some("application/json")
+    } else if route.extractor_data(Extractor::Form).is_some() {
+        Some("application/x-www-form-urlencoded")
+    } else {
+        None
+    }
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": { "$ref": format!("{SCHEMA_REF}ErrorResponse") }
+            }
+        }
+    })
+}
+
+/// Mirrors the server's `ErrorResponse`
+fn error_response_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "code": { "type": "integer" },
+            "message": { "type": "string" },
+            "description": { "type": "string" },
+            "validation_errors": {
+                "anyOf": [
+                    { "type": "array", "items": { "type": "object" } },
+                    { "type": "null" }
+                ]
+            }
+        },
+        "required": ["code", "message", "description"]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_actix_paths() {
+        assert_eq!(openapi_path("/users/{id:\\d+}/x"), "/users/{id}/x");
+        assert_eq!(
+            path_template_params("/a/{id}/b/{name:[a-z]+}"),
+            vec!["id".to_string(), "name".to_string()]
+        );
+    }
+}
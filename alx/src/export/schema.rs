@@ -0,0 +1,237 @@
+//! Maps analyzed data fields to JSON Schema, shared by every exporter that needs to describe payloads
+use crate::{
+    config::{Data, Field},
+    validation::Validation,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+const DEFS_REF: &str = "#/$defs/";
+
+/// Context needed to resolve types while building schemas
+pub struct SchemaContext<'a> {
+    /// Known data structs, used to reference nested types
+    pub data: &'a [Data],
+    /// Regex static names mapped to their patterns
+    pub regexes: &'a HashMap<String, String>,
+    /// The prefix used when referencing other data structs, e.g. `#/components/schemas/`
+    pub ref_prefix: &'a str,
+}
+
+/// Builds an object schema describing the given data struct
+pub fn data_schema(data: &Data, ctx: &SchemaContext) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for field in data.fields.iter() {
+        properties.insert(field.wire_name().to_string(), field_schema(field, ctx));
+        if field.required {
+            required.push(Value::String(field.wire_name().to_string()));
+        }
+    }
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+    });
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+    schema
+}
+
+/// Builds a standalone JSON Schema of the data struct. The `known` structs its fields refer to, and
+/// the ones those refer to, end up in its `$defs`.
+pub fn json_schema(data: &Data, known: &[Data], regexes: &HashMap<String, String>) -> Value {
+    let ctx = SchemaContext {
+        data: known,
+        regexes,
+        ref_prefix: DEFS_REF,
+    };
+    let mut schema = json!({
+        "$schema": JSON_SCHEMA_DIALECT,
+        "title": data.id,
+    });
+    if let (Value::Object(schema), Value::Object(body)) = (&mut schema, data_schema(data, &ctx)) {
+        schema.extend(body);
+    }
+
+    let mut defs = Map::new();
+    let mut pending = nested(data, known);
+    while let Some(d) = pending.pop() {
+        if defs.contains_key(&d.id) {
+            continue;
+        }
+        defs.insert(d.id.clone(), data_schema(d, &ctx));
+        pending.extend(nested(d, known));
+    }
+    if !defs.is_empty() {
+        schema["$defs"] = Value::Object(defs);
+    }
+    schema
+}
+
+/// The known data structs the fields of the struct refer to
+fn nested<'a>(data: &Data, known: &'a [Data]) -> Vec<&'a Data> {
+    let names = data
+        .fields
+        .iter()
+        .flat_map(|f| f.ty.split(|c: char| !c.is_alphanumeric() && c != '_'))
+        .collect::<Vec<_>>();
+    known
+        .iter()
+        .filter(|k| names.contains(&k.id.as_str()))
+        .collect()
+}
+
+/// Builds the schema for a single field, including its validation constraints
+pub fn field_schema(field: &Field, ctx: &SchemaContext) -> Value {
+    let mut schema = type_schema(&field.ty, ctx);
+    let validations = Validation::parse_all(&field.validation);
+    // Constraints apply to the inner type of optionals
+    let target = match schema.get_mut("anyOf") {
+        Some(Value::Array(variants)) => &mut variants[0],
+        _ => &mut schema,
+    };
+    for v in validations.iter() {
+        apply_validation(target, v, ctx);
+    }
+    schema
+}
+
+/// Maps a rust type as written by the analyzer, e.g. `Option<Vec<String>>`, to a schema
+pub fn type_schema(ty: &str, ctx: &SchemaContext) -> Value {
+    let ty = ty.trim();
+    if let Some(inner) = generic_inner(ty, "Option") {
+        return json!({ "anyOf": [type_schema(inner, ctx), { "type": "null" }] });
+    }
+    if let Some(inner) = generic_inner(ty, "Vec").or_else(|| generic_inner(ty, "HashSet")) {
+        return json!({ "type": "array", "items": type_schema(inner, ctx) });
+    }
+    if let Some(inner) = generic_inner(ty, "HashMap").or_else(|| generic_inner(ty, "BTreeMap")) {
+        let value = inner.split_once(',').map_or(inner, |(_, v)| v);
+        return json!({ "type": "object", "additionalProperties": type_schema(value, ctx) });
+    }
+    if let Some(inner) = generic_inner(ty, "Box") {
+        return type_schema(inner, ctx);
+    }
+    match ty {
+        "String" | "str" | "&str" | "char" => json!({ "type": "string" }),
+        "bool" => json!({ "type": "boolean" }),
+        "u8" => json!({ "type": "integer", "minimum": 0, "maximum": u8::MAX }),
+        "u16" => json!({ "type": "integer", "minimum": 0, "maximum": u16::MAX }),
+        "u32" => json!({ "type": "integer", "minimum": 0, "maximum": u32::MAX }),
+        "u64" | "u128" | "usize" => json!({ "type": "integer", "minimum": 0 }),
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => json!({ "type": "integer" }),
+        "f32" | "f64" => json!({ "type": "number" }),
+        "Uuid" => json!({ "type": "string", "format": "uuid" }),
+        "NaiveDateTime" | "DateTime" => json!({ "type": "string", "format": "date-time" }),
+        "NaiveDate" => json!({ "type": "string", "format": "date" }),
+        "Value" => json!({}),
+        other => match ctx.data.iter().find(|d| d.id == other) {
+            Some(d) => json!({ "$ref": format!("{}{}", ctx.ref_prefix, d.id) }),
+            // Types defined outside of data.rs, we can't know their shape
+            None => json!({ "description": format!("Rust type `{other}`") }),
+        },
+    }
+}
+
+fn apply_validation(schema: &mut Value, validation: &Validation, ctx: &SchemaContext) {
+    let is_array = schema["type"] == "array";
+    let (min_key, max_key) = if is_array {
+        ("minItems", "maxItems")
+    } else {
+        ("minLength", "maxLength")
+    };
+    match validation {
+        Validation::Length { min, max, equal } => {
+            if let Some(eq) = equal {
+                schema[min_key] = json!(eq);
+                schema[max_key] = json!(eq);
+            }
+            if let Some(min) = min {
+                schema[min_key] = json!(min);
+            }
+            if let Some(max) = max {
+                schema[max_key] = json!(max);
+            }
+        }
+        Validation::Range { min, max } => {
+            if let Some(min) = min {
+                schema["minimum"] = number(*min);
+            }
+            if let Some(max) = max {
+                schema["maximum"] = number(*max);
+            }
+        }
+        Validation::Regex(name) => match ctx.regexes.get(name) {
+            Some(pattern) => schema["pattern"] = json!(pattern),
+            None => schema["x-regex"] = json!(name),
+        },
+        Validation::Email => schema["format"] = json!("email"),
+        Validation::Url => schema["format"] = json!("uri"),
+        Validation::Custom(f) => schema["x-custom-validator"] = json!(f),
+        Validation::Other(raw) => schema["x-validate"] = json!(raw),
+    }
+}
+
+/// Keeps integral bounds as integers in the output
+fn number(n: f64) -> Value {
+    if n.fract() == 0. && n.abs() < i64::MAX as f64 {
+        json!(n as i64)
+    } else {
+        json!(n)
+    }
+}
+
+/// Returns the inner type of `Wrapper<Inner>` if the type is wrapped by the given wrapper
+pub fn generic_inner<'a>(ty: &'a str, wrapper: &str) -> Option<&'a str> {
+    ty.strip_prefix(wrapper)?
+        .trim()
+        .strip_prefix('<')?
+        .strip_suffix('>')
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, ty: &str, required: bool, validation: &[&str]) -> Field {
+        Field {
+            name: name.to_string(),
+            ty: ty.to_string(),
+            required,
+            validation: validation.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn builds_standalone_schemas_with_nested_defs() {
+        let known = [
+            Data {
+                id: "Order".to_string(),
+                fields: vec![
+                    field("email", "String", true, &["email"]),
+                    field("items", "Vec<Item>", true, &["length(min = 1)"]),
+                ],
+            },
+            Data {
+                id: "Item".to_string(),
+                fields: vec![field("amount", "Option<u16>", false, &["range(min = 1)"])],
+            },
+        ];
+        let schema = json_schema(&known[0], &known, &HashMap::new());
+        assert_eq!(schema["$schema"], JSON_SCHEMA_DIALECT);
+        assert_eq!(schema["required"], json!(["email", "items"]));
+        assert_eq!(schema["properties"]["email"]["format"], "email");
+        assert_eq!(
+            schema["properties"]["items"],
+            json!({ "type": "array", "items": { "$ref": "#/$defs/Item" }, "minItems": 1 })
+        );
+        assert_eq!(
+            schema["$defs"]["Item"]["properties"]["amount"]["anyOf"][0],
+            json!({ "type": "integer", "minimum": 1, "maximum": 65535 })
+        );
+    }
+}
@@ -3,21 +3,30 @@ mod boiler;
 mod commands;
 mod config;
 mod error;
+mod export;
+mod migration;
+mod secrets;
+mod settings;
+mod validation;
 
 use crate::analyzer::analyze;
+use crate::boiler::files::{dry_run, write_mode};
 use crate::commands::alx::{Alx, Command};
-use crate::commands::generate::{handle_gen_mw, handle_gen_route};
-use crate::commands::migration::{
-    migration_generate, migration_redo_all, migration_rev, migration_run,
+use crate::commands::generate::{
+    handle_gen_client, handle_gen_from_spec, handle_gen_handler, handle_gen_integration, handle_gen_mw,
+    handle_gen_repository, handle_gen_route, handle_gen_tests,
 };
+use crate::commands::route::{handle_add_contract, RouteSubcommand};
+use crate::settings::settings;
 use clap::Parser;
+use colored::Colorize;
 use commands::generate::GenerateSubcommand;
 use std::sync::atomic::AtomicBool;
 
 pub const INDENT: &str = "    ";
 pub const DEFAULT_API_PATH: &str = "server/src/api";
-pub const DEFAULT_MIDDLEWARE_PATH: &str = "server/src/api/middleware";
-pub const DEFAULT_ROUTER_PATH: &str = "server/src/api/router";
+pub const DEFAULT_STORE_PATH: &str = "infrastructure/src/store";
+pub const DEFAULT_MIGRATIONS_PATH: &str = "infrastructure/src/store/adapters/postgres/migrations";
 pub const ROUTE_FILES: [&str; 7] = [
     "contract",
     "data",
@@ -32,48 +41,340 @@ static VERBOSE: AtomicBool = AtomicBool::new(false);
 
 pub fn main() {
     let alx = Alx::parse();
-    println!("{}", alx.command);
+    if let Err(e) = settings::load() {
+        println!("{}", e.to_string().red());
+        std::process::exit(1);
+    }
+    // The graph is meant to be piped when printed to stdout
+    if !matches!(alx.command, Command::Graph(ref opts) if opts.out.is_none()) {
+        println!("{}", alx.command);
+    }
     match alx.command {
+        Command::New(opts) => {
+            verbose(opts.verbose);
+            write_mode(&opts.write);
+            if let Err(e) = commands::new::handle(opts) {
+                println!("{}", e.to_string().red());
+                std::process::exit(1);
+            }
+        }
         Command::Generate(cmd) | Command::Gen(cmd) | Command::G(cmd) => match cmd.subject {
             GenerateSubcommand::Route(args) | GenerateSubcommand::R(args) => {
                 verbose(args.verbose);
+                write_mode(&args.write);
                 let path = match args.path {
                     Some(ref p) => p.to_string(),
-                    None => DEFAULT_ROUTER_PATH.to_string(),
+                    None => settings().paths.router.clone(),
                 };
-                handle_gen_route(args, &path);
+                if let Err(e) = handle_gen_route(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
             }
             GenerateSubcommand::Middleware(args) | GenerateSubcommand::MW(args) => {
                 verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.middleware.clone(),
+                };
+                if let Err(e) = handle_gen_mw(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            GenerateSubcommand::Client(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.api.clone(),
+                };
+                if let Err(e) = handle_gen_client(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            GenerateSubcommand::FromSpec(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
                 let path = match args.path {
                     Some(ref p) => p.to_string(),
-                    None => DEFAULT_MIDDLEWARE_PATH.to_string(),
+                    None => settings().paths.router.clone(),
                 };
-                handle_gen_mw(args, &path);
+                if let Err(e) = handle_gen_from_spec(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            GenerateSubcommand::Handler(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.router.clone(),
+                };
+                if let Err(e) = handle_gen_handler(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            GenerateSubcommand::Tests(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.router.clone(),
+                };
+                if let Err(e) = handle_gen_tests(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            GenerateSubcommand::Integration(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.api.clone(),
+                };
+                if let Err(e) = handle_gen_integration(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            GenerateSubcommand::Repository(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.store.clone(),
+                };
+                if let Err(e) = handle_gen_repository(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        },
+        Command::Route(cmd) => match cmd.command {
+            RouteSubcommand::Gen(args) | RouteSubcommand::G(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.router.clone(),
+                };
+                if let Err(e) = handle_gen_route(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+            RouteSubcommand::AddContract(args) | RouteSubcommand::AC(args) => {
+                verbose(args.verbose);
+                write_mode(&args.write);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.router.clone(),
+                };
+                if let Err(e) = handle_add_contract(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
             }
         },
         Command::Analyze(args) | Command::Anal(args) => {
             verbose(args.verbose);
             let path = match args.path {
                 Some(ref p) => p.to_string(),
-                None => DEFAULT_API_PATH.to_string(),
+                None => settings().paths.api.clone(),
             };
-            analyze::handle(args, &path);
+            match analyze::handle(args, &path) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => {}
+                Err(e) => {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Diff(args) => {
+            verbose(args.verbose);
+            let path = match args.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            match commands::diff::handle(args, &path) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => {}
+                Err(e) => {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Remove(cmd) => match cmd.subject {
+            commands::remove::RemoveSubcommand::Route(args) => {
+                verbose(args.verbose);
+                let path = match args.path {
+                    Some(ref p) => p.to_string(),
+                    None => settings().paths.router.clone(),
+                };
+                if let Err(e) = commands::remove::handle_remove_route(args, &path) {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        },
+        Command::Doctor(opts) => {
+            verbose(opts.verbose);
+            let path = match opts.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            match commands::doctor::handle(opts, &path) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => {}
+                Err(e) => {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Lint(opts) => {
+            verbose(opts.verbose);
+            let path = match opts.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            match commands::lint::handle(opts, &path) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => {}
+                Err(e) => {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Audit(opts) => {
+            verbose(opts.verbose);
+            let path = match opts.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            match commands::audit::handle(opts, &path) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => {}
+                Err(e) => {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Graph(args) => {
+            verbose(args.verbose);
+            let path = match args.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            if let Err(e) = commands::graph::handle(args, &path) {
+                println!("{}", e.to_string().red());
+                std::process::exit(1);
+            }
+        }
+        Command::Openapi(args) => {
+            verbose(args.verbose);
+            let path = match args.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            if let Err(e) = commands::openapi::handle(args, &path) {
+                println!("{}", e.to_string().red());
+                std::process::exit(1);
+            }
+        }
+        Command::Schema(args) => {
+            verbose(args.verbose);
+            let path = match args.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            if let Err(e) = commands::schema::handle(args, &path) {
+                println!("{}", e.to_string().red());
+                std::process::exit(1);
+            }
+        }
+        Command::Export(cmd) => {
+            let args = cmd.format.args();
+            verbose(args.verbose);
+            let path = match args.path {
+                Some(ref p) => p.to_string(),
+                None => settings().paths.api.clone(),
+            };
+            if let Err(e) = commands::export::handle(cmd.format, &path) {
+                println!("{}", e.to_string().red());
+                std::process::exit(1);
+            }
         }
         Command::Envex(args) => {
+            write_mode(&args.write);
             commands::envex::envex(args.path);
         }
-        Command::Migration(c) | Command::Mig(c) | Command::M(c) => match c.action {
-            commands::migration::MigrationSubcommand::Gen(args) => migration_generate(args),
-            commands::migration::MigrationSubcommand::Run => migration_run(),
-            commands::migration::MigrationSubcommand::Rev => migration_rev(),
-            commands::migration::MigrationSubcommand::Redo(args) => migration_redo_all(args),
+        Command::Env(cmd) => match cmd.action {
+            commands::env::EnvSubcommand::Check(args) => {
+                verbose(args.verbose);
+                match commands::env::handle_check(args) {
+                    Ok(true) => std::process::exit(1),
+                    Ok(false) => {}
+                    Err(e) => {
+                        println!("{}", e.to_string().red());
+                        std::process::exit(1);
+                    }
+                }
+            }
         },
+        Command::Secrets(cmd) => {
+            verbose(cmd.action.verbose());
+            match commands::secrets::handle(cmd.action) {
+                Ok(true) => std::process::exit(1),
+                Ok(false) => {}
+                Err(e) => {
+                    println!("{}", e.to_string().red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Migration(c) | Command::Mig(c) | Command::M(c) => {
+            verbose(c.action.verbose());
+            if let Some(args) = c.action.write() {
+                write_mode(args);
+            }
+            let path = c
+                .action
+                .path()
+                .unwrap_or(&settings().paths.migrations)
+                .to_string();
+            if let Err(e) = commands::migration::handle(c.action, &path) {
+                println!("{}", e.to_string().red());
+                std::process::exit(1);
+            }
+        }
+    }
+    if dry_run() {
+        println!("{}", "Dry run, nothing was written".yellow());
     }
 }
 
 fn uppercase(s: &str) -> String {
-    format!("{}{}", &s[..1].to_string().to_uppercase(), &s[1..])
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `blog_post` to `BlogPost`
+fn pascal_case(s: &str) -> String {
+    s.split('_').filter(|w| !w.is_empty()).map(uppercase).collect()
 }
 
 #[inline]
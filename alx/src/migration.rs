@@ -0,0 +1,252 @@
+//! In process migration engine. Migrations use the diesel CLI layout, a directory per migration
+//! named `<version>_<name>` holding an `up.sql` and a `down.sql`, and applied versions are tracked
+//! in the same `__diesel_schema_migrations` table so the engine and the diesel CLI can be used
+//! interchangeably.
+use crate::{boiler::files, error::AlxError};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    migration::{Migration, MigrationSource},
+    pg::{Pg, PgConnection},
+    sql_query,
+    sql_types::{Text, Timestamp},
+    Connection, QueryableByName, RunQueryDsl,
+};
+use diesel_migrations::{FileBasedMigrations, MigrationHarness};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A migration found in the migrations directory
+#[derive(Debug, Clone)]
+pub struct Local {
+    /// The directory name, i.e. `2022-10-09-075159_create_users`
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+}
+
+impl Local {
+    pub fn up(&self) -> Result<String, AlxError> {
+        Ok(fs::read_to_string(self.path.join("up.sql"))?)
+    }
+
+    pub fn down(&self) -> Result<String, AlxError> {
+        Ok(fs::read_to_string(self.path.join("down.sql"))?)
+    }
+}
+
+/// A migration version recorded in the database
+#[derive(Debug, Clone, QueryableByName)]
+pub struct Applied {
+    #[diesel(sql_type = Text)]
+    pub version: String,
+    #[diesel(sql_type = Timestamp)]
+    pub run_on: NaiveDateTime,
+}
+
+/// The version of a migration the same way diesel reads it, i.e. `20221009075159` for
+/// `2022-10-09-075159_create_users`. Accepts both the directory name and the version itself.
+pub fn version(name: &str) -> String {
+    name.split('_').next().unwrap_or_default().replace('-', "")
+}
+
+/// Lists the migrations in the directory sorted by their version
+pub fn local(dir: &str) -> Result<Vec<Local>, AlxError> {
+    if !Path::new(dir).is_dir() {
+        return Err(AlxError::Migration(format!(
+            "migrations directory {dir} does not exist"
+        )));
+    }
+    let mut migrations = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.join("up.sql").is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        migrations.push(Local {
+            version: version(&name),
+            name,
+            path,
+        });
+    }
+    migrations.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(migrations)
+}
+
+/// Writes a new migration to the directory, named the same way the diesel CLI names them.
+/// Returns the path of the migration.
+pub fn create(dir: &str, name: &str, up: &str, down: &str) -> Result<String, AlxError> {
+    let path = format!("{}/{}_{}", dir, Utc::now().format("%Y-%m-%d-%H%M%S"), name);
+    files::create_dir_all(&path)?;
+    files::write(format!("{path}/up.sql"), up)?;
+    files::write(format!("{path}/down.sql"), down)?;
+    Ok(path)
+}
+
+/// The pending migrations to run, up to and including `to` if given
+pub fn plan_run<'a>(
+    local: &'a [Local],
+    applied: &[Applied],
+    to: Option<&str>,
+) -> Result<Vec<&'a Local>, AlxError> {
+    let to = match to {
+        Some(to) => Some(known(local, to)?),
+        None => None,
+    };
+    Ok(local
+        .iter()
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .filter(|m| to.as_ref().is_none_or(|to| m.version <= *to))
+        .collect())
+}
+
+/// The applied migrations to revert, latest first. Without `to` only the last applied migration
+/// is reverted, otherwise every migration applied after `to`.
+pub fn plan_revert<'a>(
+    local: &'a [Local],
+    applied: &[Applied],
+    to: Option<&str>,
+) -> Result<Vec<&'a Local>, AlxError> {
+    let mut applied = applied.iter().map(|a| &a.version).collect::<Vec<_>>();
+    applied.sort_by(|a, b| b.cmp(a));
+    let reverted = match to {
+        Some(to) => {
+            let to = known(local, to)?;
+            applied.into_iter().filter(|v| **v > to).collect()
+        }
+        None => applied.into_iter().take(1).collect::<Vec<_>>(),
+    };
+    reverted
+        .into_iter()
+        .map(|v| {
+            local.iter().find(|m| m.version == *v).ok_or_else(|| {
+                AlxError::Migration(format!(
+                    "applied migration {v} is missing from the migrations directory"
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Normalizes `to` to a version, making sure it exists
+fn known(local: &[Local], to: &str) -> Result<String, AlxError> {
+    let to = version(to);
+    match local.iter().any(|m| m.version == to) {
+        true => Ok(to),
+        false => Err(AlxError::Migration(format!(
+            "unknown migration version {to}"
+        ))),
+    }
+}
+
+/// Runs and reverts migrations from the migrations directory on a database
+pub struct Engine {
+    conn: PgConnection,
+    source: FileBasedMigrations,
+}
+
+impl Engine {
+    pub fn connect(url: &str, dir: &str) -> Result<Self, AlxError> {
+        let conn = PgConnection::establish(url)?;
+        let source =
+            FileBasedMigrations::from_path(dir).map_err(|e| AlxError::Migration(e.to_string()))?;
+        Ok(Self { conn, source })
+    }
+
+    /// The migrations recorded in the database, creating the migrations table if it doesn't exist
+    pub fn applied(&mut self) -> Result<Vec<Applied>, AlxError> {
+        MigrationHarness::applied_migrations(&mut self.conn)
+            .map_err(|e| AlxError::Migration(e.to_string()))?;
+        Ok(
+            sql_query("SELECT version, run_on FROM __diesel_schema_migrations ORDER BY version")
+                .load(&mut self.conn)?,
+        )
+    }
+
+    /// Runs the migration in a transaction and records it
+    pub fn run(&mut self, migration: &Local) -> Result<(), AlxError> {
+        let source = self.find(migration)?;
+        self.conn
+            .run_migration(source.as_ref())
+            .map_err(|e| AlxError::Migration(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Reverts the migration in a transaction and removes its record
+    pub fn revert(&mut self, migration: &Local) -> Result<(), AlxError> {
+        let source = self.find(migration)?;
+        self.conn
+            .revert_migration(source.as_ref())
+            .map_err(|e| AlxError::Migration(e.to_string()))?;
+        Ok(())
+    }
+
+    fn find(&self, migration: &Local) -> Result<Box<dyn Migration<Pg>>, AlxError> {
+        MigrationSource::<Pg>::migrations(&self.source)
+            .map_err(|e| AlxError::Migration(e.to_string()))?
+            .into_iter()
+            .find(|m| m.name().version().to_string() == migration.version)
+            .ok_or_else(|| AlxError::Migration(format!("unable to load {}", migration.name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migration(name: &str) -> Local {
+        Local {
+            name: name.to_string(),
+            version: version(name),
+            path: PathBuf::from(name),
+        }
+    }
+
+    fn applied(name: &str) -> Applied {
+        Applied {
+            version: version(name),
+            run_on: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn plans_migrations() {
+        let local = [
+            migration("00000000000000_diesel_initial_setup"),
+            migration("2022-10-09-075159_create_users"),
+            migration("2022-10-09-080209_create_sessions"),
+            migration("2023-01-01-000000_create_posts"),
+        ];
+        let applied = [
+            applied("00000000000000_diesel_initial_setup"),
+            applied("2022-10-09-075159_create_users"),
+        ];
+        let names = |plan: Vec<&Local>| plan.iter().map(|m| m.name.clone()).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(plan_run(&local, &applied, None).unwrap()),
+            [
+                "2022-10-09-080209_create_sessions",
+                "2023-01-01-000000_create_posts"
+            ]
+        );
+        assert_eq!(
+            names(plan_run(&local, &applied, Some("2022-10-09-080209")).unwrap()),
+            ["2022-10-09-080209_create_sessions"]
+        );
+        assert_eq!(
+            names(plan_revert(&local, &applied, None).unwrap()),
+            ["2022-10-09-075159_create_users"]
+        );
+        assert_eq!(
+            names(plan_revert(&local, &applied, Some("00000000000000")).unwrap()),
+            ["2022-10-09-075159_create_users"]
+        );
+        assert!(plan_run(&local, &applied, Some("20240101000000")).is_err());
+        assert!(plan_revert(&local[2..], &applied, None).is_err());
+    }
+}
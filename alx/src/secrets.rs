@@ -0,0 +1,342 @@
+//! Secrets in the .env file and key pairs in the encryption directory. Secrets are generated from
+//! random bytes encoded to text and used by the application as is, i.e. the HMAC and cookie keys
+//! are the bytes of the encoded string. Their strength is judged on that string.
+use crate::error::AlxError;
+use data_encoding::{Encoding, BASE32, BASE64, BASE64URL, HEXLOWER};
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rand::rand_bytes,
+    rsa::Rsa,
+    x509::{
+        extension::{BasicConstraints, SubjectAlternativeName, SubjectKeyIdentifier},
+        X509Builder, X509Name, X509NameBuilder, X509,
+    },
+};
+use std::{collections::HashSet, fmt::Display};
+
+/// Suffix of the key holding the value a secret had before it was rotated
+pub const PREVIOUS: &str = "_PREVIOUS";
+
+/// The bytes of the secret the application uses it with. `Key::from` in the cookie crate panics on
+/// anything shorter than 64 bytes and HMAC keys shouldn't be shorter than the 32 byte hash.
+const MIN_LENGTHS: [(&str, usize); 1] = [("COOKIE_SECRET", 64)];
+const MIN_LENGTH: usize = 32;
+/// Estimated bits of entropy below which a secret counts as weak
+const MIN_ENTROPY: f64 = 128.;
+
+/// The subject of the certificates openssl/openssl.sh generates
+const CERT_SUBJECT: [(&str, &str); 6] = [
+    ("C", "HR"),
+    ("ST", "OS"),
+    ("L", "Osijek"),
+    ("O", "Myco"),
+    ("OU", "Myco"),
+    ("CN", "localhost"),
+];
+const CERT_DAYS: u32 = 365;
+
+const PASSWORD_ALPHABET: &[u8; 60] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!@#$%^&*";
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SecretEncoding {
+    Hex,
+    B32,
+    B64,
+    B64u,
+}
+
+impl SecretEncoding {
+    fn encoding(&self) -> Encoding {
+        match self {
+            Self::Hex => HEXLOWER,
+            Self::B32 => BASE32,
+            Self::B64 => BASE64,
+            Self::B64u => BASE64URL,
+        }
+    }
+
+    /// Guesses the encoding of an existing secret so it can be rotated to the same kind of value
+    pub fn detect(value: &str) -> Self {
+        let all = |f: fn(char) -> bool| value.trim_end_matches('=').chars().all(f);
+        if all(|c| c.is_ascii_hexdigit()) {
+            Self::Hex
+        } else if all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c)) {
+            Self::B32
+        } else if value.contains(['-', '_']) {
+            Self::B64u
+        } else {
+            Self::B64
+        }
+    }
+
+    /// The number of random bytes encoded in a value of this length
+    pub fn decoded_len(&self, len: usize) -> usize {
+        match self {
+            Self::Hex => len / 2,
+            Self::B32 => len * 5 / 8,
+            Self::B64 | Self::B64u => len * 3 / 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum KeyAlgorithm {
+    Rsa,
+    /// P-256
+    Ec,
+    Ed25519,
+}
+
+impl KeyAlgorithm {
+    /// The directory the pair goes in by default. RSA keys go where the jwt module reads them.
+    pub fn default_dir(&self) -> &'static str {
+        match self {
+            Self::Rsa => "key_pair",
+            Self::Ec => "ec_key_pair",
+            Self::Ed25519 => "ed25519_key_pair",
+        }
+    }
+}
+
+/// What's wrong with a secret
+#[derive(Debug, PartialEq)]
+pub enum Weakness {
+    Empty,
+    TooShort { len: usize, min: usize },
+    LowEntropy { bits: usize },
+}
+
+impl Display for Weakness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty"),
+            Self::TooShort { len, min } => write!(f, "{len} bytes, needs at least {min}"),
+            Self::LowEntropy { bits } => {
+                write!(f, "about {bits} bits of entropy, needs {MIN_ENTROPY}")
+            }
+        }
+    }
+}
+
+/// Whether the key holds a secret judging by its name
+pub fn is_secret(key: &str) -> bool {
+    let key = key.to_uppercase();
+    !key.contains("URL")
+        && ["SECRET", "PASSWORD", "TOKEN", "PRIVATE", "KEY"]
+            .iter()
+            .any(|s| key.contains(s))
+}
+
+/// `len` random bytes in the encoding
+pub fn secret(len: usize, encoding: SecretEncoding) -> Result<String, AlxError> {
+    let mut buf = vec![0; len];
+    rand_bytes(&mut buf)?;
+    Ok(encoding.encoding().encode(&buf))
+}
+
+/// A random password of `len` letters and symbols
+pub fn password(len: usize) -> Result<String, AlxError> {
+    let mut pw = String::with_capacity(len);
+    let mut byte = [0];
+    while pw.len() < len {
+        rand_bytes(&mut byte)?;
+        // Rejecting the bytes past the last multiple of the alphabet keeps every character equally likely
+        if (byte[0] as usize) < 256 - 256 % PASSWORD_ALPHABET.len() {
+            pw.push(PASSWORD_ALPHABET[byte[0] as usize % PASSWORD_ALPHABET.len()] as char);
+        }
+    }
+    Ok(pw)
+}
+
+/// The private key as PKCS#8 and the public key as PEM, PKCS#1 for RSA like the jwt module expects
+/// and SubjectPublicKeyInfo otherwise
+pub fn key_pair(alg: KeyAlgorithm, bits: u32) -> Result<(Vec<u8>, Vec<u8>), AlxError> {
+    let key: PKey<Private> = match alg {
+        KeyAlgorithm::Rsa => {
+            let rsa = Rsa::generate(bits)?;
+            let public = rsa.public_key_to_pem_pkcs1()?;
+            return Ok((PKey::from_rsa(rsa)?.private_key_to_pem_pkcs8()?, public));
+        }
+        KeyAlgorithm::Ec => {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+            PKey::from_ec_key(EcKey::generate(&group)?)?
+        }
+        KeyAlgorithm::Ed25519 => PKey::generate_ed25519()?,
+    };
+    Ok((key.private_key_to_pem_pkcs8()?, key.public_key_to_pem()?))
+}
+
+/// The PEMs of a CA and of a certificate for the server signed by it
+#[derive(Debug)]
+pub struct Certificates {
+    pub ca_key: Vec<u8>,
+    pub ca: Vec<u8>,
+    pub key: Vec<u8>,
+    pub cert: Vec<u8>,
+}
+
+/// Does what openssl/openssl.sh does. The certificate is valid for localhost and 127.0.0.1 for a year.
+pub fn certificates(bits: u32) -> Result<Certificates, AlxError> {
+    let ca_key = PKey::from_rsa(Rsa::generate(bits)?)?;
+    let ca = certificate(&ca_key, None)?;
+    let key = PKey::from_rsa(Rsa::generate(bits)?)?;
+    let cert = certificate(&key, Some((&ca, &ca_key)))?;
+    Ok(Certificates {
+        ca_key: ca_key.private_key_to_pem_pkcs8()?,
+        ca: ca.to_pem()?,
+        key: key.private_key_to_pem_pkcs8()?,
+        cert: cert.to_pem()?,
+    })
+}
+
+/// A self signed CA if there's no issuer
+fn certificate(
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+) -> Result<X509, AlxError> {
+    let mut name = X509NameBuilder::new()?;
+    for (field, value) in CERT_SUBJECT {
+        name.append_entry_by_text(field, value)?;
+    }
+    let name: X509Name = name.build();
+    let mut serial = BigNum::new()?;
+    serial.rand(159, MsbOption::MAYBE_ZERO, false)?;
+
+    let mut cert = X509Builder::new()?;
+    cert.set_version(2)?;
+    let serial = serial.to_asn1_integer()?;
+    cert.set_serial_number(&serial)?;
+    cert.set_subject_name(&name)?;
+    cert.set_pubkey(key)?;
+    let (not_before, not_after) = (
+        Asn1Time::days_from_now(0)?,
+        Asn1Time::days_from_now(CERT_DAYS)?,
+    );
+    cert.set_not_before(&not_before)?;
+    cert.set_not_after(&not_after)?;
+    match issuer {
+        None => {
+            cert.set_issuer_name(&name)?;
+            cert.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+            let id = SubjectKeyIdentifier::new().build(&cert.x509v3_context(None, None))?;
+            cert.append_extension(id)?;
+            cert.sign(key, MessageDigest::sha256())?;
+        }
+        Some((ca, ca_key)) => {
+            cert.set_issuer_name(ca.subject_name())?;
+            let san = SubjectAlternativeName::new()
+                .dns("localhost")
+                .ip("127.0.0.1")
+                .build(&cert.x509v3_context(Some(ca), None))?;
+            cert.append_extension(san)?;
+            cert.sign(ca_key, MessageDigest::sha256())?;
+        }
+    }
+    Ok(cert.build())
+}
+
+/// The value without the quotes it may be declared with
+pub fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Checks the length of the secret against what the application needs and estimates its entropy
+/// from the number of distinct characters in it
+pub fn weakness(key: &str, value: &str) -> Option<Weakness> {
+    let value = unquote(value);
+    if value.is_empty() {
+        return Some(Weakness::Empty);
+    }
+    let min = MIN_LENGTHS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map_or(MIN_LENGTH, |(_, min)| *min);
+    if value.len() < min {
+        return Some(Weakness::TooShort {
+            len: value.len(),
+            min,
+        });
+    }
+    let distinct = value.chars().collect::<HashSet<_>>().len();
+    let bits = value.chars().count() as f64 * (distinct as f64).log2();
+    if bits < MIN_ENTROPY {
+        return Some(Weakness::LowEntropy {
+            bits: bits as usize,
+        });
+    }
+    None
+}
+
+/// Sets the key in the .env file, replacing its line if it's declared and appending it otherwise
+pub fn set_var(src: &str, key: &str, value: &str) -> String {
+    let line = format!("{key} = \"{value}\"");
+    let mut found = false;
+    let mut out = src
+        .lines()
+        .map(|l| {
+            let declared = l
+                .split_once('=')
+                .map(|(k, _)| k.trim().trim_start_matches("export ").trim());
+            match declared == Some(key) && !l.trim_start().starts_with('#') {
+                true => {
+                    found = true;
+                    line.clone()
+                }
+                false => l.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !found {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&line);
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_and_judges_secrets() {
+        let src = "# Secrets\nCOOKIE_SECRET = \"short\"\nPORT=8080\n";
+        let src = set_var(src, "COOKIE_SECRET_PREVIOUS", "short");
+        let src = set_var(&src, "COOKIE_SECRET", "abc");
+        assert_eq!(
+            src,
+            "# Secrets\nCOOKIE_SECRET = \"abc\"\nPORT=8080\nCOOKIE_SECRET_PREVIOUS = \"short\"\n"
+        );
+
+        assert_eq!(
+            weakness("COOKIE_SECRET", "\"abc\""),
+            Some(Weakness::TooShort { len: 3, min: 64 })
+        );
+        assert_eq!(
+            weakness("REG_TOKEN_SECRET", &"ab".repeat(20)),
+            Some(Weakness::LowEntropy { bits: 40 })
+        );
+        let hex = secret(32, SecretEncoding::Hex).unwrap();
+        assert_eq!(SecretEncoding::detect(&hex), SecretEncoding::Hex);
+        assert_eq!(weakness("COOKIE_SECRET", &hex), None);
+        let b64 = secret(32, SecretEncoding::B64).unwrap();
+        assert_eq!(weakness("REG_TOKEN_SECRET", &b64), None);
+        assert!(weakness("COOKIE_SECRET", &b64).is_some());
+        assert!(is_secret("REG_TOKEN_SECRET") && !is_secret("POSTGRES_URL"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Project settings read from the `alx.toml` in the working directory or the closest of its parents,
+//! so alx can be run from anywhere in the project and in projects that keep the API somewhere other
+//! than `server/src/api`. Paths are relative to the directory the file is in. Everything is
+//! optional, i.e.
+//!
+//! ```toml
+//! [paths]
+//! api = "services/server/src/api"
+//! store = "services/infrastructure/src/store"
+//!
+//! [lock]
+//! format = "json"
+//! dir = "docs"
+//!
+//! [route]
+//! contracts = ["repo"]
+//! files = ["contract", "domain", "handler", "infrastructure", "mod", "setup"]
+//! ```
+use crate::{
+    boiler::plate::BoilerType, config::ConfigFormat, error::AlxError, DEFAULT_API_PATH,
+    DEFAULT_MIGRATIONS_PATH, DEFAULT_STORE_PATH, MW_FILES, ROUTE_FILES,
+};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+pub const SETTINGS_FILE: &str = "alx.toml";
+/// Name of the lock file without the extension
+const LOCK_NAME: &str = "alx_lock";
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AlxToml {
+    paths: PathsToml,
+    lock: LockToml,
+    route: GeneratorToml,
+    middleware: GeneratorToml,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PathsToml {
+    api: Option<String>,
+    router: Option<String>,
+    middleware: Option<String>,
+    store: Option<String>,
+    migrations: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LockToml {
+    format: Option<ConfigFormat>,
+    dir: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct GeneratorToml {
+    files: Option<Vec<String>>,
+    contracts: Option<Vec<String>>,
+    module_visibility: Option<String>,
+    visibility: Option<String>,
+    entry_visibility: Option<String>,
+    contract_visibility: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    /// The directory the alx.toml is in
+    root: Option<PathBuf>,
+    pub paths: Paths,
+    pub lock: Lock,
+    route: Generator,
+    middleware: Generator,
+}
+
+#[derive(Debug)]
+pub struct Paths {
+    pub api: String,
+    pub router: String,
+    pub middleware: String,
+    pub store: String,
+    pub migrations: String,
+}
+
+#[derive(Debug)]
+pub struct Lock {
+    /// What `alx analyze` writes when no format is given
+    pub format: ConfigFormat,
+    pub dir: String,
+}
+
+/// What the route and middleware generators write
+#[derive(Debug)]
+pub struct Generator {
+    /// The files of the module without the extension
+    pub files: Vec<String>,
+    /// Contracts the module gets when none are given
+    pub contracts: Vec<String>,
+    /// Of the module in its parent's mod.rs
+    pub module_visibility: String,
+    /// Of the files in the module's mod.rs
+    pub visibility: String,
+    /// Of setup.rs for routes and interceptor.rs for middleware, which are used outside the module
+    pub entry_visibility: String,
+    /// Of the contract traits
+    pub contract_visibility: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::resolve(AlxToml::default(), None)
+    }
+}
+
+impl Settings {
+    fn read(file: &Path) -> Result<Self, AlxError> {
+        let toml = toml::from_str(&fs::read_to_string(file)?)
+            .map_err(|e| AlxError::Settings(format!("{}: {e}", file.display())))?;
+        Ok(Self::resolve(toml, Some(file)))
+    }
+
+    fn resolve(toml: AlxToml, file: Option<&Path>) -> Self {
+        let root = file.and_then(Path::parent);
+        let path =
+            |p: Option<String>, default: &str| in_root(root, p.as_deref().unwrap_or(default));
+        let api = path(toml.paths.api, DEFAULT_API_PATH);
+        let paths = Paths {
+            router: path(toml.paths.router, &format!("{api}/router")),
+            middleware: path(toml.paths.middleware, &format!("{api}/middleware")),
+            store: path(toml.paths.store, DEFAULT_STORE_PATH),
+            migrations: path(toml.paths.migrations, DEFAULT_MIGRATIONS_PATH),
+            api,
+        };
+        let lock = Lock {
+            format: toml.lock.format.unwrap_or(ConfigFormat::Both),
+            dir: path(toml.lock.dir, "."),
+        };
+        Self {
+            root: root.map(Path::to_path_buf),
+            paths,
+            lock,
+            route: Generator::resolve(toml.route, BoilerType::Route),
+            middleware: Generator::resolve(toml.middleware, BoilerType::MW),
+        }
+    }
+
+    /// The directory the alx.toml is in, the working directory without one. The `.alx` directory
+    /// holding templates, the lint config and the audit allowlist is in here.
+    pub fn root(&self) -> &Path {
+        self.root.as_deref().unwrap_or(Path::new("."))
+    }
+
+    /// The path relative to the root
+    pub fn project_path(&self, path: &str) -> String {
+        in_root(self.root.as_deref(), path)
+    }
+
+    pub fn generator(&self, ty: BoilerType) -> &Generator {
+        match ty {
+            BoilerType::Route => &self.route,
+            BoilerType::MW => &self.middleware,
+        }
+    }
+}
+
+impl Paths {
+    /// The router directory of the API, the configured one if it's the configured API
+    pub fn router_of(&self, api: &str) -> String {
+        match api == self.api {
+            true => self.router.clone(),
+            false => format!("{api}/router"),
+        }
+    }
+
+    /// The middleware directory of the API, the configured one if it's the configured API
+    pub fn middleware_of(&self, api: &str) -> String {
+        match api == self.api {
+            true => self.middleware.clone(),
+            false => format!("{api}/middleware"),
+        }
+    }
+}
+
+impl Lock {
+    /// The lock file with the extension
+    pub fn file(&self, ext: &str) -> String {
+        Path::new(&self.dir)
+            .join(format!("{LOCK_NAME}.{ext}"))
+            .display()
+            .to_string()
+    }
+}
+
+impl Generator {
+    fn resolve(toml: GeneratorToml, ty: BoilerType) -> Self {
+        let (files, contract_visibility) = match ty {
+            BoilerType::Route => (&ROUTE_FILES[..], "pub(super)"),
+            BoilerType::MW => (&MW_FILES[..], "pub(crate)"),
+        };
+        let files = toml
+            .files
+            .unwrap_or_else(|| files.iter().map(ToString::to_string).collect());
+        Self {
+            files,
+            contracts: toml.contracts.unwrap_or_default(),
+            module_visibility: toml
+                .module_visibility
+                .unwrap_or_else(|| "pub(crate)".to_string()),
+            visibility: toml.visibility.unwrap_or_else(|| "pub(super)".to_string()),
+            entry_visibility: toml
+                .entry_visibility
+                .unwrap_or_else(|| "pub(crate)".to_string()),
+            contract_visibility: toml
+                .contract_visibility
+                .unwrap_or_else(|| contract_visibility.to_string()),
+        }
+    }
+
+    /// The visibility of the file in the module's mod.rs
+    pub fn file_visibility(&self, file: &str, ty: BoilerType) -> &str {
+        match file == ty.entry() {
+            true => &self.entry_visibility,
+            false => &self.visibility,
+        }
+    }
+}
+
+/// The path as given if there are no settings or they're in the working directory so messages stay
+/// short, joined to their directory otherwise
+fn in_root(root: Option<&Path>, path: &str) -> String {
+    match (root, env::current_dir()) {
+        (None, _) => path.to_string(),
+        (Some(root), Ok(cwd)) if cwd == root => path.to_string(),
+        (Some(root), _) => root.join(path).display().to_string(),
+    }
+}
+
+/// The closest alx.toml walking up from the directory
+fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(SETTINGS_FILE))
+        .find(|f| f.is_file())
+}
+
+/// Reads the alx.toml, if there is one, for the rest of the run
+pub fn load() -> Result<(), AlxError> {
+    let settings = match find(&env::current_dir()?) {
+        Some(file) => Settings::read(&file)?,
+        None => Settings::default(),
+    };
+    // Only fails if the settings were already read
+    let _ = SETTINGS.set(settings);
+    Ok(())
+}
+
+/// The settings loaded at start up, the defaults if they weren't
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_settings_relative_to_the_file() {
+        let toml = toml::from_str(
+            r#"
+            [paths]
+            api = "services/server/src/api"
+
+            [lock]
+            format = "json"
+
+            [middleware]
+            contracts = ["cache"]
+            entry-visibility = "pub"
+            "#,
+        )
+        .unwrap();
+        let settings = Settings::resolve(toml, Some(Path::new("/monorepo/alx.toml")));
+        assert_eq!(settings.paths.api, "/monorepo/services/server/src/api");
+        assert_eq!(
+            settings.paths.router_of(&settings.paths.api),
+            "/monorepo/services/server/src/api/router"
+        );
+        assert_eq!(settings.paths.router_of("api"), "api/router");
+        assert_eq!(settings.lock.file("json"), "/monorepo/./alx_lock.json");
+        assert_eq!(settings.root(), Path::new("/monorepo"));
+        assert_eq!(
+            settings.project_path(".alx/lint.yaml"),
+            "/monorepo/.alx/lint.yaml"
+        );
+        assert_eq!(
+            Settings::default().project_path(".alx/lint.yaml"),
+            ".alx/lint.yaml"
+        );
+
+        let mw = settings.generator(BoilerType::MW);
+        assert_eq!(mw.contracts, ["cache"]);
+        assert_eq!(mw.files, MW_FILES);
+        assert_eq!(mw.file_visibility("interceptor", BoilerType::MW), "pub");
+        assert_eq!(mw.file_visibility("domain", BoilerType::MW), "pub(super)");
+        assert_eq!(mw.contract_visibility, "pub(crate)");
+
+        let bad = toml::from_str::<AlxToml>("[paths]\nrouters = \"x\"\n");
+        assert!(bad.is_err());
+    }
+}
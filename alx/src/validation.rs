@@ -0,0 +1,274 @@
+//! Structured view over the raw `#[validate(..)]` tokens captured by the analyzer
+use proc_macro2::{TokenStream, TokenTree};
+use std::{collections::HashMap, fs, path::Path};
+use syn::{Lit, Meta, NestedMeta};
+
+/// A single validator rule found on a data field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Validation {
+    /// `length(min = .., max = .., equal = ..)`
+    Length {
+        min: Option<u64>,
+        max: Option<u64>,
+        equal: Option<u64>,
+    },
+    /// `range(min = .., max = ..)`
+    Range {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// `regex = "STATIC_NAME"`, holds the name of the static the regex is stored in
+    Regex(String),
+    Email,
+    Url,
+    /// `custom = "function_name"`
+    Custom(String),
+    /// Anything we don't know how to map, kept verbatim
+    Other(String),
+}
+
+impl Validation {
+    /// Parses every rule contained in the raw validation string captured from the attribute.
+    /// A single attribute can contain multiple rules, e.g. `length(min = 1), email`.
+    pub fn parse(raw: &str) -> Vec<Self> {
+        let metas = match syn::parse_str::<MetaList>(raw) {
+            Ok(list) => list.0,
+            Err(_) => return vec![Self::Other(raw.to_string())],
+        };
+        metas.iter().map(Self::from_meta).collect()
+    }
+
+    /// Parses all the validation strings of a field
+    pub fn parse_all(raw: &[String]) -> Vec<Self> {
+        raw.iter().flat_map(|v| Self::parse(v)).collect()
+    }
+
+    fn from_meta(meta: &Meta) -> Self {
+        let name = meta
+            .path()
+            .get_ident()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        match (name.as_str(), meta) {
+            ("email", _) => Self::Email,
+            ("url", _) => Self::Url,
+            ("length", Meta::List(list)) => {
+                let args = name_values(&list.nested);
+                Self::Length {
+                    min: args.get("min").and_then(lit_u64),
+                    max: args.get("max").and_then(lit_u64),
+                    equal: args.get("equal").and_then(lit_u64),
+                }
+            }
+            ("range", Meta::List(list)) => {
+                let args = name_values(&list.nested);
+                Self::Range {
+                    min: args.get("min").and_then(lit_f64),
+                    max: args.get("max").and_then(lit_f64),
+                }
+            }
+            ("regex", Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(ref s) => Self::Regex(s.value()),
+                _ => Self::Other(quote_meta(meta)),
+            },
+            // regex(path = "..") form
+            ("regex", Meta::List(list)) => match name_values(&list.nested).get("path") {
+                Some(Lit::Str(s)) => Self::Regex(s.value()),
+                _ => Self::Other(quote_meta(meta)),
+            },
+            ("custom", Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(ref s) => Self::Custom(s.value()),
+                _ => Self::Other(quote_meta(meta)),
+            },
+            ("custom", Meta::List(list)) => match name_values(&list.nested).get("function") {
+                Some(Lit::Str(s)) => Self::Custom(s.value()),
+                _ => Self::Other(quote_meta(meta)),
+            },
+            _ => Self::Other(quote_meta(meta)),
+        }
+    }
+}
+
+/// Comma separated list of metas as they appear inside `#[validate(..)]`
+struct MetaList(Vec<Meta>);
+
+impl syn::parse::Parse for MetaList {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let list = input.parse_terminated::<Meta, syn::Token![,]>(Meta::parse)?;
+        Ok(Self(list.into_iter().collect()))
+    }
+}
+
+fn name_values(
+    nested: &syn::punctuated::Punctuated<NestedMeta, syn::Token![,]>,
+) -> HashMap<String, Lit> {
+    nested
+        .iter()
+        .filter_map(|n| match n {
+            NestedMeta::Meta(Meta::NameValue(nv)) => nv
+                .path
+                .get_ident()
+                .map(|id| (id.to_string(), nv.lit.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn lit_u64(lit: &Lit) -> Option<u64> {
+    match lit {
+        Lit::Int(i) => i.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+fn lit_f64(lit: &Lit) -> Option<f64> {
+    match lit {
+        Lit::Int(i) => i.base10_parse().ok(),
+        Lit::Float(f) => f.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+fn quote_meta(meta: &Meta) -> String {
+    quote::ToTokens::to_token_stream(meta).to_string()
+}
+
+/// Searches all the rust files in the given directory for `static ref NAME: Regex = .. Regex::new("..")`
+/// declarations and maps the static names to their patterns. Used to turn `regex = "EMAIL_REGEX"` in to
+/// an actual pattern.
+pub fn resolve_regexes(dir: &Path) -> HashMap<String, String> {
+    let mut regexes = HashMap::new();
+    resolve_regexes_recursive(dir, &mut regexes);
+    regexes
+}
+
+fn resolve_regexes_recursive(dir: &Path, regexes: &mut HashMap<String, String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            resolve_regexes_recursive(&path, regexes);
+            continue;
+        }
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let Ok(src) = fs::read_to_string(&path) else {
+            continue;
+        };
+        // lazy_static bodies aren't parsed by syn so we go through the raw tokens
+        if let Ok(tokens) = src.parse::<TokenStream>() {
+            scan_regex_tokens(tokens, regexes);
+        }
+    }
+}
+
+fn scan_regex_tokens(tokens: TokenStream, regexes: &mut HashMap<String, String>) {
+    let tokens = tokens.into_iter().collect::<Vec<TokenTree>>();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TokenTree::Group(g) => scan_regex_tokens(g.stream(), regexes),
+            TokenTree::Ident(id) if id == "ref" => {
+                let Some(TokenTree::Ident(name)) = tokens.get(i + 1) else {
+                    continue;
+                };
+                // Skip to the type and make sure it's a Regex
+                if !matches!(tokens.get(i + 3), Some(TokenTree::Ident(ty)) if ty == "Regex") {
+                    continue;
+                }
+                // The pattern is the first string literal in the `Regex::new()` call following the name
+                let decl = tokens[i + 1..]
+                    .iter()
+                    .take_while(|t| !matches!(t, TokenTree::Punct(p) if p.as_char() == ';'))
+                    .cloned()
+                    .collect::<TokenStream>();
+                if let Some(pattern) = regex_new_literal(decl) {
+                    regexes.insert(name.to_string(), pattern);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn regex_new_literal(tokens: TokenStream) -> Option<String> {
+    let tokens = tokens.into_iter().collect::<Vec<TokenTree>>();
+    for (i, token) in tokens.iter().enumerate() {
+        match (token, tokens.get(i + 1)) {
+            (TokenTree::Ident(id), Some(TokenTree::Group(args))) if id == "new" => {
+                return args
+                    .stream()
+                    .into_iter()
+                    .find_map(|t| first_str_literal(&t));
+            }
+            (TokenTree::Group(g), _) => {
+                if let Some(pattern) = regex_new_literal(g.stream()) {
+                    return Some(pattern);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn first_str_literal(token: &TokenTree) -> Option<String> {
+    match token {
+        TokenTree::Literal(lit) => match syn::parse_str::<syn::LitStr>(&lit.to_string()) {
+            Ok(s) => Some(s.value()),
+            Err(_) => None,
+        },
+        TokenTree::Group(g) => g.stream().into_iter().find_map(|t| first_str_literal(&t)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_validator_rules() {
+        assert_eq!(
+            Validation::parse("length (min = 1)"),
+            vec![Validation::Length {
+                min: Some(1),
+                max: None,
+                equal: None
+            }]
+        );
+        assert_eq!(
+            Validation::parse("range (min = 1, max = 65_535)"),
+            vec![Validation::Range {
+                min: Some(1.),
+                max: Some(65535.)
+            }]
+        );
+        assert_eq!(
+            Validation::parse("regex = \"EMAIL_REGEX\""),
+            vec![Validation::Regex("EMAIL_REGEX".to_string())]
+        );
+        assert_eq!(
+            Validation::parse("email, custom = \"check\""),
+            vec![Validation::Email, Validation::Custom("check".to_string())]
+        );
+    }
+
+    #[test]
+    fn resolves_lazy_static_regexes() {
+        let src = r#"lazy_static! {
+            pub static ref PHONE_REGEX: Regex = {
+                trace!("Loading PHONE regex");
+                Regex::new(r"^(00|\+)$").unwrap()
+            };
+            static ref NOT_A_REGEX: String = String::from("nope");
+        }"#;
+        let mut regexes = HashMap::new();
+        scan_regex_tokens(src.parse().unwrap(), &mut regexes);
+        assert_eq!(regexes.len(), 1);
+        // The trace message is a string literal as well but isn't inside the `Regex::new` call
+        assert_eq!(regexes["PHONE_REGEX"], r"^(00|\+)$");
+    }
+}